@@ -17,3 +17,4 @@ pub const KEYCLOAK_SCOPE: &str = "openid profile email";
 // Backend GraphQL endpoints (must match your backend configuration)
 pub const GRAPHQL_HTTP_ENDPOINT: &str = "http://localhost:8080/graphql";
 pub const GRAPHQL_WS_ENDPOINT: &str = "ws://localhost:8080/subscriptions";
+pub const EXPORT_ENDPOINT: &str = "http://localhost:8080/export";
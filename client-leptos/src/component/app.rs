@@ -1,4 +1,4 @@
-use crate::component::{Banner, EventTable, Footer, GlobalState, NavigationBar};
+use crate::component::{Banner, ErrorMessage, EventTable, Footer, GlobalState, NavigationBar};
 use leptos::prelude::*;
 use reactive_stores::Store;
 use crate::component::keycloak_catcher::GlobalStateStoreFields;
@@ -12,6 +12,7 @@ pub fn App() -> impl IntoView {
         <div class="ml-20 mr-20">
             <Banner/>
             <hr/>
+            <ErrorMessage/>
             <Show
                 when=move || state.is_authenticated().get()
                 fallback=|| view! { <p> Please Login </p> }
@@ -1,10 +1,9 @@
 use crate::component::GlobalState;
-use crate::graphql::ClockSubscriptionResponse;
-use futures::{SinkExt, StreamExt};
+use crate::graphql::{subscribe, ClockSubscriptionResponse};
+use futures::StreamExt;
 use leptos::logging::log;
 use leptos::prelude::*;
 use reactive_stores::Store;
-use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use ws_stream_wasm::*;
 use crate::component::keycloak_catcher::GlobalStateStoreFields;
@@ -26,45 +25,15 @@ pub fn ClockComponent() -> impl IntoView {
             log!("[GraphQL Subscription] Using unauthenticated connection");
         }
 
-        // Create a WebSocket connection
-        // async-graphql uses the `graphql-transport-ws` subprotocol for subscriptions
-        let ws_url = crate::auth_config::GRAPHQL_WS_ENDPOINT;
-        let (_ws, mut wsio) = match WsMeta::connect(ws_url, Some(vec!["graphql-transport-ws"])) .await {
-            Ok(ws) => ws,
+        let query = "subscription tt {\n  clock {\n    clock\n  }\n}";
+        let (_ws, mut wsio, id) = match subscribe(query, token).await {
+            Ok(subscription) => subscription,
             Err(e) => {
-                log!("WS connect error: {e}");
-                write_clock.set(format!("WS connect error: {e}"));
+                log!("{e}");
+                write_clock.set(e);
                 return;
             }
         };
-
-        // Init connection with optional authorization token
-        let payload = if let Some(token) = token {
-            serde_json::json!({
-                "Authorization": format!("Bearer {}", token)
-            })
-        } else {
-            serde_json::json!({})
-        };
-
-        let conn_init = serde_json::to_string(&serde_json::json!({
-            "type": "connection_init",
-            "payload": payload
-        })).unwrap();
-
-        log!("[GraphQL Subscription] Connection init: {}", conn_init);
-        wsio.send(WsMessage::Text(conn_init)).await.ok();
-        wsio.next().await;
-
-        // Send start subscription
-        let id = Uuid::new_v4().to_string();
-        let start_msg = serde_json::to_string(&serde_json::json!({
-            "id":id,
-            "type":"subscribe",
-            "payload":{"query":"subscription tt {\n  clock {\n    clock\n  }\n}",
-            "operationName":"tt"}
-            })).unwrap();
-        wsio.send(WsMessage::Text(start_msg)).await.ok();
         write_clock.set("demo".to_string());
 
         // Listen for messages
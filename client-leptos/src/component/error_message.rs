@@ -0,0 +1,23 @@
+use crate::component::GlobalState;
+use crate::component::keycloak_catcher::GlobalStateStoreFields;
+use leptos::prelude::*;
+use reactive_stores::{Patch, Store};
+
+/// Shows the last server error until the user dismisses it
+#[component]
+pub fn ErrorMessage() -> impl IntoView {
+    let state = expect_context::<Store<GlobalState>>();
+
+    let dismiss = move |_| {
+        state.error().patch(None);
+    };
+
+    view! {
+        <Show when=move || state.error().get().is_some()>
+            <div class="flex flex-row items-center justify-between mt-2 mb-2 px-4 py-3 rounded-lg border border-red-400 bg-red-100 text-red-700" role="alert">
+                <span>{move || state.error().get().unwrap_or_default()}</span>
+                <button class="font-bold ml-4" on:click=dismiss>"✕"</button>
+            </div>
+        </Show>
+    }
+}
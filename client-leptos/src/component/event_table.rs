@@ -1,15 +1,23 @@
-use crate::component::{KeycloakAccessAdmin, EventTableDelete, EventTableEdit, EventTableModal, GlobalState};
+use crate::component::{save_file, KeycloakAccessAdmin, EventTableDelete, EventTableEdit, EventTableExport, EventTableModal, EventTableRefresh, EventTableRestore, GlobalState};
 use crate::graphql::{delete_meetup_url_by_uuid_id,
+                     export_meetup_urls,
                      fetch_meetup_url_data,
                      insert_meetup_event,
+                     refresh_meetup_url_metadata,
+                     restore_meetup_url_by_uuid_id,
+                     subscribe,
                      update_meetup_event,
+                     MeetupUrlChangedResponse,
 };
-use crate::model::{Event, Filter, MeetupUrlEdit};
+use futures::future::abortable;
+use futures::{FutureExt, StreamExt};
+use crate::model::{cycle_sort, day_start, sort_marker, Event, ExportFormat, Filter, MeetupUrlEdit, PageInfo, SortField, SortOrder};
 use leptos::logging::log;
 use leptos::prelude::*;
-use reactive_stores::Store;
+use reactive_stores::{Patch, Store};
 use thaw::*;
 use crate::component::keycloak_catcher::GlobalStateStoreFields;
+use ws_stream_wasm::WsMessage;
 
 #[component]
 pub fn EventTable() -> impl IntoView {
@@ -22,10 +30,19 @@ pub fn EventTable() -> impl IntoView {
     let filter_title = RwSignal::new(String::from(""));
     let filter_url = RwSignal::new(String::from(""));
     let filter_description = RwSignal::new(String::from(""));
+    // Dates of the date inputs, `yyyy-mm-dd`
+    let filter_created_after = RwSignal::new(String::from(""));
+    let filter_created_before = RwSignal::new(String::from(""));
+    let filter_modified_after = RwSignal::new(String::from(""));
+    let filter_modified_before = RwSignal::new(String::from(""));
     let old_db_trigger = RwSignal::new(String::from(""));
+    let filter_tags = RwSignal::new(Vec::<String>::new());
+    let sort = RwSignal::new(Vec::<SortOrder>::new());
+    // Admins switch between the live rows and the trash
+    let trash = RwSignal::new(false);
 
     let (filter, set_filter) = signal(Filter { page: Some(1), size: Some(10), ..Default::default() });
-    let fetch_urls = LocalResource::new(move || load_data(filter.get(), state.token().get()));
+    let fetch_urls = LocalResource::new(move || load_data(filter.get(), state.token().get(), state));
 
     let fire_refresh = move || {
         let mut new_filter = Filter::default();
@@ -41,6 +58,13 @@ pub fn EventTable() -> impl IntoView {
         if filter_description.get() != "" {
             new_filter.description = Some(filter_description.get());
         };
+        new_filter.created_after = day_start(&filter_created_after.get());
+        new_filter.created_before = day_start(&filter_created_before.get());
+        new_filter.modified_after = day_start(&filter_modified_after.get());
+        new_filter.modified_before = day_start(&filter_modified_before.get());
+        new_filter.tags = filter_tags.get();
+        new_filter.sort = sort.get();
+        new_filter.trash = trash.get();
         if max_size.get() != "ALL" {
             new_filter.page = Some((page.get() - 1) as i64);
             new_filter.size = Some(max_size.get().parse::<i64>().unwrap());
@@ -56,6 +80,48 @@ pub fn EventTable() -> impl IntoView {
         }
     });
 
+    // Refresh on changes made by anyone, the subscription needs a logged-in user.
+    // Aborting the listener drops its socket, before a new token opens the
+    // next one and when the table goes away.
+    Effect::new(move |_| {
+        if let Some(token) = state.token().get() {
+            let (listening, listener) = abortable(listen_for_changes(token, fire_refresh));
+            leptos::task::spawn_local(async move {
+                let _ = listening.await;
+            });
+            on_cleanup(move || listener.abort());
+        }
+    });
+
+    // A new filter, sort or page size starts again on the first page
+    let apply_filter = move || {
+        page.set(1);
+        fire_refresh();
+    };
+
+    let sort_by = move |field: SortField| {
+        sort.update(|s| cycle_sort(s, field));
+        apply_filter();
+    };
+
+    // Clicking a tag narrows the list to it, clicking it in the header widens it again
+    let add_tag = move |tag: String| {
+        if !filter_tags.get().contains(&tag) {
+            filter_tags.update(|tags| tags.push(tag));
+            apply_filter();
+        }
+    };
+
+    let remove_tag = move |tag: String| {
+        filter_tags.update(|tags| tags.retain(|t| *t != tag));
+        apply_filter();
+    };
+
+    let toggle_trash = move |_e| {
+        trash.update(|t| *t = !*t);
+        apply_filter();
+    };
+
     let add_item = move |_e| {
         meetup_url_select.set(MeetupUrlEdit::default());
         show_modal.set(true);
@@ -65,9 +131,10 @@ pub fn EventTable() -> impl IntoView {
         let edit = MeetupUrlEdit {
             uri_uuid: Some(item.id),
             title: Some(item.title),
-            domain: Some(item.domain),
             url: Some(item.url),
-            description: Some(item.description),
+            auto_descr: Some(item.auto_descr),
+            man_descr: Some(item.man_descr),
+            modi_time: Some(item.modi_time),
         };
         meetup_url_select.set(edit);
         show_modal.set(true);
@@ -76,33 +143,63 @@ pub fn EventTable() -> impl IntoView {
     let delete_item = move |item: Event| {
         let token = state.token().get();
         leptos::task::spawn_local(async move {
-            delete_meetup_url_by_uuid_id(item.id, token).await;
+            if let Err(e) = delete_meetup_url_by_uuid_id(item.id, token).await {
+                state.error().patch(Some(e));
+            }
             fire_refresh();
         });
     };
 
-    fn get_pages(page_size: RwSignal<String>, items: i64) -> impl Into<Signal<usize>> + Sized {
-        if page_size.get() == "ALL" {
-            0
-        } else {
-            let ps = page_size.get().parse::<i64>().unwrap();
-            items.div_ceil(ps) as usize
-        }
-    }
+    let restore_item = move |item: Event| {
+        let token = state.token().get();
+        leptos::task::spawn_local(async move {
+            if let Err(e) = restore_meetup_url_by_uuid_id(item.id, token).await {
+                state.error().patch(Some(e));
+            }
+            fire_refresh();
+        });
+    };
+
+    let refresh_item = move |item: Event| {
+        let token = state.token().get();
+        leptos::task::spawn_local(async move {
+            if let Err(e) = refresh_meetup_url_metadata(item.id, token).await {
+                state.error().patch(Some(e));
+            }
+            fire_refresh();
+        });
+    };
+
+    // All pages of the filter and sort on screen
+    let export_view = move |format: ExportFormat| {
+        let token = state.token().get();
+        let filter = filter.get();
+        leptos::task::spawn_local(async move {
+            let saved = export_meetup_urls(filter, format, token).await
+                .and_then(|content| save_file(format.file_name(), format.content_type(), &content));
+            if let Err(e) = saved {
+                state.error().patch(Some(e));
+            }
+        });
+    };
 
     let close_modal = move |item: MeetupUrlEdit| {
         let token = state.token().get();
         if item.uri_uuid.is_none() {
             log!("INSERT {:?}", item);
             leptos::task::spawn_local(async move {
-                insert_meetup_event(item, token).await;
+                if let Err(e) = insert_meetup_event(item, token).await {
+                    state.error().patch(Some(e));
+                }
                 fire_refresh();
             });
         } else {
             log!("UPDATE {:?}", item);
             let token_clone = token.clone();
             leptos::task::spawn_local(async move {
-                update_meetup_event(item, token_clone).await;
+                if let Err(e) = update_meetup_event(item, token_clone).await {
+                    state.error().patch(Some(e));
+                }
                 fire_refresh();
             });
         }
@@ -112,50 +209,143 @@ pub fn EventTable() -> impl IntoView {
         show_modal.set(false);
     };
 
-    async fn load_data(filter: Filter, token: Option<String>) -> (Vec<Event>, i64) {
-        fetch_meetup_url_data(filter, token).await
+    async fn load_data(filter: Filter, token: Option<String>, state: Store<GlobalState>) -> (Vec<Event>, PageInfo) {
+        match fetch_meetup_url_data(filter, token).await {
+            Ok(data) => data,
+            Err(e) => {
+                state.error().patch(Some(e));
+                (Vec::new(), PageInfo::default())
+            }
+        }
+    }
+
+    async fn listen_for_changes(token: String, refresh: impl Fn() + 'static) {
+        let query = "subscription { meetupUrlChanged { kind meetupUrl { uriUuid } } }";
+        let (_ws, mut wsio, id) = match subscribe(query, Some(token)).await {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                log!("[EventTable] meetupUrlChanged subscription failed: {e}");
+                return;
+            }
+        };
+
+        while let Some(msg) = wsio.next().await {
+            let mut changed = is_change(&msg, &id);
+
+            // Coalesce bursts, e.g. an init database, into one refresh
+            while let Some(Some(msg)) = wsio.next().now_or_never() {
+                changed |= is_change(&msg, &id);
+            }
+
+            if changed {
+                refresh();
+            }
+        }
+    }
+
+    fn is_change(msg: &WsMessage, id: &str) -> bool {
+        let WsMessage::Text(data) = msg else {
+            return false;
+        };
+        match serde_json::from_str::<MeetupUrlChangedResponse>(data) {
+            Ok(v) => v.type_field == "next" && v.id == id,
+            Err(_) => false,
+        }
     }
 
     view! {
           <div class="w-full mt-2 mb-2">
               <Suspense fallback=move || view! { <p>"Loading..."</p> }>
                 {move || Suspend::new(async move {
-                let (urls, count) = fetch_urls.await;
+                let (urls, page_info) = fetch_urls.await;
 
                 view! {
-                  <p> Count: <strong>{count}</strong> item(s) selected</p>
+                  <p> Count: <strong>{page_info.total}</strong> item(s) selected</p>
                   <Table class="w-full table-auto">
                       <TableHeader>
                         <TableRow>
                           <TableHeaderCell>
                             <div class="grid grid-flow-col grid-rows-2">
-                                <div>Domain</div>
+                                <div class="cursor-pointer select-none" on:click=move |_| sort_by(SortField::Domain)>
+                                    "Domain" {move || sort_marker(&sort.get(), SortField::Domain)}
+                                </div>
                                 <div class="border ml-1 mr-1"
-                                    ><Input value=filter_domain on:change = move |_event| {fire_refresh();}/>
+                                    ><Input value=filter_domain on:change = move |_event| {apply_filter();}/>
                                 </div>
                             </div>
                           </TableHeaderCell>
                           <TableHeaderCell>
                             <div class="grid grid-flow-col grid-rows-2">
-                                <div>Title</div>
+                                <div class="cursor-pointer select-none" on:click=move |_| sort_by(SortField::Title)>
+                                    "Title" {move || sort_marker(&sort.get(), SortField::Title)}
+                                </div>
                                 <div class="border ml-1 mr-1">
-                                    <Input value=filter_title on:change = move |_event| {fire_refresh();}/>
+                                    <Input value=filter_title on:change = move |_event| {apply_filter();}/>
                                 </div>
                             </div>
                           </TableHeaderCell>
                           <TableHeaderCell>
                             <div class="grid grid-flow-col grid-rows-2">
-                                <div>URL</div>
+                                <div class="cursor-pointer select-none" on:click=move |_| sort_by(SortField::Url)>
+                                    "URL" {move || sort_marker(&sort.get(), SortField::Url)}
+                                </div>
                                 <div class="border ml-1 mr-1">
-                                    <Input value=filter_url on:change = move |_event| {fire_refresh();}/>
+                                    <Input value=filter_url on:change = move |_event| {apply_filter();}/>
                                 </div>
                             </div>
                           </TableHeaderCell>
                           <TableHeaderCell>
                             <div class="grid grid-flow-col grid-rows-2">
-                                <div>Description</div>
+                                <div class="cursor-pointer select-none" on:click=move |_| sort_by(SortField::Description)>
+                                    "Description" {move || sort_marker(&sort.get(), SortField::Description)}
+                                </div>
                                 <div class="border ml-1 mr-1">
-                                    <Input value=filter_description on:change = move |_event| {fire_refresh();}/>
+                                    <Input value=filter_description on:change = move |_event| {apply_filter();}/>
+                                </div>
+                            </div>
+                          </TableHeaderCell>
+                          <TableHeaderCell>
+                            <div class="grid grid-flow-col grid-rows-2">
+                                <div class="cursor-pointer select-none" on:click=move |_| sort_by(SortField::Created)>
+                                    "Created" {move || sort_marker(&sort.get(), SortField::Created)}
+                                </div>
+                                <div class="flex flex-col ml-1 mr-1">
+                                    <input type="date" title="From" bind:value=filter_created_after on:change = move |_event| {apply_filter();}/>
+                                    <input type="date" title="Before" bind:value=filter_created_before on:change = move |_event| {apply_filter();}/>
+                                </div>
+                            </div>
+                          </TableHeaderCell>
+                          <TableHeaderCell>
+                            <div class="grid grid-flow-col grid-rows-2">
+                                <div class="cursor-pointer select-none" on:click=move |_| sort_by(SortField::Modified)>
+                                    "Modified" {move || sort_marker(&sort.get(), SortField::Modified)}
+                                </div>
+                                <div class="flex flex-col ml-1 mr-1">
+                                    <input type="date" title="From" bind:value=filter_modified_after on:change = move |_event| {apply_filter();}/>
+                                    <input type="date" title="Before" bind:value=filter_modified_before on:change = move |_event| {apply_filter();}/>
+                                </div>
+                            </div>
+                          </TableHeaderCell>
+                          <TableHeaderCell>
+                            <div class="grid grid-flow-col grid-rows-2">
+                                <div>"Tags"</div>
+                                <div class="flex flex-wrap gap-1">
+                                    <For
+                                        each=move || filter_tags.get()
+                                        key=|tag| tag.clone()
+                                        let:tag
+                                    >
+                                        <span
+                                            class="cursor-pointer select-none rounded-full bg-blue-600 text-white text-sm px-2"
+                                            title="Remove from filter"
+                                            on:click={
+                                                let tag = tag.clone();
+                                                move |_| remove_tag(tag.clone())
+                                            }
+                                        >
+                                            {tag.clone()} " ×"
+                                        </span>
+                                    </For>
                                 </div>
                             </div>
                           </TableHeaderCell>
@@ -163,7 +353,12 @@ pub fn EventTable() -> impl IntoView {
                             <div class="relative h-24 w-full">
                                 <div class="absolute bottom-4 left-1/2 transform -translate-x-1/2">
                                 <KeycloakAccessAdmin>
-                                    <Button appearance=ButtonAppearance::Primary on_click=add_item>"ADD ENTRY"</Button>
+                                    <Show when=move || !trash.get()>
+                                        <Button appearance=ButtonAppearance::Primary on_click=add_item>"ADD ENTRY"</Button>
+                                    </Show>
+                                    <Button appearance=ButtonAppearance::Secondary on_click=toggle_trash>
+                                        {move || if trash.get() { "BACK TO LIST" } else { "TRASH" }}
+                                    </Button>
                                 </KeycloakAccessAdmin>
                                 </div>
                             </div>
@@ -178,23 +373,64 @@ pub fn EventTable() -> impl IntoView {
                         >
                         {
                             let domain = event.domain.clone();
+                            let favicon = event.favicon.clone();
                             let title = event.title.clone();
                             let url = event.url.clone();
                             let description = event.description.clone();
+                            let created = event.crea_time.get(..10).unwrap_or_default().to_string();
+                            let modified = event.modi_time.get(..10).unwrap_or_default().to_string();
+                            let tags = event.tags.clone();
+                            let deleted_by = event.deleted_by.clone();
+                            let in_trash = deleted_by.is_some();
                             view!{
                                 <TableRow>
-                                    <TableCell>{{domain}}</TableCell>
+                                    <TableCell>
+                                        <div class="flex items-center gap-2">
+                                            {favicon.map(|favicon| view! { <img class="w-4 h-4" src=favicon alt=""/> })}
+                                            {domain}
+                                        </div>
+                                    </TableCell>
                                     <TableCell>{{title}}</TableCell>
                                     <TableCell><Link href=url.clone()>{{url}}</Link></TableCell>
                                     <TableCell>
                                         <Textarea size=TextareaSize::Large resize=TextareaResize::Vertical value=description/>
                                     </TableCell>
+                                    <TableCell>{created}</TableCell>
+                                    <TableCell>{modified}</TableCell>
+                                    <TableCell>
+                                        <div class="flex flex-wrap gap-1">
+                                            {tags.into_iter().map(|(name, count)| view! {
+                                                <span
+                                                    class="cursor-pointer select-none rounded-full bg-gray-200 text-gray-800 text-sm px-2"
+                                                    title="Filter by this tag"
+                                                    on:click={
+                                                        let name = name.clone();
+                                                        move |_| add_tag(name.clone())
+                                                    }
+                                                >
+                                                    {name.clone()} " (" {count} ")"
+                                                </span>
+                                            }).collect_view()}
+                                        </div>
+                                    </TableCell>
                                     <TableCell>
                                             <div class="basis-1/12 flex items-center justify-center">
                                                <div class="flex flex-row-reverse space-x-4 space-x-reverse">
                                                     <KeycloakAccessAdmin>
-                                                    <EventTableEdit event={event.clone()} on_click=edit_item></EventTableEdit>
-                                                    <EventTableDelete event={event.clone()} on_click=delete_item></EventTableDelete>
+                                                    <Show
+                                                        when=move || in_trash
+                                                        fallback={
+                                                            let event = event.clone();
+                                                            move || view! {
+                                                                <EventTableEdit event={event.clone()} on_click=edit_item></EventTableEdit>
+                                                                <EventTableRefresh event={event.clone()} on_click=refresh_item></EventTableRefresh>
+                                                                <EventTableDelete event={event.clone()} on_click=delete_item></EventTableDelete>
+                                                            }
+                                                        }
+                                                    >
+                                                        <EventTableRestore event={event.clone()} on_click=restore_item></EventTableRestore>
+                                                        <span class="text-sm">"deleted by " {deleted_by.clone()}</span>
+                                                    </Show>
                                                     </KeycloakAccessAdmin>
                                                </div>
                                             </div>
@@ -206,12 +442,13 @@ pub fn EventTable() -> impl IntoView {
                       </TableBody>
                       <tfoot>
                         <Flex>
-                        <Pagination page page_count=get_pages(max_size, count) on:click = move |_event| {fire_refresh();} />
-                        <Select value=max_size on:change = move |_event| {fire_refresh();} >
+                        <Pagination page page_count={page_info.total_pages as usize} on:click = move |_event| {fire_refresh();} />
+                        <Select value=max_size on:change = move |_event| {apply_filter();} >
                             <option>10</option>
                             <option>50</option>
                             <option>ALL</option>
                         </Select>
+                        <EventTableExport on_export=export_view/>
                         </Flex>
                       </tfoot>
                   </Table>
@@ -0,0 +1,52 @@
+use crate::model::ExportFormat;
+use leptos::prelude::*;
+use std::time::Duration;
+use thaw::*;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+/// Format choice and button exporting the rows of the current filter
+#[component]
+pub fn EventTableExport<F>(on_export: F) -> impl IntoView
+where
+    F: Fn(ExportFormat) + 'static + Copy,
+{
+    let format = RwSignal::new(ExportFormat::Csv.name().to_string());
+
+    let export = move |_| {
+        if let Some(format) = ExportFormat::from_name(&format.get()) {
+            on_export(format);
+        }
+    };
+
+    view! {
+        <Flex>
+            <Select value=format>
+                {ExportFormat::ALL.into_iter().map(|format| view! {
+                    <option value=format.name()>{format.label()}</option>
+                }).collect_view()}
+            </Select>
+            <Button appearance=ButtonAppearance::Secondary on_click=export>"EXPORT CURRENT VIEW"</Button>
+        </Flex>
+    }
+}
+
+/// Hands `content` to the browser as a download named `file_name`
+pub fn save_file(file_name: &str, content_type: &str, content: &str) -> Result<(), String> {
+    let failed = |e: JsValue| format!("Cannot save {}: {:?}", file_name, e);
+
+    let options = BlobPropertyBag::new();
+    options.set_type(content_type);
+    let parts = js_sys::Array::of1(&JsValue::from_str(content));
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options).map_err(failed)?;
+    let href = Url::create_object_url_with_blob(&blob).map_err(failed)?;
+
+    let link = document().create_element("a").map_err(failed)?.unchecked_into::<HtmlAnchorElement>();
+    link.set_href(&href);
+    link.set_download(file_name);
+    link.click();
+
+    // Revoked once the browser took the download over
+    set_timeout(move || { let _ = Url::revoke_object_url(&href); }, Duration::from_secs(10));
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use crate::model::MeetupUrlEdit;
-use leptos::html::Input;
+use leptos::html::{Input, Textarea};
 use leptos::prelude::*;
 
 #[component]
@@ -17,12 +17,12 @@ where
     let (title, _set_title) = signal(meetup_url.get().title);
     let title_node: NodeRef<Input> = NodeRef::new();
 
-    let (domain, _set_domain) = signal(meetup_url.get().domain);
-    let domain_node: NodeRef<Input> = NodeRef::new();
-
-    let (description, _set_description) = signal(meetup_url.get().description);
+    let (description, _set_description) = signal(meetup_url.get().auto_descr);
     let description_node: NodeRef<Input> = NodeRef::new();
 
+    let (man_descr, _set_man_descr) = signal(meetup_url.get().man_descr.unwrap_or_default());
+    let man_descr_node: NodeRef<Textarea> = NodeRef::new();
+
     let (url, _set_url) = signal(meetup_url.get().url);
     let url_node: NodeRef<Input> = NodeRef::new();
 
@@ -37,22 +37,22 @@ where
             .expect("<description> should be mounted")
             .value();
 
-        let url = url_node
+        let man_descr = man_descr_node
             .get()
-            .expect("<url> should be mounted")
+            .expect("<man_descr> should be mounted")
             .value();
 
-        let domain = domain_node
+        let url = url_node
             .get()
-            .expect("<domain> should be mounted")
+            .expect("<url> should be mounted")
             .value();
 
         let mut rtn = meetup_url.get();
 
         rtn.title = Some(title);
-        rtn.description = Some(description);
+        rtn.auto_descr = Some(description);
+        rtn.man_descr = Some(man_descr);
         rtn.url = Some(url);
-        rtn.domain = Some(domain);
 
         if is_update {
             rtn.uri_uuid = meetup_url.get().uri_uuid;
@@ -95,6 +95,17 @@ where
                         type="text"
                         value=description
                         placeholder="Description"/>
+
+                    <label class="block text-gray-700 text-sm font-bold mb-2 mt-2" for="man_descr">
+                        Curator description
+                    </label>
+                    <textarea
+                        node_ref=man_descr_node
+                        class=input_field_class
+                        id="man_descr"
+                        rows="4"
+                        prop:value=man_descr
+                        placeholder="Shown instead of the description when set"></textarea>
                         
                     <label class="block text-gray-700 text-sm font-bold mb-2 mt-2" for="url">
                         URL
@@ -106,17 +117,6 @@ where
                         type="text"
                         value=url
                         placeholder="Url"/>
-        
-                    <label class="block text-gray-700 text-sm font-bold mb-2 mt-2" for="domain">
-                        Domain
-                    </label>
-                    <input
-                        node_ref=domain_node
-                        class=input_field_class
-                        id="domain"
-                        type="text"
-                        value=domain
-                        placeholder="Domain"/>        
                 </div>               
 
                 <div class="flex flex-row-reverse space-x-4 space-x-reverse">
@@ -0,0 +1,24 @@
+use crate::model::Event;
+use leptos::prelude::*;
+
+#[component]
+pub fn EventTableRefresh<F>(#[prop(into)] event: Event, on_click: F) -> impl IntoView
+where
+    F: Fn(Event) + 'static + Copy,
+{
+    let button_refresh_class = "text-white bg-teal-700 hover:bg-teal-800 focus:ring-4 focus:outline-none focus:ring-teal-300 font-medium rounded-full text-sm p-2.5 text-center inline-flex items-center mr-2";
+    let event: RwSignal<Event> = RwSignal::new(event);
+
+    let refresh = move |_| {
+        on_click(event.get());
+    };
+
+    view! {
+        <button
+            class=button_refresh_class
+            title="Read title and description from the page"
+            on:click=refresh>
+            <i class="fa-solid fa-wand-magic-sparkles"></i>
+        </button>
+    }
+}
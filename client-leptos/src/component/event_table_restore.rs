@@ -0,0 +1,23 @@
+use crate::model::Event;
+use leptos::prelude::*;
+
+#[component]
+pub fn EventTableRestore<F>(#[prop(into)] event: Event, on_click: F) -> impl IntoView
+where
+    F: Fn(Event) + 'static + Copy,
+{
+    let button_restore_class = "text-white bg-green-700 hover:bg-green-800 focus:ring-4 focus:outline-none focus:ring-green-300 font-medium rounded-full text-sm p-2.5 text-center inline-flex items-center mr-2";
+    let event: RwSignal<Event> = RwSignal::new(event);
+
+    let restore = move |_| {
+        on_click(event.get());
+    };
+
+    view! {
+        <button
+            class=button_restore_class
+            on:click=restore>
+            <i class="fa-solid fa-rotate-left"></i>
+        </button>
+    }
+}
@@ -12,7 +12,8 @@ pub struct GlobalState {
     pub name: Option<String>,
     pub roles: Vec<String>,
     pub is_authenticated: bool,
-    pub refresh_table: String
+    pub refresh_table: String,
+    pub error: Option<String>,
 }
 
 #[component]
@@ -7,7 +7,11 @@ mod event_table;
 mod clock_component;
 mod event_table_delete;
 mod event_table_edit;
+mod event_table_export;
 mod event_table_modal;
+mod event_table_refresh;
+mod event_table_restore;
+mod error_message;
 
 mod keycloak_catcher;
 mod keycloak_access_admin;
@@ -21,7 +25,11 @@ pub use clock_component::ClockComponent;
 pub use event_table::EventTable;
 pub use event_table_delete::EventTableDelete;
 pub use event_table_edit::EventTableEdit;
+pub use event_table_export::{save_file, EventTableExport};
 pub use event_table_modal::EventTableModal;
+pub use event_table_refresh::EventTableRefresh;
+pub use event_table_restore::EventTableRestore;
+pub use error_message::ErrorMessage;
 pub use footer::Footer;
 pub use navigation_bar::NavigationBar;
 pub use navigation_user_menu::NavigationUserMenu;
\ No newline at end of file
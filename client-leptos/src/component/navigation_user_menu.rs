@@ -5,7 +5,6 @@ use leptos::prelude::*;
 use reactive_stores::{Patch, Store};
 use leptos::logging::log;
 use thaw::{Button, ButtonAppearance};
-use uuid::Uuid;
 use crate::component::keycloak_catcher::GlobalStateStoreFields;
 use crate::graphql::{init_database};
 use crate::component::KeycloakAccessAdmin;
@@ -172,10 +171,11 @@ pub fn InitDatabaseButton(dropdown_open: RwSignal<bool>) -> impl IntoView {
     let init_database = store_value(move |_| {
         let token_clone = token.clone();
         leptos::task::spawn_local(async move {
-            init_database(token_clone).await;
+            if let Err(e) = init_database(token_clone).await {
+                state.error().patch(Some(e));
+            }
             dropdown_open.set(false);
         });
-        state.refresh_table().patch(Uuid::new_v4().to_string());
     });
 
     view! {
@@ -1,12 +1,20 @@
 use crate::graphql::meetup_url_graphql::meetup_url_insert_mutation::UpsertMeetupUrl as InsertMeetupUrl;
 use crate::graphql::meetup_url_graphql::meetup_url_query::MeetupUrlQueryMeetupUrlListResult;
-use crate::graphql::meetup_url_graphql::meetup_url_update_mutation::UpsertMeetupUrl as UpdateMeetupUrl;
+use crate::graphql::meetup_url_graphql::meetup_url_update_mutation::MeetupUrlPatch;
 use crate::model::Event;
+use crate::model::ExportFormat;
 use crate::model::Filter;
+use crate::model::SortField;
 use crate::model::MeetupUrlEdit;
+use crate::model::PageInfo;
 use graphql_client::GraphQLQuery;
 use leptos::logging::log;
 use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The server's `DateTime` scalar, RFC 3339
+type DateTime = String;
 
 #[derive(GraphQLQuery)]
 #[graphql(
@@ -22,6 +30,20 @@ pub struct MeetupUrlQuery;
 )]
 pub struct MeetupUrlDeleteMutation;
 
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/meetup_url.graphql",
+)]
+pub struct MeetupUrlRestoreMutation;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/meetup_url.graphql",
+)]
+pub struct MeetupUrlRefreshMutation;
+
 #[derive(GraphQLQuery)]
 #[graphql(
     schema_path = "graphql/schema.graphql",
@@ -33,6 +55,7 @@ pub struct MeetupUrlInsertMutation;
 #[graphql(
     schema_path = "graphql/schema.graphql",
     query_path = "graphql/meetup_url.graphql",
+    skip_serializing_none,
 )]
 pub struct MeetupUrlUpdateMutation;
 
@@ -43,7 +66,7 @@ pub struct MeetupUrlUpdateMutation;
 )]
 pub struct InitDatabaseMutation;
 
-use crate::auth_config::GRAPHQL_HTTP_ENDPOINT;
+use crate::auth_config::{EXPORT_ENDPOINT, GRAPHQL_HTTP_ENDPOINT};
 
 const ENDPOINT: &str = GRAPHQL_HTTP_ENDPOINT;
 
@@ -66,9 +89,38 @@ fn build_client_with_auth(token: Option<String>) -> Client {
         .unwrap()
 }
 
-pub async fn fetch_meetup_url_data(filter: Filter, token: Option<String>) -> (Vec<Event>, i64) {
-    let client = build_client_with_auth(token);
+/// Send a GraphQL request; transport and GraphQL errors become a message for the user
+async fn send<B: Serialize, D: DeserializeOwned>(client: &Client, request_body: &B) -> Result<D, String> {
+    let http_resp = client
+        .post(ENDPOINT)
+        .json(request_body)
+        .send()
+        .await
+        .map_err(|e| format!("Server not reachable: {}", e))?;
 
+    if !http_resp.status().is_success() {
+        return Err(format!("Server answered {}", http_resp.status()));
+    }
+
+    let response: graphql_client::Response<D> = http_resp
+        .json()
+        .await
+        .map_err(|e| format!("Invalid server response: {}", e))?;
+
+    if let Some(error) = response.errors.as_ref().and_then(|errors| errors.first()) {
+        let code = error.extensions.as_ref()
+            .and_then(|ext| ext.get("code"))
+            .and_then(|code| code.as_str())
+            .unwrap_or("ERROR");
+        log!("[GraphQL] {}: {}", code, error.message);
+        return Err(format!("{}: {}", code, error.message));
+    }
+
+    response.data.ok_or("Empty server response".to_string())
+}
+
+/// The filter as the server takes it, also for the export
+fn query_filter(filter: Filter) -> meetup_url_query::MeetupUrlFilter {
     let page = if filter.page.is_none() {
         None
     } else {
@@ -78,111 +130,172 @@ pub async fn fetch_meetup_url_data(filter: Filter, token: Option<String>) -> (Ve
         })
     };
 
-    let filter = meetup_url_query::MeetupUrlFilter {
+    let sort = filter.sort.iter()
+        .map(|order| meetup_url_query::MeetupUrlOrder {
+            field: match order.field {
+                SortField::Domain => meetup_url_query::MeetupUrlSort::DOMAIN,
+                SortField::Title => meetup_url_query::MeetupUrlSort::TITLE,
+                SortField::Url => meetup_url_query::MeetupUrlSort::URL,
+                SortField::Description => meetup_url_query::MeetupUrlSort::DESCRIPTION,
+                SortField::Created => meetup_url_query::MeetupUrlSort::CREATED,
+                SortField::Modified => meetup_url_query::MeetupUrlSort::MODIFIED,
+            },
+            direction: Some(if order.descending {
+                meetup_url_query::SortDirection::DESC
+            } else {
+                meetup_url_query::SortDirection::ASC
+            }),
+        })
+        .collect();
+
+    meetup_url_query::MeetupUrlFilter {
         domain: filter.domain,
         title: filter.title,
         url: filter.url,
         description: filter.description,
+        created_after: filter.created_after,
+        created_before: filter.created_before,
+        modified_after: filter.modified_after,
+        modified_before: filter.modified_before,
+        include_deleted: None,
+        only_deleted: filter.trash.then_some(true),
+        tags: (!filter.tags.is_empty()).then(|| meetup_url_query::TagFilter {
+            names: filter.tags,
+            mode: meetup_url_query::TagMatch::ALL,
+        }),
+        link_state: None,
         pagination: page,
-        sort: None,
-    };
+        sort: Some(sort),
+    }
+}
+
+pub async fn fetch_meetup_url_data(filter: Filter, token: Option<String>) -> Result<(Vec<Event>, PageInfo), String> {
+    let client = build_client_with_auth(token);
 
-    let variables = meetup_url_query::Variables { filter };
+    let variables = meetup_url_query::Variables { filter: query_filter(filter) };
 
-    // Build GraphQL request body and send via reqwest 0.12
     let request_body = MeetupUrlQuery::build_query(variables);
+    let data: meetup_url_query::ResponseData = send(&client, &request_body).await?;
+
+    let page = data.meetup_url_list.page;
+    Ok((
+        meetup_url_to_event(data.meetup_url_list.result),
+        PageInfo {
+            current: page.current,
+            size: page.size,
+            total: page.total,
+            total_pages: page.total_pages,
+            has_next: page.has_next,
+            has_previous: page.has_previous,
+        },
+    ))
+}
+
+/// The rows of the filter in `format`, all pages of them in the order of the filter
+pub async fn export_meetup_urls(filter: Filter, format: ExportFormat, token: Option<String>) -> Result<String, String> {
+    let client = build_client_with_auth(token);
+
+    let filter = serde_json::to_string(&query_filter(filter)).map_err(|e| e.to_string())?;
     let http_resp = client
-        .post(ENDPOINT)
-        .json(&request_body)
+        .get(EXPORT_ENDPOINT)
+        .query(&[("format", format.name()), ("filter", filter.as_str())])
         .send()
         .await
-        .expect("Failed to send GraphQL HTTP request");
-
-    let response: graphql_client::Response<meetup_url_query::ResponseData> = http_resp
-        .json()
-        .await
-        .expect("Failed to deserialize GraphQL response");
+        .map_err(|e| format!("Server not reachable: {}", e))?;
 
-    if let Some(data) = response.data {
-        (
-            meetup_url_to_event(data.meetup_url_list.result),
-            data.meetup_url_count.count as i64,
-        )
-    } else {
-        (Vec::new(), 0)
+    if !http_resp.status().is_success() {
+        let status = http_resp.status();
+        let reason = http_resp.text().await.unwrap_or_default();
+        return Err(format!("Export failed, server answered {}: {}", status, reason));
     }
+    http_resp.text().await.map_err(|e| format!("Export incomplete: {}", e))
 }
 
-pub async fn delete_meetup_url_by_uuid_id(uuid: String, token: Option<String>) {
+pub async fn delete_meetup_url_by_uuid_id(uuid: String, token: Option<String>) -> Result<(), String> {
     let client = build_client_with_auth(token);
 
     let variables = meetup_url_delete_mutation::Variables { id: uuid };
 
     let request_body = MeetupUrlDeleteMutation::build_query(variables);
-    let _http_resp = client
-        .post(ENDPOINT)
-        .json(&request_body)
-        .send()
-        .await
-        .expect("Failed to execute GraphQL delete mutation");
+    let _data: meetup_url_delete_mutation::ResponseData = send(&client, &request_body).await?;
+    Ok(())
+}
+
+pub async fn restore_meetup_url_by_uuid_id(uuid: String, token: Option<String>) -> Result<(), String> {
+    let client = build_client_with_auth(token);
+
+    let variables = meetup_url_restore_mutation::Variables { uri_uuid: uuid };
+
+    let request_body = MeetupUrlRestoreMutation::build_query(variables);
+    let _data: meetup_url_restore_mutation::ResponseData = send(&client, &request_body).await?;
+    Ok(())
 }
 
-pub async fn insert_meetup_event(item: MeetupUrlEdit, token: Option<String>) {
+/// Has the server read title, description and favicon from the page again
+pub async fn refresh_meetup_url_metadata(uuid: String, token: Option<String>) -> Result<(), String> {
+    let client = build_client_with_auth(token);
+
+    let variables = meetup_url_refresh_mutation::Variables { uri_uuid: uuid };
+
+    let request_body = MeetupUrlRefreshMutation::build_query(variables);
+    let _data: meetup_url_refresh_mutation::ResponseData = send(&client, &request_body).await?;
+    Ok(())
+}
+
+pub async fn insert_meetup_event(item: MeetupUrlEdit, token: Option<String>) -> Result<(), String> {
     let client = build_client_with_auth(token);
 
     let variables = meetup_url_insert_mutation::Variables {
         upsert_meetup_url: InsertMeetupUrl {
             uri_uuid: None,
             url: item.url.unwrap(),
-            host: item.domain.unwrap(),
+            host: None,
             title: item.title.unwrap(),
-            auto_descr: item.description.unwrap(),
+            auto_descr: item.auto_descr.unwrap(),
+            man_descr: item.man_descr,
         },
     };
 
     let request_body = MeetupUrlInsertMutation::build_query(variables);
-    let _http_resp = client
-        .post(ENDPOINT)
-        .json(&request_body)
-        .send()
-        .await
-        .expect("Failed to execute GraphQL insert mutation");
+    let _data: meetup_url_insert_mutation::ResponseData = send(&client, &request_body).await?;
+    Ok(())
 }
 
-pub async fn update_meetup_event(item: MeetupUrlEdit, token: Option<String>) {
+/// Sends only the fields of `item` that are set, the server keeps the others
+pub async fn update_meetup_event(item: MeetupUrlEdit, token: Option<String>) -> Result<(), String> {
     let client = build_client_with_auth(token);
 
+    let uri_uuid = item.uri_uuid.ok_or("No entry selected".to_string())?;
     let variables = meetup_url_update_mutation::Variables {
-        upsert_meetup_url: UpdateMeetupUrl {
-            uri_uuid: Some(item.uri_uuid.unwrap()),
-            url: item.url.unwrap(),
-            host: item.domain.unwrap(),
-            title: item.title.unwrap(),
-            auto_descr: item.description.unwrap(),
+        meetup_url_patch: MeetupUrlPatch {
+            uri_uuid,
+            url: item.url,
+            host: None,
+            title: item.title,
+            auto_descr: item.auto_descr,
+            man_descr: item.man_descr,
+            expected_modi_time: item.modi_time,
         },
     };
 
     let request_body = MeetupUrlUpdateMutation::build_query(variables);
-    let _http_resp = client
-        .post(ENDPOINT)
-        .json(&request_body)
-        .send()
-        .await
-        .expect("Failed to execute GraphQL update mutation");
+    let _data: meetup_url_update_mutation::ResponseData = send(&client, &request_body).await?;
+    Ok(())
 }
 
-pub async fn init_database(token: Option<String>) {
+pub async fn init_database(token: Option<String>) -> Result<(), String> {
     let client = build_client_with_auth(token);
 
     let variables = init_database_mutation::Variables {};
 
     let request_body = InitDatabaseMutation::build_query(variables);
-    let _http_resp = client
-        .post(ENDPOINT)
-        .json(&request_body)
-        .send()
-        .await
-        .expect("Failed to execute GraphQL update mutation");
+    let data: init_database_mutation::ResponseData = send(&client, &request_body).await?;
+    let report = data.init_database;
+    log!("Imported {} rows, updated {}, skipped {}, failed {}", report.inserted, report.updated, report.skipped, report.failed);
+    if report.failed > 0 {
+        return Err(format!("Import: {} rows failed, see the server log", report.failed));
+    }
+    Ok(())
 }
 
 fn meetup_url_to_event(data: Vec<MeetupUrlQueryMeetupUrlListResult>) -> Vec<Event> {
@@ -192,7 +305,14 @@ fn meetup_url_to_event(data: Vec<MeetupUrlQueryMeetupUrlListResult>) -> Vec<Even
             title: e.title.clone(),
             domain: e.host.clone(),
             url: e.url.clone(),
-            description: e.auto_descr.clone(),
+            description: if e.man_descr.trim().is_empty() { e.auto_descr.clone() } else { e.man_descr.clone() },
+            auto_descr: e.auto_descr.clone(),
+            man_descr: e.man_descr.clone(),
+            crea_time: e.crea_time.clone(),
+            modi_time: e.modi_time.clone(),
+            deleted_by: e.deleted_by.clone(),
+            tags: e.tags.iter().map(|tag| (tag.name.clone(), tag.count)).collect(),
+            favicon: e.metadata.as_ref().and_then(|metadata| metadata.favicon.clone()),
         })
         .collect()
 }
\ No newline at end of file
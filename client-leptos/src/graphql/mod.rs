@@ -2,9 +2,14 @@ mod meetup_url_graphql;
 mod subscription_graphql;
 
 pub use meetup_url_graphql::delete_meetup_url_by_uuid_id;
+pub use meetup_url_graphql::export_meetup_urls;
 pub use meetup_url_graphql::fetch_meetup_url_data;
 pub use meetup_url_graphql::insert_meetup_event;
+pub use meetup_url_graphql::refresh_meetup_url_metadata;
+pub use meetup_url_graphql::restore_meetup_url_by_uuid_id;
 pub use meetup_url_graphql::update_meetup_event;
 pub use meetup_url_graphql::init_database;
 
-pub use subscription_graphql::ClockSubscriptionResponse;
\ No newline at end of file
+pub use subscription_graphql::subscribe;
+pub use subscription_graphql::ClockSubscriptionResponse;
+pub use subscription_graphql::MeetupUrlChangedResponse;
\ No newline at end of file
@@ -1,8 +1,36 @@
 // {"type":"next","id":"fb087db7-0baa-48f7-8c47-ff24575465a7","payload":{"data":{"clock":{"clock":"2025-05-02 20:02:55.020169 UTC"}}}}
 // https://transform.tools/json-to-rust-serde
 
+use futures::{SinkExt, StreamExt};
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
+use uuid::Uuid;
+use ws_stream_wasm::{WsMessage, WsMeta, WsStream};
+
+/// Opens a `graphql-transport-ws` connection, authenticates it in
+/// `connection_init` and starts `query`. Returns the subscription id,
+/// keep `WsMeta` alive as long as the stream is read.
+pub async fn subscribe(query: &str, token: Option<String>) -> Result<(WsMeta, WsStream, String), String> {
+    let (ws, mut wsio) = WsMeta::connect(crate::auth_config::GRAPHQL_WS_ENDPOINT, Some(vec!["graphql-transport-ws"]))
+        .await
+        .map_err(|e| format!("WS connect error: {e}"))?;
+
+    let payload = match token {
+        Some(token) => serde_json::json!({ "Authorization": format!("Bearer {}", token) }),
+        None => serde_json::json!({}),
+    };
+    let conn_init = serde_json::json!({ "type": "connection_init", "payload": payload }).to_string();
+    wsio.send(WsMessage::Text(conn_init)).await.map_err(|e| e.to_string())?;
+
+    // connection_ack, the server closes the socket when it rejects the token
+    wsio.next().await.ok_or("WS closed during connection_init".to_string())?;
+
+    let id = Uuid::new_v4().to_string();
+    let start_msg = serde_json::json!({ "id": id, "type": "subscribe", "payload": { "query": query } }).to_string();
+    wsio.send(WsMessage::Text(start_msg)).await.map_err(|e| e.to_string())?;
+
+    Ok((ws, wsio, id))
+}
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -29,4 +57,40 @@ pub struct Data {
 #[serde(rename_all = "camelCase")]
 pub struct Clock {
     pub clock: String,
-}
\ No newline at end of file
+}
+
+// {"type":"next","id":"…","payload":{"data":{"meetupUrlChanged":{"kind":"UPDATED","meetupUrl":{"uriUuid":"…"}}}}}
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetupUrlChangedResponse {
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub id: String,
+    pub payload: MeetupUrlChangedPayload,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetupUrlChangedPayload {
+    pub data: MeetupUrlChangedData,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetupUrlChangedData {
+    pub meetup_url_changed: MeetupUrlChanged,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetupUrlChanged {
+    pub kind: String,
+    /// None for `RELOAD`
+    pub meetup_url: Option<ChangedMeetupUrl>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedMeetupUrl {
+    pub uri_uuid: String,
+}
@@ -4,5 +4,17 @@ pub struct Event {
     pub title: String,
     pub domain: String,
     pub url: String,
+    /// `man_descr` when the curator wrote one, else `auto_descr`
     pub description: String,
+    pub auto_descr: String,
+    pub man_descr: String,
+    /// RFC 3339
+    pub crea_time: String,
+    pub modi_time: String,
+    /// Set for rows in the trash
+    pub deleted_by: Option<String>,
+    /// Name and usage count
+    pub tags: Vec<(String, i64)>,
+    /// Read from the page by the server
+    pub favicon: Option<String>,
 }
\ No newline at end of file
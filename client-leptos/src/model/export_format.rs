@@ -0,0 +1,53 @@
+/// File formats of the server's `/export`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+    NetscapeBookmarks,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [ExportFormat::Json, ExportFormat::Csv, ExportFormat::Markdown, ExportFormat::NetscapeBookmarks];
+
+    /// As named by the server
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Markdown => "MARKDOWN",
+            ExportFormat::NetscapeBookmarks => "NETSCAPE_BOOKMARKS",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::NetscapeBookmarks => "Bookmarks",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        ExportFormat::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "meetup-urls.json",
+            ExportFormat::Csv => "meetup-urls.csv",
+            ExportFormat::Markdown => "meetup-urls.md",
+            ExportFormat::NetscapeBookmarks => "meetup-urls.html",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Markdown => "text/markdown",
+            ExportFormat::NetscapeBookmarks => "text/html",
+        }
+    }
+}
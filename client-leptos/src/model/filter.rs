@@ -6,4 +6,56 @@ pub struct Filter {
     pub domain: Option<String>,
     pub url: Option<String>,
     pub description: Option<String>,
+    /// Rows carrying all of them
+    pub tags: Vec<String>,
+    /// RFC 3339, `after` inclusive and `before` exclusive
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub modified_after: Option<String>,
+    pub modified_before: Option<String>,
+    pub sort: Vec<SortOrder>,
+    /// Rows in the trash instead of the live ones
+    pub trash: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortField {
+    Domain,
+    Title,
+    Url,
+    Description,
+    Created,
+    Modified,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortOrder {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+/// The start of a `yyyy-mm-dd` date as entered in a date input, in UTC
+pub fn day_start(date: &str) -> Option<String> {
+    let date = date.trim();
+    (!date.is_empty()).then(|| format!("{}T00:00:00Z", date))
+}
+
+/// Cycles `field` through ascending, descending and unsorted. A newly
+/// sorted field is appended, so it breaks ties of the existing keys.
+pub fn cycle_sort(sort: &mut Vec<SortOrder>, field: SortField) {
+    match sort.iter().position(|o| o.field == field) {
+        None => sort.push(SortOrder { field, descending: false }),
+        Some(i) if !sort[i].descending => sort[i].descending = true,
+        Some(i) => {
+            sort.remove(i);
+        }
+    }
+}
+
+/// Header marker like `▲1`, empty when `field` is not sorted
+pub fn sort_marker(sort: &[SortOrder], field: SortField) -> String {
+    sort.iter()
+        .position(|o| o.field == field)
+        .map(|i| format!("{}{}", if sort[i].descending { "▼" } else { "▲" }, i + 1))
+        .unwrap_or_default()
 }
\ No newline at end of file
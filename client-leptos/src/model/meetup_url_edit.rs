@@ -2,7 +2,9 @@
 pub struct MeetupUrlEdit {
     pub uri_uuid: Option<String>,
     pub title: Option<String>,
-    pub domain: Option<String>,
     pub url: Option<String>,
-    pub description: Option<String>,
-}
\ No newline at end of file
+    pub auto_descr: Option<String>,
+    pub man_descr: Option<String>,
+    /// The version the edit is based on, an update fails once it is outdated
+    pub modi_time: Option<String>,
+}
@@ -1,7 +1,11 @@
 mod event;
+mod export_format;
 mod filter;
 mod meetup_url_edit;
+mod page_info;
 
 pub use event::Event;
-pub use filter::Filter;
+pub use export_format::ExportFormat;
+pub use filter::{cycle_sort, day_start, sort_marker, Filter, SortField, SortOrder};
 pub use meetup_url_edit::MeetupUrlEdit;
+pub use page_info::PageInfo;
@@ -0,0 +1,10 @@
+/// Page metadata as returned by `meetupUrlList`; `current` counts from 0
+#[derive(Clone, Debug, Default)]
+pub struct PageInfo {
+    pub current: i64,
+    pub size: i64,
+    pub total: i64,
+    pub total_pages: i64,
+    pub has_next: bool,
+    pub has_previous: bool,
+}
@@ -5,9 +5,12 @@ use ratatui::prelude::*;
 use ratatui::widgets::TableState;
 use crate::components::{body, footer, header, filter_modal, login};
 use crate::graphql::fetch_meetup_url_data;
-use crate::model::{Event as MeetupEvent, FilterGraphql, FilterForm};
+use crate::model::{Event as MeetupEvent, FilterGraphql, FilterForm, SortField};
 use crate::auth::authenticate;
 
+/// Rows left below the selection when the next page is fetched
+const SCROLL_AHEAD: usize = 5;
+
 enum AppState {
     Login,
     Main,
@@ -20,9 +23,13 @@ pub struct App {
     table_state: TableState,
     data: Vec<MeetupEvent>,
     total_count: i64,
+    end_cursor: Option<String>,
+    has_next: bool,
     show_filter_modal: bool,
     filter_form: FilterForm,
     current_filter: FilterGraphql,
+    sort_column: usize,
+    error_message: Option<String>,
     rt: tokio::runtime::Runtime,
 }
 
@@ -37,9 +44,13 @@ impl App {
             table_state: TableState::default().with_selected(0),
             data: Vec::new(),
             total_count: 0,
+            end_cursor: None,
+            has_next: false,
             show_filter_modal: false,
             filter_form: FilterForm::default(),
             current_filter: FilterGraphql::default(),
+            sort_column: 0,
+            error_message: None,
             rt,
         }
     }
@@ -71,8 +82,8 @@ impl App {
                     .split(frame.area());
 
                 header::render(frame, layout[0]);
-                body::render(frame, layout[1], &mut self.table_state, &self.data);
-                footer::render(frame, layout[2]);
+                body::render(frame, layout[1], &mut self.table_state, &self.data, self.total_count, &self.current_filter.sort, self.sort_column);
+                footer::render(frame, layout[2], self.error_message.as_deref());
                 if self.show_filter_modal {
                     filter_modal::render(frame, &self.filter_form);
                 }
@@ -80,6 +91,44 @@ impl App {
         }
     }
 
+    /// Fetch with `filter`; on failure the old rows stay and the error is shown in the footer
+    fn load(&mut self, filter: FilterGraphql) {
+        match self.rt.block_on(fetch_meetup_url_data(&filter, None, self.access_token.as_deref())) {
+            Ok(page) => {
+                self.data = page.events;
+                self.total_count = page.total;
+                self.end_cursor = page.end_cursor;
+                self.has_next = page.has_next;
+                self.error_message = None;
+                self.table_state.select(Some(0));
+            }
+            Err(e) => {
+                self.error_message = Some(e);
+            }
+        }
+        self.current_filter = filter;
+    }
+
+    /// Endless scrolling: appends the page after the last loaded row
+    fn load_more(&mut self) {
+        if !self.has_next {
+            return;
+        }
+        let after = self.end_cursor.clone();
+        match self.rt.block_on(fetch_meetup_url_data(&self.current_filter, after.as_deref(), self.access_token.as_deref())) {
+            Ok(page) => {
+                self.data.extend(page.events);
+                self.total_count = page.total;
+                self.end_cursor = page.end_cursor;
+                self.has_next = page.has_next;
+                self.error_message = None;
+            }
+            Err(e) => {
+                self.error_message = Some(e);
+            }
+        }
+    }
+
     fn handle_events(&mut self) -> Result<bool> {
         if let Event::Key(key) = event::read()? {
             if key.kind == KeyEventKind::Press {
@@ -97,12 +146,7 @@ impl App {
                                         self.state = AppState::Main;
 
                                         // Load initial data
-                                        let initial_filter = FilterGraphql::default();
-                                        let (data, total_count) = self.rt.block_on(fetch_meetup_url_data(&initial_filter, self.access_token.as_deref()));
-                                        self.data = data;
-                                        self.total_count = total_count;
-                                        self.current_filter = initial_filter;
-                                        self.table_state.select(Some(0));
+                                        self.load(FilterGraphql::default());
                                     }
                                     Err(e) => {
                                         self.login_form.set_error(e);
@@ -121,12 +165,9 @@ impl App {
                                     return Ok(false);
                                 }
                                 filter_modal::FilterModalAction::Apply => {
-                                    let filter = self.filter_form.to_filter();
-                                    let (data, total_count) = self.rt.block_on(fetch_meetup_url_data(&filter, self.access_token.as_deref()));
-                                    self.data = data;
-                                    self.total_count = total_count;
-                                    self.current_filter = filter;
-                                    self.table_state.select(Some(0));
+                                    let mut filter = self.filter_form.to_filter();
+                                    filter.sort = self.current_filter.sort.clone();
+                                    self.load(filter);
                                     self.show_filter_modal = false;
                                     return Ok(false);
                                 }
@@ -140,11 +181,41 @@ impl App {
                                     self.show_filter_modal = true;
                                     return Ok(false);
                                 }
+                                // Narrows the list to the next tag of the selected row
+                                KeyCode::Char('t') | KeyCode::Char('T') => {
+                                    let tag = self.table_state.selected()
+                                        .and_then(|i| self.data.get(i))
+                                        .and_then(|e| e.tags.iter().find(|tag| !self.current_filter.tags.contains(tag)))
+                                        .cloned();
+                                    if let Some(tag) = tag {
+                                        let mut filter = self.current_filter.clone();
+                                        filter.tags.push(tag);
+                                        self.load(filter);
+                                    }
+                                    return Ok(false);
+                                }
+                                KeyCode::Left => {
+                                    self.sort_column = self.sort_column.saturating_sub(1);
+                                    return Ok(false);
+                                }
+                                KeyCode::Right => {
+                                    self.sort_column = (self.sort_column + 1).min(SortField::ALL.len() - 1);
+                                    return Ok(false);
+                                }
+                                KeyCode::Char('s') | KeyCode::Char('S') => {
+                                    let mut filter = self.current_filter.clone();
+                                    filter.cycle_sort(SortField::ALL[self.sort_column]);
+                                    self.load(filter);
+                                    return Ok(false);
+                                }
                                 KeyCode::Down => {
                                     if !self.data.is_empty() {
                                         let i = self.table_state.selected().unwrap_or(0);
                                         let new_i = (i + 1).min(self.data.len() - 1);
                                         self.table_state.select(Some(new_i));
+                                        if new_i + SCROLL_AHEAD >= self.data.len() {
+                                            self.load_more();
+                                        }
                                     }
                                     return Ok(false);
                                 }
@@ -1,21 +1,48 @@
 use ratatui::layout::Rect;
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders, Cell, Row, Table, TableState};
-use crate::model::Event;
+use crate::model::{Event, SortField, SortOrder};
 
-pub fn render(frame: &mut Frame, area: Rect, state: &mut TableState, data: &[Event]) {
+/// Header cell with the sort marker, e.g. `TITLE ▲1`; the column under the sort cursor is underlined
+fn header_cell(name: &str, field: SortField, sort: &[SortOrder], selected: bool) -> Cell<'static> {
+    let marker = sort.iter()
+        .position(|o| o.field == field)
+        .map(|i| format!(" {}{}", if sort[i].descending { "▼" } else { "▲" }, i + 1))
+        .unwrap_or_default();
+
+    let style = if selected {
+        Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+
+    Cell::from(format!("{}{}", name, marker)).style(style)
+}
+
+/// The date of an RFC 3339 time
+fn day(time: &str) -> &str {
+    time.get(..10).unwrap_or(time)
+}
+
+pub fn render(frame: &mut Frame, area: Rect, state: &mut TableState, data: &[Event], total: i64, sort: &[SortOrder], sort_column: usize) {
     let rows = data.iter().map(|e| {
         Row::new(vec![
             Cell::from(e.title.as_str()),
             Cell::from(e.domain.as_str()),
             Cell::from(e.url.as_str()),
             Cell::from(e.description.as_str()),
+            Cell::from(day(&e.crea_time)),
+            Cell::from(day(&e.modi_time)),
+            Cell::from(e.tags.join(", ")),
         ])
     });
 
-    let header = ["TITLE", "DOMAIN", "URL", "DESCRIPTION"]
+    let header = ["TITLE", "DOMAIN", "URL", "DESCRIPTION", "CREATED", "MODIFIED"]
         .into_iter()
-        .map(Cell::from)
+        .zip(SortField::ALL)
+        .enumerate()
+        .map(|(i, (name, field))| header_cell(name, field, sort, i == sort_column))
+        .chain([Cell::from("TAGS")])
         .collect::<Row>()
         .height(1);
 
@@ -27,12 +54,15 @@ pub fn render(frame: &mut Frame, area: Rect, state: &mut TableState, data: &[Eve
             Constraint::Min(10),
             Constraint::Min(10),
             Constraint::Min(10),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Min(10),
         ],
     )
     .header(header)
     .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
     .highlight_symbol(">> ")
-    .block(Block::new().borders(Borders::ALL));
+    .block(Block::new().borders(Borders::ALL).title(format!(" {} of {} ", data.len(), total)));
 
     frame.render_stateful_widget(table, area, state);
 }
\ No newline at end of file
@@ -34,29 +34,34 @@ pub fn handle_key(code: KeyCode, form: &mut FilterForm) -> FilterModalAction {
 }
 
 pub fn render(frame: &mut Frame, form: &FilterForm) {
-    let area = centered_rect(70, 50, frame.area());
+    let area = centered_rect(70, 60, frame.area());
 
     frame.render_widget(Clear, area);
 
     let title = "Filter";
     let help = "Tab/Shift-Tab = Switch • Enter = Apply • Esc = Cancel";
-    let labels = ["Title", "Domain", "Url", "Description"];
+    let labels = ["Title", "Domain", "Url", "Description", "Tags", "Created from", "Created before", "Modified from", "Modified before"];
     let values = [
         form.title_input.as_str(),
         form.domain_input.as_str(),
         form.url_input.as_str(),
         form.description_input.as_str(),
+        form.tags_input.as_str(),
+        form.created_after_input.as_str(),
+        form.created_before_input.as_str(),
+        form.modified_after_input.as_str(),
+        form.modified_before_input.as_str(),
     ];
 
     let mut body = String::new();
     body.push_str(help);
     body.push_str("\n\n");
-    for i in 0..4 {
+    for i in 0..labels.len() {
         if i == form.active_index { body.push('>'); } else { body.push(' '); }
         body.push_str(labels[i]);
         body.push_str(": ");
         body.push_str(values[i]);
-        if i < 3 { body.push('\n'); }
+        if i < labels.len() - 1 { body.push('\n'); }
     }
 
     let block = Block::default().title(title).borders(Borders::ALL);
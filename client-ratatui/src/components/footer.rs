@@ -2,8 +2,8 @@ use ratatui::layout::Rect;
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders, Paragraph};
 
-pub fn render(frame: &mut Frame, area: Rect) {
-    let line = Line::from(vec![
+pub fn render(frame: &mut Frame, area: Rect, error: Option<&str>) {
+    let mut spans = vec![
         Span::raw(" "),
         Span::styled(
             "(Q)",
@@ -22,7 +22,32 @@ pub fn render(frame: &mut Frame, area: Rect) {
                 .add_modifier(Modifier::BOLD),
         ),
         Span::raw(" Filter"),
-    ]);
+        Span::raw("  "),
+        Span::styled(
+            "(←→ S)",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" Sort"),
+        Span::raw("  "),
+        Span::styled(
+            "(T)",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" Filter by tag"),
+    ];
+
+    if let Some(error) = error {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(error, Style::default().fg(Color::Red)));
+    }
+
+    let line = Line::from(spans);
 
     frame.render_widget(
         Paragraph::new(line).block(Block::new().borders(Borders::ALL)),
@@ -1,44 +1,76 @@
-use crate::graphql::meetup_url_graphql::meetup_url_query::MeetupUrlQueryMeetupUrlListResult;
-use crate::model::{Event, FilterGraphql};
+use crate::model::{Event, EventPage, FilterGraphql, SortField};
 use graphql_client::GraphQLQuery;
 use ::reqwest::Client;
 
+/// The server's `DateTime` scalar, RFC 3339
+type DateTime = String;
+
 #[derive(GraphQLQuery)]
 #[graphql(
     schema_path = "graphql/schema.graphql",
     query_path = "graphql/meetup_url.graphql",
 )]
-pub struct MeetupUrlQuery;
+pub struct MeetupUrlConnectionQuery;
 
 const ENDPOINT: &str = "http://localhost:8080/graphql";
 
-pub async fn fetch_meetup_url_data(filter: &FilterGraphql, token: Option<&str>) -> (Vec<Event>, i64) {
-    let client = match Client::builder().build() {
-        Ok(c) => c,
-        Err(_e) => {
-            return (Vec::new(), 0);
-        }
-    };
-    fetch_meetup_url_data_with(&client, filter, token).await
+/// Events fetched per scroll step
+const PAGE_SIZE: i64 = 50;
+
+/// The page after `after`, or the first page
+pub async fn fetch_meetup_url_data(filter: &FilterGraphql, after: Option<&str>, token: Option<&str>) -> Result<EventPage, String> {
+    let client = Client::builder()
+        .build()
+        .map_err(|e| format!("Cannot create HTTP client: {}", e))?;
+    fetch_meetup_url_data_with(&client, filter, after, token).await
 }
 
-pub async fn fetch_meetup_url_data_with(client: &Client, filter: &FilterGraphql, token: Option<&str>) -> (Vec<Event>, i64) {
-    let page = match (filter.page, filter.size) {
-        (Some(current), Some(size)) => Some(meetup_url_query::Pagination { current, size }),
-        _ => None,
-    };
+pub async fn fetch_meetup_url_data_with(client: &Client, filter: &FilterGraphql, after: Option<&str>, token: Option<&str>) -> Result<EventPage, String> {
 
-    let filter = meetup_url_query::MeetupUrlFilter {
+    let sort = filter.sort.iter()
+        .map(|order| meetup_url_connection_query::MeetupUrlOrder {
+            field: match order.field {
+                SortField::Title => meetup_url_connection_query::MeetupUrlSort::TITLE,
+                SortField::Domain => meetup_url_connection_query::MeetupUrlSort::DOMAIN,
+                SortField::Url => meetup_url_connection_query::MeetupUrlSort::URL,
+                SortField::Description => meetup_url_connection_query::MeetupUrlSort::DESCRIPTION,
+                SortField::Created => meetup_url_connection_query::MeetupUrlSort::CREATED,
+                SortField::Modified => meetup_url_connection_query::MeetupUrlSort::MODIFIED,
+            },
+            direction: Some(if order.descending {
+                meetup_url_connection_query::SortDirection::DESC
+            } else {
+                meetup_url_connection_query::SortDirection::ASC
+            }),
+        })
+        .collect();
+
+    let filter = meetup_url_connection_query::MeetupUrlFilter {
         domain: filter.domain.clone(),
         title: filter.title.clone(),
         url: filter.url.clone(),
         description: filter.description.clone(),
-        pagination: page,
-        sort: None,
+        created_after: filter.created_after.clone(),
+        created_before: filter.created_before.clone(),
+        modified_after: filter.modified_after.clone(),
+        modified_before: filter.modified_before.clone(),
+        include_deleted: None,
+        only_deleted: None,
+        tags: (!filter.tags.is_empty()).then(|| meetup_url_connection_query::TagFilter {
+            names: filter.tags.clone(),
+            mode: meetup_url_connection_query::TagMatch::ALL,
+        }),
+        link_state: None,
+        pagination: None,
+        sort: Some(sort),
     };
 
-    let variables = meetup_url_query::Variables { filter };
-    let request_body = MeetupUrlQuery::build_query(variables);
+    let variables = meetup_url_connection_query::Variables {
+        filter,
+        first: Some(PAGE_SIZE),
+        after: after.map(str::to_string),
+    };
+    let request_body = MeetupUrlConnectionQuery::build_query(variables);
 
     let mut request = client.post(ENDPOINT).json(&request_body);
 
@@ -46,33 +78,48 @@ pub async fn fetch_meetup_url_data_with(client: &Client, filter: &FilterGraphql,
         request = request.header("Authorization", format!("Bearer {}", token));
     }
 
-    let http_resp = match request.send().await {
-        Ok(r) => r,
-        Err(_e) => return (Vec::new(), 0),
-    };
+    let http_resp = request
+        .send()
+        .await
+        .map_err(|e| format!("Server not reachable: {}", e))?;
 
-    let response: graphql_client::Response<meetup_url_query::ResponseData> = match http_resp.json().await {
-        Ok(json) => json,
-        Err(_e) => return (Vec::new(), 0),
-    };
+    if !http_resp.status().is_success() {
+        return Err(format!("Server answered {}", http_resp.status()));
+    }
 
-    if let Some(data) = response.data {
-        (
-            meetup_url_to_event(data.meetup_url_list.result),
-            data.meetup_url_count.count as i64,
-        )
-    } else {
-        (Vec::new(), 0)
+    let response: graphql_client::Response<meetup_url_connection_query::ResponseData> = http_resp
+        .json()
+        .await
+        .map_err(|e| format!("Invalid server response: {}", e))?;
+
+    if let Some(error) = response.errors.as_ref().and_then(|errors| errors.first()) {
+        let code = error.extensions.as_ref()
+            .and_then(|ext| ext.get("code"))
+            .and_then(|code| code.as_str())
+            .unwrap_or("ERROR");
+        return Err(format!("{}: {}", code, error.message));
     }
-}
 
-fn meetup_url_to_event(data: Vec<MeetupUrlQueryMeetupUrlListResult>) -> Vec<Event> {
-    data.into_iter()
-        .map(|e| Event {
-            title: e.title,
-            domain: e.host,
-            url: e.url,
-            description: e.auto_descr,
-        })
-        .collect()
-}
\ No newline at end of file
+    let Some(data) = response.data else {
+        return Err("Empty server response".to_string());
+    };
+
+    let connection = data.meetup_url_connection;
+    Ok(EventPage {
+        events: connection.edges.into_iter()
+            .map(|edge| Event {
+                title: edge.node.title,
+                domain: edge.node.host,
+                url: edge.node.url,
+                // The curator's description wins over the automatic one
+                description: if edge.node.man_descr.trim().is_empty() { edge.node.auto_descr } else { edge.node.man_descr },
+                tags: edge.node.tags.into_iter().map(|tag| tag.name).collect(),
+                crea_time: edge.node.crea_time,
+                modi_time: edge.node.modi_time,
+            })
+            .collect(),
+        total: connection.total_count,
+        end_cursor: connection.page_info.end_cursor,
+        has_next: connection.page_info.has_next_page,
+    })
+}
@@ -4,4 +4,8 @@ pub struct Event {
     pub domain: String,
     pub url: String,
     pub description: String,
+    pub tags: Vec<String>,
+    /// RFC 3339
+    pub crea_time: String,
+    pub modi_time: String,
 }
\ No newline at end of file
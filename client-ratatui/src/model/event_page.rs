@@ -0,0 +1,11 @@
+use super::Event;
+
+/// One slice of the endless list
+#[derive(Clone, Debug, Default)]
+pub struct EventPage {
+    pub events: Vec<Event>,
+    pub total: i64,
+    /// Cursor to continue after the last event
+    pub end_cursor: Option<String>,
+    pub has_next: bool,
+}
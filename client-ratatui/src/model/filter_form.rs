@@ -1,12 +1,39 @@
 use super::FilterGraphql;
 
+/// Inputs of the form, `active_index` cycles through them
+const INPUTS: usize = 9;
+
 #[derive(Clone, Debug, Default)]
 pub struct FilterForm {
     pub title_input: String,
     pub domain_input: String,
     pub url_input: String,
     pub description_input: String,
-    pub active_index: usize, // 0=title,1=domain,2=url,3=description
+    /// Comma separated
+    pub tags_input: String,
+    /// A date like `2024-08-29` or an RFC 3339 time
+    pub created_after_input: String,
+    pub created_before_input: String,
+    pub modified_after_input: String,
+    pub modified_before_input: String,
+    pub active_index: usize, // 0=title,1=domain,2=url,3=description,4=tags,5..=8=times
+}
+
+/// The start of a date in UTC, other input is passed on as it is
+fn filter_time(input: &str) -> Option<String> {
+    let input = input.trim();
+    match input.len() {
+        0 => None,
+        10 => Some(format!("{}T00:00:00Z", input)),
+        _ => Some(input.to_string()),
+    }
+}
+
+/// The input of a time set by `filter_time`
+fn time_input(time: &Option<String>) -> String {
+    time.as_deref()
+        .map(|time| time.strip_suffix("T00:00:00Z").unwrap_or(time).to_string())
+        .unwrap_or_default()
 }
 
 impl FilterForm {
@@ -15,6 +42,11 @@ impl FilterForm {
         self.domain_input = filter.domain.clone().unwrap_or_default();
         self.url_input = filter.url.clone().unwrap_or_default();
         self.description_input = filter.description.clone().unwrap_or_default();
+        self.tags_input = filter.tags.join(", ");
+        self.created_after_input = time_input(&filter.created_after);
+        self.created_before_input = time_input(&filter.created_before);
+        self.modified_after_input = time_input(&filter.modified_after);
+        self.modified_before_input = time_input(&filter.modified_before);
         self.active_index = 0;
     }
 
@@ -28,34 +60,50 @@ impl FilterForm {
         if !d.is_empty() { filter.domain = Some(d.to_string()); }
         if !u.is_empty() { filter.url = Some(u.to_string()); }
         if !desc.is_empty() { filter.description = Some(desc.to_string()); }
+        filter.tags = self.tags_input.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        filter.created_after = filter_time(&self.created_after_input);
+        filter.created_before = filter_time(&self.created_before_input);
+        filter.modified_after = filter_time(&self.modified_after_input);
+        filter.modified_before = filter_time(&self.modified_before_input);
         filter
     }
 
     pub fn cycle_next(&mut self) {
-        self.active_index = (self.active_index + 1) % 4;
+        self.active_index = (self.active_index + 1) % INPUTS;
     }
 
     pub fn cycle_prev(&mut self) {
-        self.active_index = if self.active_index == 0 { 3 } else { self.active_index - 1 };
+        self.active_index = if self.active_index == 0 { INPUTS - 1 } else { self.active_index - 1 };
     }
 
-    pub fn push_active(&mut self, c: char) {
+    fn active_input(&mut self) -> Option<&mut String> {
         match self.active_index {
-            0 => self.title_input.push(c),
-            1 => self.domain_input.push(c),
-            2 => self.url_input.push(c),
-            3 => self.description_input.push(c),
-            _ => {}
+            0 => Some(&mut self.title_input),
+            1 => Some(&mut self.domain_input),
+            2 => Some(&mut self.url_input),
+            3 => Some(&mut self.description_input),
+            4 => Some(&mut self.tags_input),
+            5 => Some(&mut self.created_after_input),
+            6 => Some(&mut self.created_before_input),
+            7 => Some(&mut self.modified_after_input),
+            8 => Some(&mut self.modified_before_input),
+            _ => None,
+        }
+    }
+
+    pub fn push_active(&mut self, c: char) {
+        if let Some(input) = self.active_input() {
+            input.push(c);
         }
     }
 
     pub fn pop_active(&mut self) {
-        match self.active_index {
-            0 => { self.title_input.pop(); },
-            1 => { self.domain_input.pop(); },
-            2 => { self.url_input.pop(); },
-            3 => { self.description_input.pop(); },
-            _ => {}
+        if let Some(input) = self.active_input() {
+            input.pop();
         }
     }
-}
\ No newline at end of file
+}
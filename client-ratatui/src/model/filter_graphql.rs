@@ -1,9 +1,57 @@
 #[derive(Clone, Debug, Default)]
 pub struct FilterGraphql {
-    pub page: Option<i64>,
-    pub size: Option<i64>,
     pub title: Option<String>,
     pub domain: Option<String>,
     pub url: Option<String>,
     pub description: Option<String>,
-}
\ No newline at end of file
+    /// Rows carrying all of them
+    pub tags: Vec<String>,
+    /// RFC 3339, `after` inclusive and `before` exclusive
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub modified_after: Option<String>,
+    pub modified_before: Option<String>,
+    pub sort: Vec<SortOrder>,
+}
+
+/// Sortable columns, in the order of the body table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortField {
+    Title,
+    Domain,
+    Url,
+    Description,
+    Created,
+    Modified,
+}
+
+impl SortField {
+    pub const ALL: [SortField; 6] = [
+        SortField::Title,
+        SortField::Domain,
+        SortField::Url,
+        SortField::Description,
+        SortField::Created,
+        SortField::Modified,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortOrder {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl FilterGraphql {
+    /// Cycles `field` through ascending, descending and unsorted. A newly
+    /// sorted field is appended, so it breaks ties of the existing keys.
+    pub fn cycle_sort(&mut self, field: SortField) {
+        match self.sort.iter().position(|o| o.field == field) {
+            None => self.sort.push(SortOrder { field, descending: false }),
+            Some(i) if !self.sort[i].descending => self.sort[i].descending = true,
+            Some(i) => {
+                self.sort.remove(i);
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
 mod event;
+mod event_page;
 mod filter_graphql;
 mod filter_form;
 
 pub use event::Event;
-pub use filter_graphql::FilterGraphql;
+pub use event_page::EventPage;
+pub use filter_graphql::{FilterGraphql, SortField, SortOrder};
 pub use filter_form::FilterForm;
\ No newline at end of file
@@ -9,6 +9,7 @@ use axum::{
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use async_graphql::Data;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RealmAccess {
@@ -34,6 +35,11 @@ pub struct Claims {
 }
 
 impl Claims {
+    /// Recorded as `crea_user` and `modi_user`
+    pub fn username(&self) -> &str {
+        self.preferred_username.as_deref().unwrap_or(&self.sub)
+    }
+
     pub fn get_realm_roles(&self) -> Vec<String> {
         self.realm_access
             .as_ref()
@@ -93,6 +99,40 @@ impl AuthState {
         *jwks_write = Some(jwks.clone());
         Ok(jwks)
     }
+
+    /// Verifies signature, issuer and audience of a bearer token
+    pub async fn validate_token(&self, token: &str) -> Result<Claims, AuthError> {
+        let header = decode_header(token).map_err(|_| AuthError::InvalidToken)?;
+        let kid = header.kid.ok_or(AuthError::InvalidToken)?;
+
+        let jwks = self.get_jwks().await.map_err(AuthError::Jwks)?;
+        let jwk = jwks.find(&kid).ok_or(AuthError::InvalidToken)?;
+
+        let decoding_key = DecodingKey::from_jwk(jwk).map_err(|_| AuthError::InvalidToken)?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.audience]);
+
+        let token_data = decode::<Claims>(token, &decoding_key, &validation)
+            .map_err(|e| {
+                tracing::error!("Token validation failed: {:?}", e);
+                AuthError::InvalidToken
+            })?;
+
+        Ok(token_data.claims)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    InvalidToken,
+    Jwks(String),
+}
+
+/// Token of an `Authorization: Bearer <token>` value
+pub fn bearer_token(value: &str) -> Option<&str> {
+    value.strip_prefix("Bearer ")
 }
 
 pub async fn auth_middleware(
@@ -100,36 +140,106 @@ pub async fn auth_middleware(
     mut req: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    let auth_header = req.headers()
+    let token = req.headers()
         .get(header::AUTHORIZATION)
         .and_then(|h| h.to_str().ok())
+        .and_then(bearer_token)
         .ok_or(StatusCode::UNAUTHORIZED)?;
 
-    if !auth_header.starts_with("Bearer ") {
-        return Err(StatusCode::UNAUTHORIZED);
+    let claims = state.validate_token(token).await
+        .map_err(|e| match e {
+            AuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+            AuthError::Jwks(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    req.extensions_mut().insert(claims);
+
+    Ok(next.run(req).await)
+}
+
+/// `connection_init` handler for GraphQL subscriptions. The client sends
+/// `{"Authorization": "Bearer <token>"}` as payload; a valid token puts its
+/// `Claims` into the connection data, an invalid one closes the connection.
+/// Without a token the connection stays anonymous and only unguarded
+/// subscriptions can be used.
+pub async fn ws_connection_init(state: Arc<AuthState>, payload: serde_json::Value) -> async_graphql::Result<Data> {
+    let mut data = Data::default();
+
+    let authorization = payload.as_object()
+        .and_then(|p| p.iter().find(|(k, _)| k.eq_ignore_ascii_case("authorization")))
+        .and_then(|(_, v)| v.as_str());
+
+    if let Some(authorization) = authorization {
+        let token = bearer_token(authorization)
+            .ok_or_else(|| async_graphql::Error::new("Malformed Authorization"))?;
+        let claims = state.validate_token(token).await
+            .map_err(|_| async_graphql::Error::new("Invalid token"))?;
+        data.insert(claims);
     }
 
-    let token = &auth_header[7..];
+    Ok(data)
+}
 
-    let header = decode_header(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
-    let kid = header.kid.ok_or(StatusCode::UNAUTHORIZED)?;
+#[cfg(test)]
+mod tests {
+    use super::{ws_connection_init, AuthError, AuthState, Claims};
+    use crate::graphql::testing::claims;
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const ISSUER: &str = "http://localhost:8888/realms/hackandlearn";
+    const AUDIENCE: &str = "hackandlearn-client";
+
+    /// Auth state with the test key preloaded, no Keycloak needed
+    pub async fn auth_state() -> Arc<AuthState> {
+        let state = AuthState::new(ISSUER.to_string(), "http://unused".to_string(), AUDIENCE.to_string());
+        *state.jwks.write().await = Some(serde_json::from_str(include_str!("../testdata/jwt_test_jwks.json")).unwrap());
+        Arc::new(state)
+    }
 
-    let jwks = state.get_jwks().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let jwk = jwks.find(&kid).ok_or(StatusCode::UNAUTHORIZED)?;
+    /// Token signed with the test key
+    pub fn token(claims: &Claims) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        let key = EncodingKey::from_rsa_pem(include_bytes!("../testdata/jwt_test_key.pem")).unwrap();
+        encode(&header, claims, &key).unwrap()
+    }
 
-    let decoding_key = DecodingKey::from_jwk(jwk).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    fn valid_claims() -> Claims {
+        Claims {
+            exp: (chrono::Utc::now().timestamp() + 300) as usize,
+            aud: Some(json!(AUDIENCE)),
+            ..claims(&["ROLE_HNL_USER"])
+        }
+    }
 
-    let mut validation = Validation::new(Algorithm::RS256);
-    validation.set_issuer(&[&state.config.issuer]);
-    validation.set_audience(&[&state.config.audience]);
+    #[tokio::test]
+    async fn test_validate_token() {
+        let state = auth_state().await;
 
-    let token_data = decode::<Claims>(token, &decoding_key, &validation)
-        .map_err(|e| {
-            tracing::error!("Token validation failed: {:?}", e);
-            StatusCode::UNAUTHORIZED
-        })?;
+        let claims = state.validate_token(&token(&valid_claims())).await.unwrap();
+        assert_eq!(claims.preferred_username.as_deref(), Some("curator"));
 
-    req.extensions_mut().insert(token_data.claims);
+        let foreign = Claims { aud: Some(json!("other-client")), ..valid_claims() };
+        assert_eq!(state.validate_token(&token(&foreign)).await.unwrap_err(), AuthError::InvalidToken);
 
-    Ok(next.run(req).await)
-}
\ No newline at end of file
+        let expired = Claims { exp: 1, ..valid_claims() };
+        assert_eq!(state.validate_token(&token(&expired)).await.unwrap_err(), AuthError::InvalidToken);
+
+        assert_eq!(state.validate_token("not.a.token").await.unwrap_err(), AuthError::InvalidToken);
+    }
+
+    #[tokio::test]
+    async fn test_ws_connection_init() {
+        let state = auth_state().await;
+
+        let bearer = format!("Bearer {}", token(&valid_claims()));
+        assert!(ws_connection_init(state.clone(), json!({ "Authorization": bearer })).await.is_ok());
+
+        assert!(ws_connection_init(state.clone(), json!({})).await.is_ok());
+
+        assert!(ws_connection_init(state.clone(), json!({ "Authorization": "Bearer forged" })).await.is_err());
+        assert!(ws_connection_init(state, json!({ "Authorization": token(&valid_claims()) })).await.is_err());
+    }
+}
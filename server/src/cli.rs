@@ -0,0 +1,82 @@
+use crate::config::Settings;
+use crate::service::{import_file, ImportOptions};
+use surrealdb::engine::any::Any;
+use surrealdb::Surreal;
+
+pub const USAGE: &str = "usage: rust-berlin-backend [import [--dry-run] [FILE]]";
+
+/// Recorded as the actor of the changes made on the command line
+const CLI_ACTOR: &str = "cli";
+
+/// What the binary was started for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Without arguments
+    Serve,
+    /// Imports `file`, else `import.data_file`, and exits
+    Import { file: Option<String>, dry_run: bool },
+}
+
+impl Command {
+    /// `args` without the program name
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let Some((command, rest)) = args.split_first() else {
+            return Ok(Command::Serve);
+        };
+        if command != "import" {
+            return Err(format!("unknown command '{}'", command));
+        }
+
+        let (mut file, mut dry_run) = (None, false);
+        for arg in rest {
+            match arg.as_str() {
+                "--dry-run" => dry_run = true,
+                flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+                path if file.is_none() => file = Some(path.to_string()),
+                extra => return Err(format!("unexpected argument '{}'", extra)),
+            }
+        }
+        Ok(Command::Import { file, dry_run })
+    }
+}
+
+/// Prints the report, `false` when a row failed or the file could not be read
+pub async fn run_import(db: &Surreal<Any>, settings: &Settings, file: Option<String>, dry_run: bool) -> bool {
+    let file = file.unwrap_or(settings.import.data_file.clone());
+    let options = ImportOptions { dry_run, batch_size: settings.import.batch_size };
+
+    let report = match import_file(db, &file, options, CLI_ACTOR).await {
+        Ok(imported) => imported.report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+
+    println!("{} as {:?}{}: {} inserted, {} updated, {} skipped, {} failed",
+        file, report.format, if dry_run { " (dry run)" } else { "" }, report.inserted, report.updated, report.skipped, report.failed);
+    for error in &report.errors {
+        println!("  row {} ({}): {}", error.row, error.uri_uuid.as_deref().unwrap_or("-"), error.reason);
+    }
+    report.failed == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]), Ok(Command::Serve));
+        assert_eq!(parse(&["import"]), Ok(Command::Import { file: None, dry_run: false }));
+        assert_eq!(parse(&["import", "--dry-run", "data.json"]), Ok(Command::Import { file: Some("data.json".to_string()), dry_run: true }));
+
+        assert!(parse(&["serve"]).is_err());
+        assert!(parse(&["import", "--force"]).is_err());
+        assert!(parse(&["import", "a.json", "b.json"]).is_err());
+    }
+}
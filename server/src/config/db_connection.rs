@@ -1,22 +1,37 @@
-use surrealdb::engine::remote::ws::{Client, Ws};
+use crate::config::DatabaseSettings;
+use surrealdb::engine::any::{connect, Any};
 use surrealdb::opt::auth::Root;
-use surrealdb::Surreal;
+use surrealdb::{Error, Surreal};
 
-pub async fn connect_db() -> Surreal<Client> {
+/// Opens the connection shared by all resolvers, called once at startup
+pub async fn connect_db(settings: &DatabaseSettings) -> Result<Surreal<Any>, Error> {
 
     // Connect to the server
-    let db = Surreal::new::<Ws>("localhost:8000")
-        .await.expect("cannot connect network");
+    let db = connect(settings.endpoint.as_str()).await?;
 
     // Select a specific namespace / database
-    db.use_ns("berlin").use_db("url_inventory")
-        .await.expect("cannot connect to namespace");
+    db.use_ns(settings.namespace.as_str()).use_db(settings.database.as_str()).await?;
 
     // Login User
     db.signin(Root {
-        username: "root",
-        password: "root",
-    }).await.expect("cannot connect user");
+        username: &settings.username,
+        password: &settings.password,
+    }).await?;
+
+    Ok(db)
+}
+
+/// In-memory database for tests, no running SurrealDB needed
+#[cfg(test)]
+pub async fn connect_test_db() -> Surreal<Any> {
+    let db = connect("mem://")
+        .await.expect("cannot start in-memory database");
+
+    db.use_ns("berlin").use_db("url_inventory")
+        .await.expect("cannot connect to namespace");
+
+    crate::repository::migrate(&db)
+        .await.expect("cannot migrate in-memory database");
 
     db
-}
\ No newline at end of file
+}
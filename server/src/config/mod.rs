@@ -1,3 +1,12 @@
 mod db_connection;
+mod settings;
 
 pub use db_connection::connect_db;
+#[cfg(test)]
+pub use db_connection::connect_test_db;
+pub use settings::DatabaseSettings;
+pub use settings::LinkHealthSettings;
+pub use settings::MetadataSettings;
+pub use settings::RoleSettings;
+pub use settings::Settings;
+pub use settings::TrashSettings;
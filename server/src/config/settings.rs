@@ -0,0 +1,321 @@
+use ::config::{Config, ConfigError, Environment, File, Map};
+use serde::Deserialize;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+
+/// Base configuration file, relative to the working directory
+pub const DEFAULT_CONFIG_FILE: &str = "config/default.toml";
+
+/// Prefix of the environment variables overriding the configuration files,
+/// e.g. `HNL__DATABASE__PASSWORD` overrides `database.password`
+pub const ENV_PREFIX: &str = "HNL";
+
+/// Settings read from the environment as comma separated lists
+const ROLE_LIST_KEYS: [&str; 3] = ["auth.roles.viewer", "auth.roles.editor", "auth.roles.admin"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub database: DatabaseSettings,
+    pub auth: AuthSettings,
+    pub import: ImportSettings,
+    pub trash: TrashSettings,
+    pub link_health: LinkHealthSettings,
+    pub metadata: MetadataSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerSettings {
+    pub bind: String,
+    pub static_dir: String,
+    pub public_dir: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatabaseSettings {
+    pub endpoint: String,
+    pub namespace: String,
+    pub database: String,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthSettings {
+    pub issuer: String,
+    pub jwks_url: String,
+    pub audience: String,
+    pub roles: RoleSettings,
+}
+
+/// Keycloak role names granting each application role. Roles are looked up
+/// in the realm roles and in the client roles of `client_id`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoleSettings {
+    pub client_id: String,
+    pub viewer: Vec<String>,
+    pub editor: Vec<String>,
+    pub admin: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportSettings {
+    pub data_file: String,
+    /// Rows written per transaction
+    pub batch_size: usize,
+}
+
+/// Deleted rows stay restorable for `retention_days`, the purge job looks
+/// for older ones every `purge_interval_minutes`
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrashSettings {
+    pub retention_days: u32,
+    pub purge_interval_minutes: u64,
+}
+
+/// The link checker requests every stored url each `interval_minutes`,
+/// at most `concurrency` at a time and one per `per_host_delay_ms` on the
+/// same host
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkHealthSettings {
+    pub enabled: bool,
+    pub interval_minutes: u64,
+    pub concurrency: usize,
+    pub per_host_delay_ms: u64,
+    /// Per request
+    pub timeout_seconds: u64,
+    pub max_redirects: u32,
+    /// Also check urls on loopback and private addresses, for tests
+    pub allow_private_addresses: bool,
+}
+
+/// Reading titles, descriptions and icons from the pages, see
+/// `refreshMetadata`
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetadataSettings {
+    /// Also read the page of each inserted url, in the background
+    pub fetch_on_insert: bool,
+    pub timeout_seconds: u64,
+    pub max_redirects: usize,
+    /// Larger pages are read up to this size only
+    pub max_page_kb: usize,
+    /// Also read pages on loopback and private addresses, for tests
+    pub allow_private_addresses: bool,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Load(ConfigError),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Load(e) => write!(f, "cannot load configuration: {}", e),
+            SettingsError::Invalid(errors) => write!(f, "invalid configuration: {}", errors.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<ConfigError> for SettingsError {
+    fn from(e: ConfigError) -> Self {
+        SettingsError::Load(e)
+    }
+}
+
+impl Settings {
+    /// Layers, later ones win:
+    /// 1. `config/default.toml` (or the file named by `HNL_CONFIG`)
+    /// 2. `<HNL_ENV>.toml` next to that file, optional, e.g. `config/prod.toml`
+    /// 3. `HNL__<SECTION>__<KEY>` environment variables, the role lists
+    ///    comma separated, e.g. `HNL__AUTH__ROLES__ADMIN=ROLE_A,ROLE_B`
+    pub fn load() -> Result<Self, SettingsError> {
+        let file = std::env::var("HNL_CONFIG").unwrap_or(DEFAULT_CONFIG_FILE.to_string());
+        let profile = std::env::var("HNL_ENV").ok();
+        Self::load_from(&file, profile.as_deref(), None)
+    }
+
+    /// `env` replaces the process environment, used by tests
+    pub fn load_from(file: &str, profile: Option<&str>, env: Option<Map<String, String>>) -> Result<Self, SettingsError> {
+        let mut builder = Config::builder().add_source(File::with_name(file));
+
+        if let Some(profile) = profile {
+            let profile = Path::new(file).parent().unwrap_or(Path::new("")).join(profile);
+            builder = builder.add_source(File::from(profile).required(false));
+        }
+
+        let mut environment = Environment::with_prefix(ENV_PREFIX)
+            .prefix_separator("__")
+            .separator("__")
+            .try_parsing(true)
+            .list_separator(",");
+        for key in ROLE_LIST_KEYS {
+            environment = environment.with_list_parse_key(key);
+        }
+
+        let settings: Settings = builder
+            .add_source(environment.source(env))
+            .build()?
+            .try_deserialize()?;
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let mut errors = Vec::new();
+
+        if self.server.bind.parse::<SocketAddr>().is_err() {
+            errors.push(format!("server.bind '{}' is not a socket address", self.server.bind));
+        }
+
+        let endpoint_schemes = ["ws://", "wss://", "http://", "https://", "mem://"];
+        if !endpoint_schemes.iter().any(|s| self.database.endpoint.starts_with(s)) {
+            errors.push(format!("database.endpoint '{}' must start with one of {:?}", self.database.endpoint, endpoint_schemes));
+        }
+
+        for (key, value) in [
+            ("server.static_dir", &self.server.static_dir),
+            ("server.public_dir", &self.server.public_dir),
+            ("database.namespace", &self.database.namespace),
+            ("database.database", &self.database.database),
+            ("database.username", &self.database.username),
+            ("auth.audience", &self.auth.audience),
+            ("auth.roles.client_id", &self.auth.roles.client_id),
+            ("import.data_file", &self.import.data_file),
+        ] {
+            if value.trim().is_empty() {
+                errors.push(format!("{} must not be empty", key));
+            }
+        }
+
+        for (key, value) in [("auth.issuer", &self.auth.issuer), ("auth.jwks_url", &self.auth.jwks_url)] {
+            if !value.starts_with("http://") && !value.starts_with("https://") {
+                errors.push(format!("{} '{}' must be an http(s) URL", key, value));
+            }
+        }
+
+        if self.trash.purge_interval_minutes == 0 {
+            errors.push("trash.purge_interval_minutes must be positive".to_string());
+        }
+
+        for (key, value) in [
+            ("link_health.interval_minutes", self.link_health.interval_minutes),
+            ("link_health.concurrency", self.link_health.concurrency as u64),
+            ("link_health.timeout_seconds", self.link_health.timeout_seconds),
+            ("metadata.timeout_seconds", self.metadata.timeout_seconds),
+            ("metadata.max_page_kb", self.metadata.max_page_kb as u64),
+            ("import.batch_size", self.import.batch_size as u64),
+        ] {
+            if value == 0 {
+                errors.push(format!("{} must be positive", key));
+            }
+        }
+
+        if self.auth.roles.admin.is_empty() {
+            errors.push("auth.roles.admin must name at least one Keycloak role".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(SettingsError::Invalid(errors))
+        }
+    }
+
+    pub fn bind_addr(&self) -> SocketAddr {
+        self.server.bind.parse().expect("validated on load")
+    }
+
+    /// Default file only, ignoring the process environment
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        Self::load_from(DEFAULT_CONFIG_FILE, None, Some(Map::new())).expect("default configuration is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Settings, SettingsError, DEFAULT_CONFIG_FILE};
+    use ::config::Map;
+    use std::fs;
+
+    fn env(vars: &[(&str, &str)]) -> Option<Map<String, String>> {
+        Some(vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+    }
+
+    #[test]
+    fn test_default_file() {
+        let settings = Settings::load_from(DEFAULT_CONFIG_FILE, None, env(&[])).unwrap();
+
+        assert_eq!(settings.database.endpoint, "ws://localhost:8000");
+        assert_eq!(settings.database.namespace, "berlin");
+        assert_eq!(settings.auth.audience, "hackandlearn-client");
+        assert_eq!(settings.auth.roles.admin, vec!["ROLE_HNL_ADMIN".to_string()]);
+        assert_eq!(settings.bind_addr().port(), 8080);
+        assert_eq!(settings.trash.retention_days, 30);
+        assert_eq!(settings.link_health.concurrency, 8);
+        assert!(settings.metadata.fetch_on_insert);
+        assert!(!settings.metadata.allow_private_addresses);
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let settings = Settings::load_from(DEFAULT_CONFIG_FILE, None, env(&[
+            ("HNL__DATABASE__PASSWORD", "secret"),
+            ("HNL__SERVER__BIND", "0.0.0.0:9090"),
+        ])).unwrap();
+
+        assert_eq!(settings.database.password, "secret");
+        assert_eq!(settings.bind_addr().port(), 9090);
+    }
+
+    #[test]
+    fn test_env_overrides_role_lists() {
+        let settings = Settings::load_from(DEFAULT_CONFIG_FILE, None, env(&[
+            ("HNL__AUTH__ROLES__ADMIN", "ROLE_HNL_ADMIN,ROLE_OPS"),
+            ("HNL__AUTH__ROLES__VIEWER", "ROLE_GUEST"),
+            ("HNL__DATABASE__PASSWORD", "1234"),
+        ])).unwrap();
+
+        assert_eq!(settings.auth.roles.admin, vec!["ROLE_HNL_ADMIN".to_string(), "ROLE_OPS".to_string()]);
+        assert_eq!(settings.auth.roles.viewer, vec!["ROLE_GUEST".to_string()]);
+        assert_eq!(settings.auth.roles.editor, vec!["ROLE_HNL_EDITOR".to_string()]);
+        assert_eq!(settings.database.password, "1234");
+    }
+
+    #[test]
+    fn test_profile_next_to_file() {
+        let dir = std::env::temp_dir().join(format!("hnl-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy(DEFAULT_CONFIG_FILE, dir.join("default.toml")).unwrap();
+        fs::write(dir.join("prod.toml"), "[trash]\nretention_days = 7\n").unwrap();
+
+        let file = dir.join("default.toml");
+        let settings = Settings::load_from(file.to_str().unwrap(), Some("prod"), env(&[]));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(settings.unwrap().trash.retention_days, 7);
+
+        // No profile file is no error
+        let settings = Settings::load_from(DEFAULT_CONFIG_FILE, Some("missing"), env(&[])).unwrap();
+        assert_eq!(settings.trash.retention_days, 30);
+    }
+
+    #[test]
+    fn test_validation_reports_all_errors() {
+        let result = Settings::load_from(DEFAULT_CONFIG_FILE, None, env(&[
+            ("HNL__SERVER__BIND", "localhost"),
+            ("HNL__DATABASE__ENDPOINT", "localhost:8000"),
+            ("HNL__AUTH__ISSUER", ""),
+        ]));
+
+        match result {
+            Err(SettingsError::Invalid(errors)) => assert_eq!(errors.len(), 3, "{:?}", errors),
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::auth::Claims;
+use crate::graphql::{validate_filter, ApiError, ExportFormat, MeetupUrlFilter, Role, ServerContext};
+use crate::service::export_stream;
+use async_graphql::{InputType, Pos};
+use axum::body::Body;
+use axum::extract::Query;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use serde::Deserialize;
+use tracing::log::{log, Level};
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    /// As named in GraphQL, e.g. `CSV`
+    pub format: ExportFormat,
+    /// A `MeetupUrlFilter` in JSON as passed in GraphQL variables, all rows
+    /// when missing
+    pub filter: Option<String>,
+}
+
+/// `GET /export?format=CSV&filter={"title":"Leptos"}` streams what
+/// `meetupUrlExport` returns, with the same checks of the filter
+pub async fn export_handler(
+    Extension(context): Extension<ServerContext>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let filter = match export_filter(&context, &claims, params.filter.as_deref()) {
+        Ok(filter) => filter,
+        Err(e) => return rejection(e),
+    };
+    log!(Level::Info, "Export as {:?} for {}: {:?}", params.format, claims.sub, filter);
+
+    let format = params.format;
+    (
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", format.file_name())),
+        ],
+        Body::from_stream(export_stream(context.db.clone(), filter, format)),
+    ).into_response()
+}
+
+fn export_filter(context: &ServerContext, claims: &Claims, filter: Option<&str>) -> Result<MeetupUrlFilter, ApiError> {
+    let filter = match filter {
+        None => MeetupUrlFilter::default(),
+        Some(filter) => {
+            let json = serde_json::from_str(filter)
+                .map_err(|e| ApiError::Validation(format!("filter is not JSON: {}", e)))?;
+            let value = async_graphql::Value::from_json(json)
+                .map_err(|e| ApiError::Validation(format!("filter is not JSON: {}", e)))?;
+            MeetupUrlFilter::parse(Some(value))
+                .map_err(|e| ApiError::Validation(e.into_server_error(Pos::default()).message))?
+        }
+    };
+
+    if filter.reads_trash() && !Role::Admin.granted_to(claims, &context.settings.auth.roles) {
+        return Err(ApiError::Forbidden);
+    }
+    validate_filter(&filter)?;
+    Ok(filter)
+}
+
+fn rejection(e: ApiError) -> Response {
+    let status = match e {
+        ApiError::Unauthenticated => StatusCode::UNAUTHORIZED,
+        ApiError::Forbidden => StatusCode::FORBIDDEN,
+        ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.message()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_handler, ExportParams};
+    use crate::graphql::testing::{claims, meetup_url, server_context};
+    use crate::graphql::ExportFormat;
+    use axum::body::to_bytes;
+    use axum::extract::Query;
+    use axum::http::StatusCode;
+    use axum::Extension;
+
+    #[tokio::test]
+    async fn test_export_handler() {
+        let context = server_context(vec![meetup_url("a", "actix.rs", "Actix Web"), meetup_url("b", "leptos.dev", "Leptos")]).await;
+        let export = |format: ExportFormat, filter: Option<&str>, role: &str| export_handler(
+            Extension(context.clone()),
+            Extension(claims(&[role])),
+            Query(ExportParams { format, filter: filter.map(str::to_string) }),
+        );
+
+        let response = export(ExportFormat::Json, Some(r#"{ "domain": "^leptos" }"#), "ROLE_HNL_USER").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-disposition"], "attachment; filename=\"meetup-urls.json\"");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rows: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rows.as_array().map(|rows| rows.iter().map(|row| row["uri_uuid"].clone()).collect::<Vec<_>>()), Some(vec!["b".into()]));
+
+        let response = export(ExportFormat::Csv, None, "ROLE_HNL_USER").await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.split(|byte| *byte == b'\n').filter(|line| !line.is_empty()).count(), 3);
+
+        for (filter, role, status) in [
+            (r#"{ "onlyDeleted": true }"#, "ROLE_HNL_USER", StatusCode::FORBIDDEN),
+            (r#"{ "onlyDeleted": true }"#, "ROLE_HNL_ADMIN", StatusCode::OK),
+            (r#"{ "title": "(" }"#, "ROLE_HNL_USER", StatusCode::BAD_REQUEST),
+            (r#"{ "sort": [{ "field": "NOWHERE" }] }"#, "ROLE_HNL_USER", StatusCode::BAD_REQUEST),
+            ("title", "ROLE_HNL_USER", StatusCode::BAD_REQUEST),
+        ] {
+            assert_eq!(export(ExportFormat::Markdown, Some(filter), role).await.status(), status, "{}", filter);
+        }
+    }
+}
@@ -1,27 +1,106 @@
-use async_graphql::{Enum, InputObject, SimpleObject};
+use crate::config::Settings;
+use crate::service::PageFetcher;
+use async_graphql::{Enum, InputObject, MaybeUndefined, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use surrealdb::engine::any::Any;
+use surrealdb::Surreal;
+use tokio::sync::broadcast;
 
-#[derive(Debug, Clone, Default)]
-pub struct ServerContext {}
+/// Change events a slow subscriber may fall behind before it is told to
+/// reload
+pub(crate) const CHANGE_BUFFER: usize = 1024;
+
+/// Shared state handed to every resolver. `Surreal` is a cheap handle
+/// onto one multiplexed connection, so clones share the same socket.
+#[derive(Debug, Clone)]
+pub struct ServerContext {
+    pub db: Surreal<Any>,
+    pub settings: Arc<Settings>,
+    pub pages: PageFetcher,
+    changes: broadcast::Sender<MeetupUrlChanged>,
+}
+
+impl ServerContext {
+    pub fn new(db: Surreal<Any>, settings: Arc<Settings>, pages: PageFetcher) -> Self {
+        let (changes, _) = broadcast::channel(CHANGE_BUFFER);
+        Self { db, settings, pages, changes }
+    }
+
+    /// Notifies `meetupUrlChanged` subscribers, a no-op when there are none
+    pub fn publish(&self, kind: MeetupUrlChangeKind, meetup_url: MeetupUrl) {
+        let _ = self.changes.send(MeetupUrlChanged { kind, meetup_url: Some(meetup_url) });
+    }
+
+    /// Tells subscribers to read all rows again, after changing too many to
+    /// publish one by one
+    pub fn publish_reload(&self) {
+        let _ = self.changes.send(MeetupUrlChanged::reload());
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MeetupUrlChanged> {
+        self.changes.subscribe()
+    }
+}
 
 #[derive(Enum, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum MeetupUrlSort {
-    DOMAIN,
-    TITLE,
-    URL,
-    DESCRIPTION,
+    Domain,
+    Title,
+    Url,
+    /// The description shown, `manDescr` where set, else `autoDescr`
+    Description,
+    Created,
+    Modified,
+}
+
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
 }
 
+/// One sort key, earlier keys take precedence
 #[derive(InputObject, Debug, Clone)]
+pub struct MeetupUrlOrder {
+    pub field: MeetupUrlSort,
+    #[graphql(default)]
+    pub direction: SortDirection,
+}
+
+#[derive(InputObject, Debug, Clone, Default)]
 pub struct MeetupUrlFilter {
     pub domain: Option<String>,
     pub title: Option<String>,
     pub url: Option<String>,
     pub description: Option<String>,
+    /// Created at or after
+    pub created_after: Option<DateTime<Utc>>,
+    /// Created strictly before
+    pub created_before: Option<DateTime<Utc>>,
+    pub modified_after: Option<DateTime<Utc>>,
+    pub modified_before: Option<DateTime<Utc>>,
+    /// Also rows in the trash, admins only
+    pub include_deleted: Option<bool>,
+    /// Only rows in the trash, admins only
+    pub only_deleted: Option<bool>,
+    pub tags: Option<TagFilter>,
+    /// Outcome of the last link check, rows never checked are left out
+    pub link_state: Option<LinkState>,
     pub pagination: Option<Pagination>,
-    pub sort: Option<MeetupUrlSort>,
+    pub sort: Option<Vec<MeetupUrlOrder>>,
 }
 
-#[derive(InputObject, Debug, Clone)]
+impl MeetupUrlFilter {
+    /// Asks for rows in the trash
+    pub fn reads_trash(&self) -> bool {
+        self.include_deleted == Some(true) || self.only_deleted == Some(true)
+    }
+}
+
+#[derive(InputObject, Debug, Clone, Default)]
 pub struct Pagination {
     pub current: Option<i32>,
     pub size: Option<i32>,
@@ -33,22 +112,130 @@ pub struct MeetupUrl {
     pub url: String,
     pub scheme: String,
     pub host: String,
+    pub port: Option<i32>,
     pub path: String,
+    pub query: Option<String>,
+    /// `1`/`0` in imported rows, the `LinkState` once checked
     pub live_status: String,
     pub title: String,
     pub auto_descr: String,
     pub man_descr: String,
     pub crea_user: String,
-    pub crea_time: String,
+    pub crea_time: DateTime<Utc>,
     pub modi_user: String,
-    pub modi_time: String,
+    pub modi_time: DateTime<Utc>,
+    /// Set while the row is in the trash
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: Option<String>,
+    /// By name
+    pub tags: Vec<Tag>,
+    /// `None` until the link checker got to the row
+    pub link_health: Option<LinkHealth>,
+    /// `None` until the page was read, see `refreshMetadata`
+    pub metadata: Option<PageMetadata>,
 }
 
-#[derive(SimpleObject, Debug, Clone)]
+/// What the page says about itself, besides the title and description
+/// taken over into `title` and `autoDescr`. Urls are absolute.
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct PageMetadata {
+    /// `<link rel="canonical">`
+    pub canonical_url: Option<String>,
+    /// E.g. `en-US`
+    pub language: Option<String>,
+    pub favicon: Option<String>,
+    /// `og:image` or `twitter:image`
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LinkState {
+    /// Answered with a 2xx status
+    Healthy,
+    /// Answered with a 2xx status after redirects
+    Redirected,
+    /// An error status, no answer in time or too many redirects
+    Broken,
+}
+
+/// Result of the last request of the url by the link checker
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct LinkHealth {
+    pub state: LinkState,
+    /// HTTP status of the last answer, `None` without one
+    pub status: Option<i32>,
+    /// The url of the last request, where the redirects led
+    pub final_url: String,
+    pub redirects: i32,
+    /// Of all requests, including the redirects
+    pub latency_ms: i32,
+    /// Why no answer came, e.g. a timeout
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Names are lowercase with `-` for inner whitespace, see `model::tag_name`
+#[derive(SimpleObject, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    /// Rows outside the trash carrying the tag
+    pub count: i32,
+}
+
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum TagMatch {
+    /// Rows carrying at least one of the tags
+    #[default]
+    Any,
+    /// Rows carrying every tag
+    All,
+}
+
+#[derive(InputObject, Debug, Clone)]
+pub struct TagFilter {
+    pub names: Vec<String>,
+    #[graphql(default)]
+    pub mode: TagMatch,
+}
+
+/// `current` counts from 0. Without pagination all rows form a single page.
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
 pub struct Page {
     pub current: i32,
     pub size: i32,
     pub total: i32,
+    pub total_pages: i32,
+    pub has_next: bool,
+    pub has_previous: bool,
+}
+
+impl Page {
+    pub fn new(current: i32, size: i32, total: i32) -> Self {
+        // In i64, the sum overflows i32 for sizes close to `i32::MAX`
+        let total_pages = if size > 0 { ((i64::from(total) + i64::from(size) - 1) / i64::from(size)) as i32 } else { 0 };
+        Page {
+            current,
+            size,
+            total,
+            total_pages,
+            has_next: i64::from(current) + 1 < i64::from(total_pages),
+            has_previous: current > 0,
+        }
+    }
+
+    pub fn unpaginated(total: i32) -> Self {
+        Page {
+            current: 0,
+            size: total,
+            total,
+            total_pages: total.min(1),
+            has_next: false,
+            has_previous: false,
+        }
+    }
 }
 
 #[derive(SimpleObject, Debug, Clone)]
@@ -57,11 +244,185 @@ pub struct MeetupUrlResponse {
     pub page: Page,
 }
 
+/// Column a search highlight was taken from
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SearchField {
+    Title,
+    AutoDescr,
+    ManDescr,
+    Host,
+    Url,
+}
+
+/// Piece of a highlighted column, `matched` for the query terms. Long
+/// unmatched text is shortened with `…`.
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct TextFragment {
+    pub text: String,
+    pub matched: bool,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct SearchHighlight {
+    pub field: SearchField,
+    pub fragments: Vec<TextFragment>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct SearchHit {
+    pub meetup_url: MeetupUrl,
+    /// BM25 relevance, only comparable within one search
+    pub score: f64,
+    /// Columns containing query terms
+    pub highlights: Vec<SearchHighlight>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct SearchResponse {
+    pub hits: Vec<SearchHit>,
+    pub page: Page,
+}
+
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HistoryOperation {
+    Create,
+    Update,
+    Delete,
+    /// Restored an earlier version, also of a deleted record
+    Revert,
+    /// Taken out of the trash
+    Restore,
+    /// Removed from the trash after the retention period
+    Purge,
+    /// Tags added or removed, the change names the field `tags`
+    Tag,
+}
+
+/// One column of a change, `None` where the column was not set
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct MeetupUrlHistoryEntry {
+    pub uri_uuid: String,
+    /// Counts the changes of the record from 1
+    pub version: i32,
+    pub operation: HistoryOperation,
+    pub actor: String,
+    pub time: DateTime<Utc>,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Rows of one host that probably describe the same page
+#[derive(SimpleObject, Debug, Clone)]
+pub struct DuplicateGroup {
+    /// Lowercase, without a leading `www.`
+    pub host: String,
+    pub meetup_urls: Vec<MeetupUrl>,
+}
+
+/// Extra fields of `MeetupUrlConnection`
+#[derive(SimpleObject, Debug, Clone)]
+pub struct MeetupUrlConnectionFields {
+    /// Rows matching the filter, on all pages
+    pub total_count: i32,
+}
+
 #[derive(SimpleObject, Debug, Clone)]
 pub struct MeetupUrlCount {
     pub count: i32,
 }
 
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MeetupUrlChangeKind {
+    Created,
+    Updated,
+    Deleted,
+    /// Any rows may have changed, e.g. by an import or while the subscriber
+    /// fell behind
+    Reload,
+}
+
+/// Emitted after a mutation succeeded. `Deleted` carries the record as it
+/// was, `Reload` none.
+#[derive(SimpleObject, Debug, Clone)]
+pub struct MeetupUrlChanged {
+    pub kind: MeetupUrlChangeKind,
+    pub meetup_url: Option<MeetupUrl>,
+}
+
+impl MeetupUrlChanged {
+    pub fn reload() -> Self {
+        MeetupUrlChanged { kind: MeetupUrlChangeKind::Reload, meetup_url: None }
+    }
+}
+
+/// File formats of `meetupUrlExport` and `/export`
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExportFormat {
+    /// The rows as in `data/data.json`, `importFile` reads them back
+    Json,
+    /// A header row and a row per link
+    Csv,
+    /// A link list per host
+    Markdown,
+    /// Bookmarks HTML browsers import
+    NetscapeBookmarks,
+}
+
+/// An export as a whole, for clients saving it as a file
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct MeetupUrlExport {
+    pub file_name: String,
+    pub content_type: String,
+    pub content: String,
+}
+
+/// File formats read by the imports, told apart by their content
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum ImportFormat {
+    /// JSON array of rows as exported by halreslib, like `data/data.json`
+    #[default]
+    Halreslib,
+    /// Comma, semicolon or tab separated with a header row naming the columns
+    Csv,
+    /// Bookmarks HTML as exported by Firefox and Chrome
+    NetscapeBookmarks,
+    /// `<outline>` elements carrying a url
+    Opml,
+}
+
+/// Outcome of an import, see `initDatabase` and `importFile`
+#[derive(SimpleObject, Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    /// Nothing was written, the counts tell what would have been
+    pub dry_run: bool,
+    pub format: ImportFormat,
+    pub inserted: i32,
+    /// Changed in the file since they were stored
+    pub updated: i32,
+    /// Unchanged, changed here since, or in the trash
+    pub skipped: i32,
+    pub failed: i32,
+    /// One per failed row
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(SimpleObject, Debug, Clone, PartialEq)]
+pub struct ImportRowError {
+    /// Counts the rows of the file from 1
+    pub row: i32,
+    /// `None` when the row could not be read
+    pub uri_uuid: Option<String>,
+    pub reason: String,
+}
+
 #[derive(SimpleObject)]
 pub struct ClockBox {
     pub clock: String,
@@ -69,9 +430,31 @@ pub struct ClockBox {
 
 #[derive(InputObject, Debug, Clone)]
 pub struct UpsertMeetupUrl {
+    /// Ignored, the id is derived from `url`
     pub uri_uuid: Option<String>,
+    /// Normalized before storing, see `NormalizedUrl`
     pub url: String,
-    pub host: String,
+    /// Derived from `url`, a given host must match it
+    pub host: Option<String>,
     pub title: String,
     pub auto_descr: String,
+    /// The curator's description, shown instead of `auto_descr` when set
+    pub man_descr: Option<String>,
+}
+
+/// Changes of one record: omitted fields stay as they are, an explicit
+/// `null` clears a description. `url` and `title` cannot be cleared.
+#[derive(InputObject, Debug, Clone)]
+pub struct MeetupUrlPatch {
+    pub uri_uuid: String,
+    /// Normalized before storing, see `NormalizedUrl`
+    pub url: MaybeUndefined<String>,
+    /// Derived from `url`, a given host must match it
+    pub host: Option<String>,
+    pub title: MaybeUndefined<String>,
+    pub auto_descr: MaybeUndefined<String>,
+    pub man_descr: MaybeUndefined<String>,
+    /// The `modiTime` the change is based on. The update fails with
+    /// `CONFLICT` when the record was modified since.
+    pub expected_modi_time: Option<DateTime<Utc>>,
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+use async_graphql::{Error, ErrorExtensions};
+
+/// Errors returned by resolvers. Each variant is exposed to clients with a
+/// machine-readable `extensions.code`.
+///
+/// Deliberately not `Display`: async-graphql converts any `Display` type into
+/// an `Error` without extensions, so `?` goes through the `From` below instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    Unauthenticated,
+    Forbidden,
+    NotFound(String),
+    Validation(String),
+    /// The `uri_uuid` of the record already holding the url
+    Duplicate(String),
+    /// The `uri_uuid` of a record modified by someone else in the meantime
+    Conflict(String),
+    /// Why the page of a url could not be read
+    Unreadable(String),
+    Storage(String),
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Unauthenticated => "UNAUTHENTICATED",
+            ApiError::Forbidden => "FORBIDDEN",
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::Validation(_) => "VALIDATION",
+            ApiError::Duplicate(_) => "DUPLICATE",
+            ApiError::Conflict(_) => "CONFLICT",
+            ApiError::Unreadable(_) => "UNREADABLE",
+            ApiError::Storage(_) => "STORAGE",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::Unauthenticated => "Authentication required".to_string(),
+            ApiError::Forbidden => "Not authorized for this operation".to_string(),
+            ApiError::NotFound(what) => format!("Not found: {}", what),
+            ApiError::Validation(reason) => format!("Invalid input: {}", reason),
+            ApiError::Duplicate(existing) => format!("Url already stored as {}", existing),
+            ApiError::Conflict(uri_uuid) => format!("Modified in the meantime: {}", uri_uuid),
+            ApiError::Unreadable(reason) => format!("Cannot read the page: {}", reason),
+            ApiError::Storage(_) => "Storage error".to_string(),
+        }
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(e: ApiError) -> Self {
+        // Storage details are logged, not leaked to the client
+        if let ApiError::Storage(detail) = &e {
+            tracing::error!("Storage error: {}", detail);
+        }
+        Error::new(e.message()).extend_with(|_, ext| {
+            ext.set("code", e.code());
+            if let ApiError::Duplicate(existing) = &e {
+                ext.set("uriUuid", existing.as_str());
+            }
+        })
+    }
+}
+
+impl From<surrealdb::Error> for ApiError {
+    fn from(e: surrealdb::Error) -> Self {
+        ApiError::Storage(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiError;
+    use async_graphql::{Error, Value};
+
+    #[test]
+    fn test_extensions_code() {
+        let error: Error = ApiError::NotFound("url 42".to_string()).into();
+
+        assert_eq!(error.message, "Not found: url 42");
+        assert_eq!(error.extensions.unwrap().get("code"), Some(&Value::from("NOT_FOUND")));
+    }
+
+    #[test]
+    fn test_storage_details_hidden() {
+        let error: Error = ApiError::Storage("connection refused".to_string()).into();
+
+        assert_eq!(error.message, "Storage error");
+    }
+}
@@ -0,0 +1,81 @@
+use crate::auth::Claims;
+use crate::config::RoleSettings;
+use crate::graphql::{ApiError, ServerContext};
+use async_graphql::{Context, Guard, Result};
+
+/// Application roles, ordered: each role includes the rights of the lower ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Viewer,
+    Editor,
+    Admin,
+}
+
+impl Role {
+    const ALL: [Role; 3] = [Role::Viewer, Role::Editor, Role::Admin];
+
+    /// Keycloak role names mapped to this role by configuration
+    pub fn keycloak_roles(self, settings: &RoleSettings) -> &[String] {
+        match self {
+            Role::Viewer => &settings.viewer,
+            Role::Editor => &settings.editor,
+            Role::Admin => &settings.admin,
+        }
+    }
+
+    pub fn granted_to(self, claims: &Claims, settings: &RoleSettings) -> bool {
+        Role::ALL.iter()
+            .filter(|role| **role >= self)
+            .flat_map(|role| role.keycloak_roles(settings))
+            .any(|name| claims.has_role(&settings.client_id, name))
+    }
+}
+
+/// Field guard: `#[graphql(guard = "RoleGuard::new(Role::Admin)")]`
+pub struct RoleGuard {
+    role: Role,
+}
+
+impl RoleGuard {
+    pub fn new(role: Role) -> Self {
+        Self { role }
+    }
+}
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let claims = ctx.data::<Claims>().map_err(|_| ApiError::Unauthenticated)?;
+        let settings = &ctx.data_unchecked::<ServerContext>().settings.auth.roles;
+
+        if self.role.granted_to(claims, settings) {
+            Ok(())
+        } else {
+            tracing::warn!("{} lacks role {:?} for {:?}", claims.sub, self.role, ctx.path_node.as_ref().map(|p| p.to_string()));
+            Err(ApiError::Forbidden.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Role;
+    use crate::config::Settings;
+    use crate::graphql::testing::claims;
+
+    #[test]
+    fn test_role_hierarchy() {
+        let roles = Settings::for_tests().auth.roles;
+
+        let admin = claims(&["ROLE_HNL_ADMIN"]);
+        assert!(Role::Admin.granted_to(&admin, &roles));
+        assert!(Role::Editor.granted_to(&admin, &roles));
+        assert!(Role::Viewer.granted_to(&admin, &roles));
+
+        let user = claims(&["ROLE_HNL_USER"]);
+        assert!(Role::Viewer.granted_to(&user, &roles));
+        assert!(!Role::Editor.granted_to(&user, &roles));
+        assert!(!Role::Admin.granted_to(&user, &roles));
+
+        assert!(!Role::Viewer.granted_to(&claims(&[]), &roles));
+    }
+}
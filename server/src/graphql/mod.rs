@@ -1,18 +1,55 @@
 mod domain;
+mod error;
+mod guard;
 mod query;
 mod mutation;
 mod subscription;
+#[cfg(test)]
+pub mod testing;
 
 pub use mutation::Mutation;
 pub use query::Query;
+pub(crate) use query::validate_filter;
 pub use subscription::Subscription;
 
+pub use error::ApiError;
+pub use guard::{Role, RoleGuard};
+
 pub use domain::ClockBox;
+pub use domain::DuplicateGroup;
+pub use domain::ExportFormat;
+pub use domain::FieldChange;
+pub use domain::HistoryOperation;
+pub use domain::ImportFormat;
+pub use domain::ImportReport;
+pub use domain::ImportRowError;
+pub use domain::LinkHealth;
+pub use domain::LinkState;
 pub use domain::MeetupUrl;
+pub use domain::MeetupUrlChangeKind;
+pub use domain::MeetupUrlChanged;
+pub use domain::MeetupUrlConnectionFields;
 pub use domain::MeetupUrlCount;
+pub use domain::MeetupUrlExport;
 pub use domain::MeetupUrlFilter;
+pub use domain::MeetupUrlHistoryEntry;
+pub use domain::MeetupUrlOrder;
+pub use domain::MeetupUrlPatch;
 pub use domain::MeetupUrlResponse;
 pub use domain::Page;
+pub use domain::PageMetadata;
+pub use domain::MeetupUrlSort;
+pub use domain::Pagination;
+pub use domain::SearchField;
+pub use domain::SearchHighlight;
+pub use domain::SearchHit;
+pub use domain::SearchResponse;
 pub use domain::ServerContext;
+pub use domain::SortDirection;
+pub use domain::Tag;
+#[cfg(test)]
+pub use domain::TagFilter;
+pub use domain::TagMatch;
+pub use domain::TextFragment;
 pub use domain::UpsertMeetupUrl;
 
@@ -1,97 +1,830 @@
-use crate::config::connect_db;
-use crate::graphql::{MeetupUrl, ServerContext, UpsertMeetupUrl};
-use crate::repository::{delete_by_uri_uuid, insert_meetup_url, update_meetup_url};
-use crate::service::init_database;
-use async_graphql::{Context, Object};
-use tracing::log::{log, Level};
 use crate::auth::Claims;
+use crate::graphql::{ApiError, ImportReport, MeetupUrl, MeetupUrlChangeKind, MeetupUrlPatch, Role, RoleGuard, ServerContext, UpsertMeetupUrl};
+use crate::model::{tag_name, NormalizedUrl};
+use crate::repository::{add_tags, delete_by_uri_uuid, remove_tags, find_by_url_key, insert_meetup_url, restore_meetup_url, revert_meetup_url, select_history_snapshot, update_meetup_url, Patched};
+use crate::service::{import_content, import_file, refresh_metadata, spawn_metadata_refresh, ImportError, ImportOptions};
+use async_graphql::{Context, MaybeUndefined, Object, Result, Upload};
+use surrealdb::engine::any::Any;
+use surrealdb::Surreal;
+use tracing::log::{log, Level};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Mutation;
 
+/// The normalized url of a valid `meetup_url`
+fn validate(meetup_url: &UpsertMeetupUrl) -> Result<NormalizedUrl, ApiError> {
+    if meetup_url.title.trim().is_empty() {
+        return Err(ApiError::Validation("title must not be empty".to_string()));
+    }
+    validate_url(&meetup_url.url, meetup_url.host.as_deref())
+}
+
+/// The normalized url of a valid `patch`, `None` when it keeps the url
+fn validate_patch(patch: &MeetupUrlPatch) -> Result<Option<NormalizedUrl>, ApiError> {
+    match &patch.title {
+        MaybeUndefined::Null => return Err(ApiError::Validation("title cannot be cleared".to_string())),
+        MaybeUndefined::Value(title) if title.trim().is_empty() => {
+            return Err(ApiError::Validation("title must not be empty".to_string()));
+        }
+        _ => {}
+    }
+    match &patch.url {
+        MaybeUndefined::Undefined if patch.host.is_some() => Err(ApiError::Validation("host requires url".to_string())),
+        MaybeUndefined::Undefined => Ok(None),
+        MaybeUndefined::Null => Err(ApiError::Validation("url cannot be cleared".to_string())),
+        MaybeUndefined::Value(url) => validate_url(url, patch.host.as_deref()).map(Some),
+    }
+}
+
+fn validate_url(url: &str, host: Option<&str>) -> Result<NormalizedUrl, ApiError> {
+    if url.trim().is_empty() {
+        return Err(ApiError::Validation("url must not be empty".to_string()));
+    }
+
+    let url = NormalizedUrl::parse(url).map_err(ApiError::Validation)?;
+
+    if let Some(host) = host.map(str::trim).filter(|h| !h.is_empty())
+        && !host.eq_ignore_ascii_case(&url.host)
+    {
+        return Err(ApiError::Validation(format!("host '{}' does not match the url host '{}'", host, url.host)));
+    }
+    Ok(url)
+}
+
+/// Tag names of `tags`, without repetitions
+fn validate_tags(tags: &[String]) -> Result<Vec<String>, ApiError> {
+    if tags.is_empty() {
+        return Err(ApiError::Validation("tags must not be empty".to_string()));
+    }
+    let mut names = tags.iter()
+        .map(|tag| tag_name(tag))
+        .collect::<Result<Vec<String>, String>>()
+        .map_err(ApiError::Validation)?;
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// The guards let only authenticated requests through
+fn username<'a>(ctx: &Context<'a>) -> &'a str {
+    ctx.data_opt::<Claims>().map(Claims::username).unwrap_or("api")
+}
+
+/// Fails when a record other than `own` already holds `url_key`
+async fn check_unique(db: &Surreal<Any>, url_key: String, own: Option<&str>) -> Result<(), ApiError> {
+    match find_by_url_key(db, url_key).await? {
+        Some(existing) if Some(existing.as_str()) != own => Err(ApiError::Duplicate(existing)),
+        _ => Ok(()),
+    }
+}
+
 #[Object]
 impl Mutation {
-    async fn delete_meetup_url(&self, ctx: &Context<'_>, id: String) -> i32 {
+    /// Moves the record to the trash, `restoreMeetupUrl` takes it back out
+    /// until the purge job removes it
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn delete_meetup_url(&self, ctx: &Context<'_>, id: String) -> Result<MeetupUrl> {
         log!(Level::Info, "Received Delete request: {:?}", id);
 
-        let is_admin = ctx.data::<Claims>()
-            .map(|c| c.has_role("hackandlearn-client", "ROLE_HNL_ADMIN"))
-            .unwrap_or(false);
+        let server_context = ctx.data_unchecked::<ServerContext>();
 
-        if !is_admin {
-            log!(Level::Warn, "NOT AUTHORIZED for delete_meetup_url");
-            return 0;
+        let deleted = delete_by_uri_uuid(&server_context.db, id.clone(), username(ctx))
+            .await
+            .map_err(ApiError::from)?;
+
+        let deleted = deleted.ok_or(ApiError::NotFound(id))?;
+        server_context.publish(MeetupUrlChangeKind::Deleted, deleted.clone());
+        Ok(deleted)
+    }
+
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn insert_meetup_url(&self, ctx: &Context<'_>, meetup_url: UpsertMeetupUrl) -> Result<MeetupUrl> {
+        log!(Level::Info, "Received Insert request: {:?}", meetup_url);
+
+        let url = validate(&meetup_url)?;
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        check_unique(&server_context.db, url.key(), None).await?;
+
+        // The unique index also rejects a duplicate stored since the check
+        let created = match insert_meetup_url(&server_context.db, meetup_url, url.clone(), username(ctx)).await {
+            Ok(created) => created,
+            Err(e) => {
+                check_unique(&server_context.db, url.key(), None).await?;
+                return Err(ApiError::from(e).into());
+            }
+        };
+
+        let created = created.ok_or(ApiError::Storage("insert returned no record".to_string()))?;
+        server_context.publish(MeetupUrlChangeKind::Created, created.clone());
+        if server_context.settings.metadata.fetch_on_insert {
+            spawn_metadata_refresh(server_context.clone(), created.uri_uuid.clone(), username(ctx).to_string());
         }
+        Ok(created)
+    }
 
-        let _server_context = ctx.data_unchecked::<ServerContext>();
+    /// Changes only the given fields, see `MeetupUrlPatch`
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn update_meetup_url(&self, ctx: &Context<'_>, meetup_url: MeetupUrlPatch) -> Result<MeetupUrl> {
+        log!(Level::Info, "Received Update request: {:?}", meetup_url);
 
-        let client = connect_db().await;
+        let url = validate_patch(&meetup_url)?;
+        let uri_uuid = meetup_url.uri_uuid.clone();
+        let url_key = url.as_ref().map(NormalizedUrl::key);
 
-        let result = delete_by_uri_uuid(&client, id).await;
+        let server_context = ctx.data_unchecked::<ServerContext>();
 
-        if result.is_ok() {
-            1
-        } else {
-            0
+        if let Some(url_key) = &url_key {
+            check_unique(&server_context.db, url_key.clone(), Some(&uri_uuid)).await?;
         }
+
+        let patched = match update_meetup_url(&server_context.db, meetup_url, url, username(ctx)).await {
+            Ok(patched) => patched,
+            Err(e) => {
+                if let Some(url_key) = url_key {
+                    check_unique(&server_context.db, url_key, Some(&uri_uuid)).await?;
+                }
+                return Err(ApiError::from(e).into());
+            }
+        };
+
+        let updated = match patched {
+            Patched::Updated(updated) => *updated,
+            Patched::NotFound => return Err(ApiError::NotFound(uri_uuid).into()),
+            Patched::Conflict => return Err(ApiError::Conflict(uri_uuid).into()),
+        };
+        server_context.publish(MeetupUrlChangeKind::Updated, updated.clone());
+        Ok(updated)
     }
 
-    async fn insert_meetup_url(&self, ctx: &Context<'_>, meetup_url: UpsertMeetupUrl) -> MeetupUrl {
-        log!(Level::Info, "Received Insert request: {:?}", meetup_url);
+    /// Reads the page again: a title and description found replace `title`
+    /// and `autoDescr`, `manDescr` stays as it is. Inserted urls are read
+    /// once on their own.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn refresh_metadata(&self, ctx: &Context<'_>, uri_uuid: String) -> Result<MeetupUrl> {
+        log!(Level::Info, "Received Refresh Metadata request: {:?}", uri_uuid);
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let refreshed = refresh_metadata(&server_context.db, &server_context.pages, uri_uuid, username(ctx)).await?;
+
+        server_context.publish(MeetupUrlChangeKind::Updated, refreshed.clone());
+        Ok(refreshed)
+    }
+
+    /// Takes the record out of the trash
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn restore_meetup_url(&self, ctx: &Context<'_>, uri_uuid: String) -> Result<MeetupUrl> {
+        log!(Level::Info, "Received Restore request: {:?}", uri_uuid);
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let restored = restore_meetup_url(&server_context.db, uri_uuid.clone(), username(ctx))
+            .await
+            .map_err(ApiError::from)?;
 
-        let is_admin = ctx.data::<Claims>()
-            .map(|c| c.has_role("hackandlearn-client", "ROLE_HNL_ADMIN"))
-            .unwrap_or(false);
+        let restored = restored.ok_or(ApiError::NotFound(uri_uuid))?;
+        server_context.publish(MeetupUrlChangeKind::Created, restored.clone());
+        Ok(restored)
+    }
+
+    /// Restores the record as it was at `version` of its history, also after
+    /// a delete. The revert is appended to the history as a new version.
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn revert_meetup_url(&self, ctx: &Context<'_>, uri_uuid: String, version: i32) -> Result<MeetupUrl> {
+        log!(Level::Info, "Received Revert request: {} to version {}", uri_uuid, version);
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
 
-        if !is_admin {
-            log!(Level::Warn, "NOT AUTHORIZED for insert_meetup_url");
-            return MeetupUrl::default()
+        let mut snapshot = select_history_snapshot(&server_context.db, uri_uuid.clone(), version)
+            .await
+            .map_err(ApiError::from)?
+            .ok_or(ApiError::NotFound(format!("{} version {}", uri_uuid, version)))?;
+
+        // Rows stored before `url_key` existed may lack it
+        if snapshot.url_key.is_none() {
+            snapshot.url_key = NormalizedUrl::parse(&snapshot.url).ok().map(|url| url.key());
+        }
+        let url_key = snapshot.url_key.clone();
+        if let Some(url_key) = &url_key {
+            check_unique(&server_context.db, url_key.clone(), Some(&uri_uuid)).await?;
         }
 
-        let _server_context = ctx.data_unchecked::<ServerContext>();
+        let (reverted, recreated) = match revert_meetup_url(&server_context.db, snapshot, username(ctx)).await {
+            Ok(reverted) => reverted,
+            Err(e) => {
+                if let Some(url_key) = url_key {
+                    check_unique(&server_context.db, url_key, Some(&uri_uuid)).await?;
+                }
+                return Err(ApiError::from(e).into());
+            }
+        };
 
-        let client = connect_db().await;
+        let reverted = reverted.ok_or(ApiError::Storage("revert returned no record".to_string()))?;
+        let kind = if recreated { MeetupUrlChangeKind::Created } else { MeetupUrlChangeKind::Updated };
+        server_context.publish(kind, reverted.clone());
+        Ok(reverted)
+    }
+
+    /// Tags are created on first use, names are normalized, see `Tag`
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn add_tags(&self, ctx: &Context<'_>, uri_uuid: String, tags: Vec<String>) -> Result<MeetupUrl> {
+        log!(Level::Info, "Received Add Tags request: {} {:?}", uri_uuid, tags);
+
+        let names = validate_tags(&tags)?;
 
-        let result = insert_meetup_url(&client, meetup_url).await;
+        let server_context = ctx.data_unchecked::<ServerContext>();
 
-        result.unwrap()
+        let tagged = add_tags(&server_context.db, uri_uuid.clone(), names, username(ctx))
+            .await
+            .map_err(ApiError::from)?;
+
+        let tagged = tagged.ok_or(ApiError::NotFound(uri_uuid))?;
+        server_context.publish(MeetupUrlChangeKind::Updated, tagged.clone());
+        Ok(tagged)
     }
 
-    async fn update_meetup_url(&self, ctx: &Context<'_>, meetup_url: UpsertMeetupUrl) -> MeetupUrl {
-        log!(Level::Info, "Received Update request: {:?}", meetup_url);
+    /// Tags no record carries any more are dropped
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn remove_tags(&self, ctx: &Context<'_>, uri_uuid: String, tags: Vec<String>) -> Result<MeetupUrl> {
+        log!(Level::Info, "Received Remove Tags request: {} {:?}", uri_uuid, tags);
 
-        let is_admin = ctx.data::<Claims>()
-            .map(|c| c.has_role("hackandlearn-client", "ROLE_HNL_ADMIN"))
-            .unwrap_or(false);
+        let names = validate_tags(&tags)?;
 
-        if !is_admin {
-            log!(Level::Warn, "NOT AUTHORIZED for update_meetup_url");
-            return MeetupUrl::default()
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let untagged = remove_tags(&server_context.db, uri_uuid.clone(), names, username(ctx))
+            .await
+            .map_err(ApiError::from)?;
+
+        let untagged = untagged.ok_or(ApiError::NotFound(uri_uuid))?;
+        server_context.publish(MeetupUrlChangeKind::Updated, untagged.clone());
+        Ok(untagged)
+    }
+
+    /// Imports the rows of the configured data file. Rows already stored
+    /// are updated only when changed in the file since, so the import can
+    /// run again. A dry run reports what would be written.
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn init_database(&self, ctx: &Context<'_>, #[graphql(default)] dry_run: bool) -> Result<ImportReport> {
+        log!(Level::Info, "Init Database, dry run: {}", dry_run);
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+        let settings = &server_context.settings.import;
+        let options = ImportOptions { dry_run, batch_size: settings.batch_size };
+
+        let imported = import_file(&server_context.db, &settings.data_file, options, username(ctx))
+            .await
+            .map_err(|e| match e {
+                ImportError::Read(reason) => ApiError::Validation(reason),
+                ImportError::Storage(e) => ApiError::Storage(e.to_string()),
+            })?;
+
+        if !imported.changed.is_empty() {
+            server_context.publish_reload();
         }
+        Ok(imported.report)
+    }
+
+    /// Imports an uploaded file like `initDatabase` imports the data file.
+    /// Besides the halreslib JSON it reads CSV with a header row, bookmarks
+    /// exported by browsers and OPML, told apart by the content. Rows the
+    /// file names no creator of are created by the uploader.
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn import_file(&self, ctx: &Context<'_>, file: Upload, #[graphql(default)] dry_run: bool) -> Result<ImportReport> {
+        let upload = file.value(ctx).map_err(|e| ApiError::Validation(format!("cannot read the upload: {}", e)))?;
+        log!(Level::Info, "Import of {}, dry run: {}", upload.filename, dry_run);
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+        let options = ImportOptions { dry_run, batch_size: server_context.settings.import.batch_size };
 
-        let _server_context = ctx.data_unchecked::<ServerContext>();
+        let imported = import_content(&server_context.db, &upload.content, options, username(ctx))
+            .await
+            .map_err(|e| match e {
+                ImportError::Read(reason) => ApiError::Validation(format!("{}: {}", upload.filename, reason)),
+                ImportError::Storage(e) => ApiError::Storage(e.to_string()),
+            })?;
+
+        if !imported.changed.is_empty() {
+            server_context.publish_reload();
+        }
+        Ok(imported.report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphql::testing::{claims, error_code, execute, meetup_url, schema};
+    use serde_json::json;
+
+    const ADMIN: &str = "ROLE_HNL_ADMIN";
+
+    const INSERT: &str = r#"
+        mutation ($m: UpsertMeetupUrl!) { insertMeetupUrl(meetupUrl: $m) { uriUuid title } }
+    "#;
+
+    const UPDATE: &str = r#"
+        mutation ($m: MeetupUrlPatch!) { updateMeetupUrl(meetupUrl: $m) { uriUuid title } }
+    "#;
+
+    const DELETE: &str = r#"
+        mutation ($id: String!) { deleteMeetupUrl(id: $id) { uriUuid } }
+    "#;
+
+    fn upsert(uri_uuid: Option<&str>, title: &str) -> serde_json::Value {
+        json!({ "m": { "uriUuid": uri_uuid, "url": "https://leptos.dev/", "host": "leptos.dev", "title": title, "autoDescr": "" } })
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_and_forbidden() {
+        let schema = schema(vec![]).await;
+
+        let response = execute(&schema, INSERT, upsert(None, "Leptos"), None).await;
+        assert_eq!(error_code(&response).as_deref(), Some("UNAUTHENTICATED"));
+
+        let response = execute(&schema, INSERT, upsert(None, "Leptos"), Some(claims(&[]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+
+        let response = execute(&schema, "mutation { initDatabase { inserted } }", json!({}), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+    }
+
+    #[tokio::test]
+    async fn test_init_database_without_data_file() {
+        use crate::graphql::testing::server_context;
+        use crate::graphql::{Mutation, Query, Subscription};
+        use std::sync::Arc;
+
+        let mut context = server_context(vec![]).await;
+        let mut settings = (*context.settings).clone();
+        settings.import.data_file = "../data/missing.json".to_string();
+        context.settings = Arc::new(settings);
+        let schema = crate::Schema::build(Query, Mutation, Subscription {}).data(context).finish();
+
+        let response = execute(&schema, "mutation { initDatabase { inserted } }", json!({}), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"));
+        assert!(response.errors[0].message.contains("missing.json"), "{:?}", response.errors);
+    }
 
-        let client = connect_db().await;
+    #[tokio::test]
+    async fn test_not_found() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web")]).await;
 
-        let result = update_meetup_url(&client, meetup_url).await;
+        let response = execute(&schema, DELETE, json!({ "id": "unknown" }), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("NOT_FOUND"));
 
-        result.unwrap()
+        let response = execute(&schema, UPDATE, upsert(Some("unknown"), "Leptos"), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("NOT_FOUND"));
     }
 
-    async fn init_database(&self, ctx: &Context<'_>) -> i32 {
-        log!(Level::Info, "Init Database");
+    #[tokio::test]
+    async fn test_validation() {
+        let schema = schema(vec![]).await;
 
-        let is_admin = ctx.data::<Claims>()
-            .map(|c| c.has_role("hackandlearn-client", "ROLE_HNL_ADMIN"))
-            .unwrap_or(false);
+        let response = execute(&schema, INSERT, upsert(None, " "), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"));
 
-        if is_admin {
-            let client = connect_db().await;
-            init_database(&client).await;
-            return 1;
-        } else {
-            log!(Level::Warn, "NOT AUTHORIZED for init_database");
-            return 0;
+        for patch in [json!({ "uriUuid": "a", "title": null }), json!({ "uriUuid": "a", "url": null }), json!({ "uriUuid": "a", "host": "actix.rs" })] {
+            let response = execute(&schema, UPDATE, json!({ "m": patch }), Some(claims(&[ADMIN]))).await;
+            assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"), "{}", patch);
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_url_normalized() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web")]).await;
+        let insert = r#"
+            mutation ($m: UpsertMeetupUrl!) { insertMeetupUrl(meetupUrl: $m) { url scheme host port path query } }
+        "#;
+        let submit = |url: &str, host: Option<&str>| json!({ "m": { "url": url, "host": host, "title": "Leptos", "autoDescr": "" } });
+
+        let response = execute(&schema, insert, submit("HTTPS://Leptos.DEV:443/book/?utm_source=mail&lang=en", None), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["insertMeetupUrl"], json!({
+            "url": "https://leptos.dev/book?lang=en", "scheme": "https", "host": "leptos.dev", "port": null, "path": "/book", "query": "lang=en"
+        }));
+
+        for (url, host) in [("leptos.dev", None), ("ftp://leptos.dev/", None), ("https://leptos.dev/", Some("actix.rs"))] {
+            let response = execute(&schema, insert, submit(url, host), Some(claims(&[ADMIN]))).await;
+            assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"), "{} {:?}", url, host);
+        }
+
+        let update = r#"
+            mutation ($m: MeetupUrlPatch!) { updateMeetupUrl(meetupUrl: $m) { url host port path } }
+        "#;
+        let changed = json!({ "m": { "uriUuid": "a", "url": "http://Actix.rs:8080/docs/", "title": "Actix", "autoDescr": "" } });
+        let response = execute(&schema, update, changed, Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["updateMeetupUrl"], json!({
+            "url": "http://actix.rs:8080/docs", "host": "actix.rs", "port": 8080, "path": "/docs"
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web")]).await;
+        let submit = |uri_uuid: Option<&str>, url: &str| json!({ "m": { "uriUuid": uri_uuid, "url": url, "title": "Leptos", "autoDescr": "" } });
+
+        let response = execute(&schema, INSERT, submit(None, "http://ACTIX.rs/a/"), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("DUPLICATE"));
+        let extensions = response.errors[0].extensions.as_ref().unwrap();
+        assert_eq!(extensions.get("uriUuid"), Some(&async_graphql::Value::from("a")));
+
+        let response = execute(&schema, INSERT, submit(None, "https://leptos.dev/"), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let leptos = response.data.into_json().unwrap()["insertMeetupUrl"]["uriUuid"].as_str().unwrap().to_string();
+
+        // Keeping its own url is no conflict, taking another one is
+        let response = execute(&schema, UPDATE, submit(Some("a"), "https://actix.rs/a"), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let response = execute(&schema, UPDATE, submit(Some(&leptos), "HTTPS://actix.rs/a/"), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("DUPLICATE"));
+    }
+
+    #[tokio::test]
+    async fn test_moved_url_keeps_its_id() {
+        let schema = schema(vec![]).await;
+        let submit = |uri_uuid: Option<&str>, url: &str| json!({ "m": { "uriUuid": uri_uuid, "url": url, "title": "Leptos", "autoDescr": "" } });
+
+        let response = execute(&schema, INSERT, submit(None, "https://leptos.dev/"), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let leptos = response.data.into_json().unwrap()["insertMeetupUrl"]["uriUuid"].as_str().unwrap().to_string();
+        let response = execute(&schema, UPDATE, submit(Some(&leptos), "https://book.leptos.dev/"), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        // The old url is free, its hashed id is not
+        let response = execute(&schema, INSERT, submit(None, "https://leptos.dev/"), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let inserted = response.data.into_json().unwrap()["insertMeetupUrl"]["uriUuid"].as_str().unwrap().to_string();
+        assert_ne!(inserted, leptos);
+        assert_eq!(inserted.len(), 36);
+    }
+
+    #[tokio::test]
+    async fn test_audit_fields() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web")]).await;
+        let update = r#"
+            mutation ($m: MeetupUrlPatch!) { updateMeetupUrl(meetupUrl: $m) { creaUser creaTime modiUser modiTime } }
+        "#;
+        let mut anonymous = claims(&[ADMIN]);
+        anonymous.preferred_username = None;
+
+        let response = execute(&schema, update, upsert(Some("a"), "Actix"), Some(anonymous)).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let updated = response.data.into_json().unwrap()["updateMeetupUrl"].clone();
+        assert_eq!(updated["creaUser"], "api");
+        assert_eq!(updated["creaTime"], "2024-08-29T15:48:10.384049834+00:00");
+        assert_eq!(updated["modiUser"], "0b7e7c4e-test");
+        assert!(updated["modiTime"].as_str().unwrap() > "2024-08-30");
+
+        let insert = r#"
+            mutation ($m: UpsertMeetupUrl!) { insertMeetupUrl(meetupUrl: $m) { creaUser modiUser } }
+        "#;
+        let tokio = json!({ "m": { "url": "https://tokio.rs/", "title": "Tokio", "autoDescr": "" } });
+        let response = execute(&schema, insert, tokio, Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["insertMeetupUrl"], json!({ "creaUser": "curator", "modiUser": "curator" }));
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web")]).await;
+
+        let response = execute(&schema, UPDATE, upsert(Some("a"), "Actix"), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["updateMeetupUrl"], json!({ "uriUuid": "a", "title": "Actix" }));
+
+        let response = execute(&schema, DELETE, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["deleteMeetupUrl"]["uriUuid"], "a");
+
+        let response = execute(&schema, DELETE, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("NOT_FOUND"));
+    }
+
+    #[tokio::test]
+    async fn test_history_and_revert() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web")]).await;
+        let history = r#"
+            query ($id: String!) { meetupUrlHistory(uriUuid: $id) { version operation actor changes { field before after } } }
+        "#;
+        let revert = r#"
+            mutation ($id: String!, $v: Int!) { revertMeetupUrl(uriUuid: $id, version: $v) { uriUuid title url modiUser } }
+        "#;
+        let rename = json!({ "m": { "uriUuid": "a", "url": "https://actix.rs/a", "title": "Actix", "autoDescr": "" } });
+
+        execute(&schema, UPDATE, rename, Some(claims(&[ADMIN]))).await;
+        execute(&schema, DELETE, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+
+        let response = execute(&schema, history, json!({ "id": "a" }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+
+        let response = execute(&schema, history, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let entries = response.data.into_json().unwrap()["meetupUrlHistory"].clone();
+        let entries = entries.as_array().unwrap();
+        assert_eq!(entries.iter().map(|e| (e["version"].as_i64().unwrap(), e["operation"].as_str().unwrap(), e["actor"].as_str().unwrap()))
+            .collect::<Vec<_>>(), vec![(1, "CREATE", "api"), (2, "UPDATE", "curator"), (3, "DELETE", "curator")]);
+        let changes = entries[1]["changes"].as_array().unwrap();
+        assert!(changes.contains(&json!({ "field": "title", "before": "Actix Web", "after": "Actix" })));
+        assert!(changes.contains(&json!({ "field": "modi_user", "before": "api", "after": "curator" })));
+        assert!(!changes.iter().any(|c| c["field"] == "crea_user" || c["field"] == "url_key"));
+
+        let response = execute(&schema, revert, json!({ "id": "a", "v": 9 }), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("NOT_FOUND"));
+
+        // The deleted record comes back as it was before the update
+        let response = execute(&schema, revert, json!({ "id": "a", "v": 1 }), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["revertMeetupUrl"], json!({
+            "uriUuid": "a", "title": "Actix Web", "url": "https://actix.rs/a", "modiUser": "curator"
+        }));
+
+        let response = execute(&schema, history, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+        let entries = response.data.into_json().unwrap()["meetupUrlHistory"].clone();
+        assert_eq!(entries[3]["version"], 4);
+        assert_eq!(entries[3]["operation"], "REVERT");
+
+        // A revert to a delete brings the row back as it was before, not into the trash
+        execute(&schema, DELETE, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+        let revert_to_delete = r#"
+            mutation { revertMeetupUrl(uriUuid: "a", version: 3) { title deletedAt } }
+        "#;
+        let response = execute(&schema, revert_to_delete, json!({}), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["revertMeetupUrl"], json!({ "title": "Actix", "deletedAt": null }));
+
+        // A revert onto a url another record took is a duplicate
+        let leptos = json!({ "m": { "url": "https://leptos.dev/", "title": "Leptos", "autoDescr": "" } });
+        execute(&schema, INSERT, leptos, Some(claims(&[ADMIN]))).await;
+        let moved = json!({ "m": { "uriUuid": "a", "url": "https://actix.rs/b", "title": "Actix", "autoDescr": "" } });
+        execute(&schema, UPDATE, moved, Some(claims(&[ADMIN]))).await;
+        let taken = json!({ "m": { "url": "https://actix.rs/a", "title": "Taken", "autoDescr": "" } });
+        let response = execute(&schema, INSERT, taken, Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let response = execute(&schema, revert, json!({ "id": "a", "v": 4 }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+        let response = execute(&schema, revert, json!({ "id": "a", "v": 4 }), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("DUPLICATE"));
+    }
+
+    #[tokio::test]
+    async fn test_trash_and_restore() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web"), meetup_url("b", "leptos.dev", "Leptos")]).await;
+        let list = r#"
+            query ($f: MeetupUrlFilter!) { meetupUrlList(filter: $f) { result { uriUuid deletedBy } } meetupUrlCount(filter: $f) { count } }
+        "#;
+        let restore = r#"
+            mutation ($id: String!) { restoreMeetupUrl(uriUuid: $id) { uriUuid deletedAt } }
+        "#;
+        let listed = |response: async_graphql::Response| {
+            assert!(response.errors.is_empty(), "{:?}", response.errors);
+            let data = response.data.into_json().unwrap();
+            let uuids = data["meetupUrlList"]["result"].as_array().unwrap().iter()
+                .map(|u| (u["uriUuid"].as_str().unwrap().to_string(), u["deletedBy"].as_str().map(str::to_string)))
+                .collect::<Vec<_>>();
+            (uuids, data["meetupUrlCount"]["count"].as_i64().unwrap())
+        };
+
+        let response = execute(&schema, DELETE, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let response = execute(&schema, list, json!({ "f": {} }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(listed(response), (vec![("b".to_string(), None)], 1));
+
+        let response = execute(&schema, list, json!({ "f": { "onlyDeleted": true } }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+
+        let response = execute(&schema, list, json!({ "f": { "onlyDeleted": true } }), Some(claims(&[ADMIN]))).await;
+        assert_eq!(listed(response), (vec![("a".to_string(), Some("curator".to_string()))], 1));
+        let response = execute(&schema, list, json!({ "f": { "includeDeleted": true } }), Some(claims(&[ADMIN]))).await;
+        assert_eq!(listed(response).1, 2);
+
+        // A deleted record cannot be edited, only restored
+        let response = execute(&schema, UPDATE, json!({ "m": { "uriUuid": "a", "url": "https://actix.rs/", "title": "Actix", "autoDescr": "" } }), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("NOT_FOUND"));
+
+        let response = execute(&schema, restore, json!({ "id": "a" }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+        let response = execute(&schema, restore, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["restoreMeetupUrl"], json!({ "uriUuid": "a", "deletedAt": null }));
+        let response = execute(&schema, restore, json!({ "id": "a" }), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("NOT_FOUND"));
+
+        let response = execute(&schema, list, json!({ "f": {} }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(listed(response).1, 2);
+    }
+
+    #[tokio::test]
+    async fn test_manual_description() {
+        let schema = schema(vec![]).await;
+        let insert = r#"
+            mutation ($m: UpsertMeetupUrl!) { insertMeetupUrl(meetupUrl: $m) { uriUuid autoDescr manDescr } }
+        "#;
+        let update = r#"
+            mutation ($m: MeetupUrlPatch!) { updateMeetupUrl(meetupUrl: $m) { autoDescr manDescr } }
+        "#;
+        let submit = |uri_uuid: Option<&str>, auto_descr: &str, man_descr: Option<&str>| json!({ "m": {
+            "uriUuid": uri_uuid, "url": "https://leptos.dev/", "title": "Leptos", "autoDescr": auto_descr, "manDescr": man_descr
+        } });
+
+        let response = execute(&schema, insert, submit(None, "A web framework", Some("Our favourite")), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let created = response.data.into_json().unwrap()["insertMeetupUrl"].clone();
+        assert_eq!((&created["autoDescr"], &created["manDescr"]), (&json!("A web framework"), &json!("Our favourite")));
+        let uri_uuid = created["uriUuid"].as_str().unwrap();
+
+        // A new automatic description leaves the curator's alone
+        let response = execute(&schema, update, json!({ "m": { "uriUuid": uri_uuid, "autoDescr": "Build fast web apps" } }), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["updateMeetupUrl"], json!({ "autoDescr": "Build fast web apps", "manDescr": "Our favourite" }));
+
+        let response = execute(&schema, update, submit(Some(uri_uuid), "Build fast web apps", Some("")), Some(claims(&[ADMIN]))).await;
+        assert_eq!(response.data.into_json().unwrap()["updateMeetupUrl"]["manDescr"], "");
+    }
+
+    #[tokio::test]
+    async fn test_patch() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web")]).await;
+        let update = r#"
+            mutation ($m: MeetupUrlPatch!) { updateMeetupUrl(meetupUrl: $m) { url title autoDescr manDescr modiTime } }
+        "#;
+        let patch = |m: serde_json::Value| json!({ "m": m });
+
+        let response = execute(&schema, update, patch(json!({ "uriUuid": "a", "manDescr": "Fast" })), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let patched = response.data.into_json().unwrap()["updateMeetupUrl"].clone();
+        assert_eq!((&patched["url"], &patched["title"], &patched["autoDescr"], &patched["manDescr"]),
+                   (&json!("https://actix.rs/a"), &json!("Actix Web"), &json!("Actix Web"), &json!("Fast")));
+        let modi_time = patched["modiTime"].clone();
+
+        // Based on the current version, an explicit null clears
+        let response = execute(&schema, update, patch(json!({ "uriUuid": "a", "autoDescr": null, "expectedModiTime": modi_time })), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let patched = response.data.into_json().unwrap()["updateMeetupUrl"].clone();
+        assert_eq!((&patched["autoDescr"], &patched["manDescr"]), (&json!(""), &json!("Fast")));
+
+        // Based on the version before the last change
+        let response = execute(&schema, update, patch(json!({ "uriUuid": "a", "title": "Actix", "expectedModiTime": modi_time })), Some(claims(&[ADMIN]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("CONFLICT"));
+        let response = execute(&schema, update, patch(json!({ "uriUuid": "a", "title": "Actix", "expectedModiTime": patched["modiTime"] })), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+
+    #[tokio::test]
+    async fn test_tags() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web"), meetup_url("b", "leptos.dev", "Leptos")]).await;
+        let add = r#"
+            mutation ($id: String!, $tags: [String!]!) { addTags(uriUuid: $id, tags: $tags) { tags { name count } } }
+        "#;
+        let remove = r#"
+            mutation ($id: String!, $tags: [String!]!) { removeTags(uriUuid: $id, tags: $tags) { tags { name count } } }
+        "#;
+        let list = r#"
+            query ($f: MeetupUrlFilter!) { meetupUrlList(filter: $f) { result { uriUuid } } }
+        "#;
+        let editor = || Some(claims(&["ROLE_HNL_EDITOR"]));
+        let uuids = |response: async_graphql::Response| {
+            assert!(response.errors.is_empty(), "{:?}", response.errors);
+            response.data.into_json().unwrap()["meetupUrlList"]["result"].as_array().unwrap().iter()
+                .map(|u| u["uriUuid"].as_str().unwrap().to_string())
+                .collect::<Vec<String>>()
+        };
+
+        let response = execute(&schema, add, json!({ "id": "a", "tags": ["rust"] }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+        let response = execute(&schema, add, json!({ "id": "a", "tags": [" "] }), editor()).await;
+        assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"));
+        let response = execute(&schema, add, json!({ "id": "unknown", "tags": ["rust"] }), editor()).await;
+        assert_eq!(error_code(&response).as_deref(), Some("NOT_FOUND"));
+
+        execute(&schema, add, json!({ "id": "b", "tags": ["Rust"] }), editor()).await;
+        let response = execute(&schema, add, json!({ "id": "a", "tags": ["rust", "Web  Framework", "web-framework"] }), editor()).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["addTags"]["tags"], json!([
+            { "name": "rust", "count": 2 }, { "name": "web-framework", "count": 1 }
+        ]));
+        // Adding a tag twice changes nothing
+        let response = execute(&schema, add, json!({ "id": "a", "tags": ["RUST"] }), editor()).await;
+        assert_eq!(response.data.into_json().unwrap()["addTags"]["tags"].as_array().unwrap().len(), 2);
+
+        let any = json!({ "f": { "tags": { "names": ["web framework", "rust"] }, "sort": [{ "field": "DOMAIN" }] } });
+        assert_eq!(uuids(execute(&schema, list, any, None).await), vec!["a", "b"]);
+        let all = json!({ "f": { "tags": { "names": ["web framework", "rust"], "mode": "ALL" } } });
+        assert_eq!(uuids(execute(&schema, list, all, None).await), vec!["a"]);
+
+        let response = execute(&schema, remove, json!({ "id": "a", "tags": ["web-framework", "go"] }), editor()).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["removeTags"]["tags"], json!([{ "name": "rust", "count": 2 }]));
+
+        // Unused tags are gone, rows in the trash do not count
+        execute(&schema, DELETE, json!({ "id": "b" }), Some(claims(&[ADMIN]))).await;
+        let response = execute(&schema, "{ tags { name count } }", json!({}), None).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["tags"], json!([{ "name": "rust", "count": 1 }]));
+
+        // Each change of the tags is in the history, adding one twice is none
+        let history = r#"
+            query { meetupUrlHistory(uriUuid: "a") { operation actor changes { field before after } } }
+        "#;
+        let response = execute(&schema, history, json!({}), Some(claims(&[ADMIN]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let entries = response.data.into_json().unwrap()["meetupUrlHistory"].clone();
+        assert_eq!(entries, json!([
+            { "operation": "CREATE", "actor": "api", "changes": entries[0]["changes"] },
+            { "operation": "TAG", "actor": "curator", "changes": [{ "field": "tags", "before": null, "after": "rust, web-framework" }] },
+            { "operation": "TAG", "actor": "curator", "changes": [{ "field": "tags", "before": "rust, web-framework", "after": "rust" }] },
+        ]));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_metadata() {
+        use axum::response::Html;
+        use axum::routing::get;
+
+        let app = axum::Router::new()
+            .route("/page", get(|| async { Html(r#"<html lang="en"><head><title>Actix &amp; Co</title>
+                <meta name="description" content="A powerful web framework"><link rel="icon" href="/icon.png"></head></html>"#) }))
+            .route("/text", get(|| async { "plain" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let row = |uri_uuid: &str, path: &str| crate::model::MeetupUrl {
+            url: format!("{base}{path}"),
+            man_descr: "Our favourite".to_string(),
+            ..meetup_url(uri_uuid, "127.0.0.1", "Mock")
+        };
+        let schema = schema(vec![row("a", "/page"), row("b", "/text"), row("c", "/missing")]).await;
+        let refresh = r#"
+            mutation ($id: String!) { refreshMetadata(uriUuid: $id) { title autoDescr manDescr modiUser metadata { language favicon canonicalUrl } } }
+        "#;
+        let editor = || Some(claims(&["ROLE_HNL_EDITOR"]));
+
+        let response = execute(&schema, refresh, json!({ "id": "a" }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+
+        let response = execute(&schema, refresh, json!({ "id": "a" }), editor()).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["refreshMetadata"], json!({
+            "title": "Actix & Co",
+            "autoDescr": "A powerful web framework",
+            "manDescr": "Our favourite",
+            "modiUser": "curator",
+            "metadata": { "language": "en", "favicon": format!("{base}/icon.png"), "canonicalUrl": null }
+        }));
+
+        for (uri_uuid, code) in [("b", "UNREADABLE"), ("c", "UNREADABLE"), ("unknown", "NOT_FOUND")] {
+            let response = execute(&schema, refresh, json!({ "id": uri_uuid }), editor()).await;
+            assert_eq!(error_code(&response).as_deref(), Some(code), "{}", uri_uuid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_file() {
+        use async_graphql::{Request, UploadValue, Variables};
+
+        let schema = schema(vec![meetup_url("a", "tokio.rs", "Tokio")]).await;
+        let upload = |content: &str, dry_run: bool, role: &str| {
+            let mut request = Request::new(r#"
+                mutation ($file: Upload!, $dryRun: Boolean!) {
+                    importFile(file: $file, dryRun: $dryRun) { dryRun format inserted updated skipped failed errors { row reason } }
+                }
+            "#).variables(Variables::from_json(json!({ "file": null, "dryRun": dry_run })));
+            request.set_upload("variables.file", UploadValue {
+                filename: "links.csv".to_string(),
+                content_type: Some("text/csv".to_string()),
+                content: content.to_string().into_bytes().into(),
+            });
+            schema.execute(request.data(claims(&[role])))
+        };
+        // The first row is the stored one
+        let csv = "url,title,description\nhttps://tokio.rs/a,Tokio,Tokio\nhttps://axum.rs,Axum,\nnot a url,Broken,\n";
+
+        let response = upload(csv, false, "ROLE_HNL_EDITOR").await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+
+        let response = upload(csv, true, ADMIN).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["importFile"]["inserted"], json!(1));
+
+        let response = upload(csv, false, ADMIN).await;
+        assert_eq!(response.data.into_json().unwrap()["importFile"], json!({
+            "dryRun": false,
+            "format": "CSV",
+            "inserted": 1,
+            "updated": 0,
+            "skipped": 1,
+            "failed": 1,
+            "errors": [{ "row": 3, "reason": "'not a url' is not a valid URL: relative URL without a base" }]
+        }));
+
+        let response = execute(&schema, "{ meetupUrlCount(filter: {}) { count } }", json!({}), Some(claims(&[ADMIN]))).await;
+        assert_eq!(response.data.into_json().unwrap()["meetupUrlCount"]["count"], json!(2));
+
+        let response = upload("title\nno links here\n", false, ADMIN).await;
+        assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"));
+    }
+}
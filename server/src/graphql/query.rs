@@ -1,45 +1,573 @@
-use crate::config::connect_db;
-use crate::graphql::{MeetupUrlCount, MeetupUrlFilter, MeetupUrlResponse, Page, ServerContext};
-use crate::repository::{count_url, select_url};
-use async_graphql::{Context, Object};
+use crate::graphql::{ApiError, DuplicateGroup, ExportFormat, MeetupUrl, MeetupUrlExport, MeetupUrlConnectionFields, MeetupUrlCount, MeetupUrlFilter, MeetupUrlHistoryEntry, MeetupUrlResponse, Page, Pagination, SearchResponse, Role, RoleGuard, ServerContext, Tag};
+use crate::model::tag_name;
+use crate::service::{duplicate_candidates, export};
+use crate::repository::{count_url, search_url, select_history, select_tags, select_url_slice, select_url_with_count, OrderBy, Seek, UrlCursor};
+use async_graphql::connection::{query, Connection, Edge, OpaqueCursor};
+use async_graphql::{Context, Guard, Object, Result};
+use regex::Regex;
 use tracing::log::{log, Level};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Query;
 
+/// Edges per page when neither `first` nor `last` is given
+const DEFAULT_CONNECTION_SIZE: usize = 20;
+const MAX_CONNECTION_SIZE: usize = 100;
+
+/// Hits per page when `filter.pagination` is not given
+const DEFAULT_SEARCH_SIZE: i32 = 20;
+
+type MeetupUrlConnection = Connection<OpaqueCursor<UrlCursor>, MeetupUrl, MeetupUrlConnectionFields>;
+
+/// `(current, size)` of a requested page
+fn validate_pagination(pagination: &Option<Pagination>) -> Result<Option<(i32, i32)>, ApiError> {
+    let Some(pagination) = pagination else {
+        return Ok(None);
+    };
+    match (pagination.current, pagination.size) {
+        (None, None) => Ok(None),
+        (Some(current), Some(size)) => {
+            if size <= 0 {
+                return Err(ApiError::Validation(format!("page size must be positive, got {}", size)));
+            }
+            if current < 0 {
+                return Err(ApiError::Validation(format!("page must not be negative, got {}", current)));
+            }
+            if current.checked_mul(size).is_none() {
+                return Err(ApiError::Validation(format!("page {} of size {} is out of range", current, size)));
+            }
+            Ok(Some((current, size)))
+        }
+        _ => Err(ApiError::Validation("pagination needs both current and size".to_string())),
+    }
+}
+
+/// The first page exists even when nothing matches
+fn validate_page_in_range(page: &Page) -> Result<(), ApiError> {
+    if page.current > 0 && page.current >= page.total_pages {
+        return Err(ApiError::Validation(format!(
+            "page {} is out of range, there are {} pages", page.current, page.total_pages
+        )));
+    }
+    Ok(())
+}
+
+/// Filters are regular expressions and tag names, a broken one is the
+/// caller's mistake
+pub(crate) fn validate_filter(filter: &MeetupUrlFilter) -> Result<(), ApiError> {
+    for name in filter.tags.iter().flat_map(|tags| &tags.names) {
+        tag_name(name).map_err(ApiError::Validation)?;
+    }
+
+    for (name, pattern) in [
+        ("domain", &filter.domain),
+        ("title", &filter.title),
+        ("url", &filter.url),
+        ("description", &filter.description),
+    ] {
+        if let Some(pattern) = pattern
+            && let Err(e) = Regex::new(pattern)
+        {
+            return Err(ApiError::Validation(format!("{} is not a valid pattern: {}", name, e)));
+        }
+    }
+    Ok(())
+}
+
+/// The trash is for admins
+async fn check_trash_access(ctx: &Context<'_>, filter: &MeetupUrlFilter) -> Result<()> {
+    if filter.reads_trash() {
+        RoleGuard::new(Role::Admin).check(ctx).await?;
+    }
+    Ok(())
+}
+
 #[Object]
 impl Query {
-    async fn meetup_url_list(&self, ctx: &Context<'_>, filter: MeetupUrlFilter) -> MeetupUrlResponse {
+    async fn meetup_url_list(&self, ctx: &Context<'_>, filter: MeetupUrlFilter) -> Result<MeetupUrlResponse> {
         log!(Level::Info, "Received request query: {:?}", filter);
 
-        // Access shared context if needed
-        let _server_context = ctx.data_unchecked::<ServerContext>();
+        check_trash_access(ctx, &filter).await?;
+        validate_filter(&filter)?;
+        let pagination = validate_pagination(&filter.pagination)?;
 
-        let client = connect_db().await;
-        let result = select_url(&client, filter.clone())
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let (result, total) = select_url_with_count(&server_context.db, filter)
             .await
-            .unwrap_or(Vec::new());
+            .map_err(ApiError::from)?;
+
+        let page = match pagination {
+            Some((current, size)) => Page::new(current, size, total),
+            None => Page::unpaginated(total),
+        };
+
+        validate_page_in_range(&page)?;
+
+        Ok(MeetupUrlResponse { result, page })
+    }
+
+    /// The rows matching `filter` as a file to save. `filter.pagination` is
+    /// ignored, `/export` streams the same for large exports.
+    async fn meetup_url_export(&self, ctx: &Context<'_>, filter: MeetupUrlFilter, format: ExportFormat) -> Result<MeetupUrlExport> {
+        log!(Level::Info, "Received request export as {:?}: {:?}", format, filter);
 
-        let count = count_url(&client, filter).await;
+        check_trash_access(ctx, &filter).await?;
+        validate_filter(&filter)?;
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+        Ok(export(&server_context.db, filter, format).await.map_err(ApiError::from)?)
+    }
 
-        MeetupUrlResponse {
-            result,
-            page: Page {
-                size: 0,
-                current: 0,
-                total: count.unwrap_or(0),
-            },
+    /// Full-text search over title, descriptions, host and url, best matches
+    /// first. The column filters of `filter` narrow the hits, its sort is ignored.
+    async fn search(&self, ctx: &Context<'_>, query: String, filter: Option<MeetupUrlFilter>) -> Result<SearchResponse> {
+        log!(Level::Info, "Received request search: {:?} {:?}", query, filter);
+
+        if query.trim().is_empty() {
+            return Err(ApiError::Validation("search query must not be blank".to_string()).into());
         }
+        let filter = filter.unwrap_or_default();
+        check_trash_access(ctx, &filter).await?;
+        validate_filter(&filter)?;
+        let (current, size) = validate_pagination(&filter.pagination)?.unwrap_or((0, DEFAULT_SEARCH_SIZE));
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let (hits, total) = search_url(&server_context.db, &query, &filter, current, size)
+            .await
+            .map_err(ApiError::from)?;
+
+        let page = Page::new(current, size, total);
+        validate_page_in_range(&page)?;
+
+        Ok(SearchResponse { hits, page })
     }
 
-    async fn meetup_url_count(&self, ctx: &Context<'_>, filter: MeetupUrlFilter) -> MeetupUrlCount {
+    /// Relay connection over the same rows as `meetupUrlList`. The cursors
+    /// belong to the sort order of `filter`, `filter.pagination` is ignored.
+    async fn meetup_url_connection(
+        &self,
+        ctx: &Context<'_>,
+        filter: MeetupUrlFilter,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<MeetupUrlConnection> {
+        log!(Level::Info, "Received request connection: {:?}", filter);
+
+        check_trash_access(ctx, &filter).await?;
+        validate_filter(&filter)?;
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+        let order = OrderBy::from(filter.sort.as_deref().unwrap_or_default());
+
+        query(after, before, first, last, |after: Option<OpaqueCursor<UrlCursor>>, before: Option<OpaqueCursor<UrlCursor>>, first, last| async move {
+            if first.is_some() && last.is_some() {
+                return Err(ApiError::Validation("pass either first or last, not both".to_string()).into());
+            }
+
+            let limit = first.or(last).unwrap_or(DEFAULT_CONNECTION_SIZE);
+            if limit > MAX_CONNECTION_SIZE {
+                return Err(ApiError::Validation(format!("at most {} edges per page", MAX_CONNECTION_SIZE)).into());
+            }
+
+            let after = after.map(|c| c.0);
+            let before = before.map(|c| c.0);
+            for cursor in [&after, &before].into_iter().flatten() {
+                order.check_cursor(cursor).map_err(ApiError::Validation)?;
+            }
+
+            let backward = last.is_some();
+            let seek = Seek { after: after.clone(), before: before.clone(), limit, backward };
+
+            let slice = select_url_slice(&server_context.db, filter, seek)
+                .await
+                .map_err(ApiError::from)?;
+
+            // Rows beyond a cursor exist at least up to the cursor row itself
+            let (has_previous, has_next) = if backward {
+                (slice.has_more, before.is_some())
+            } else {
+                (after.is_some(), slice.has_more)
+            };
+
+            let mut connection = Connection::with_additional_fields(
+                has_previous,
+                has_next,
+                MeetupUrlConnectionFields { total_count: slice.total },
+            );
+            connection.edges.extend(
+                slice.rows.into_iter().map(|(url, cursor)| Edge::new(OpaqueCursor(cursor), url)),
+            );
+            Ok::<_, async_graphql::Error>(connection)
+        })
+        .await
+        // Malformed cursors and negative sizes are reported by `query` without a code
+        .map_err(|e| match e.extensions {
+            Some(_) => e,
+            None => ApiError::Validation(e.message).into(),
+        })
+    }
+
+    /// Rows probably describing the same page, for curators to merge
+    #[graphql(guard = "RoleGuard::new(Role::Admin)")]
+    async fn duplicate_candidates(&self, ctx: &Context<'_>) -> Result<Vec<DuplicateGroup>> {
+        log!(Level::Info, "Received request duplicate candidates");
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let groups = duplicate_candidates(&server_context.db)
+            .await
+            .map_err(ApiError::from)?;
+
+        Ok(groups)
+    }
+
+    /// All tags with the number of rows carrying them, the most used first
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<Tag>> {
+        log!(Level::Info, "Received request tags");
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let tags = select_tags(&server_context.db)
+            .await
+            .map_err(ApiError::from)?;
+
+        Ok(tags)
+    }
+
+    /// Every change of the record, oldest first. Deleted records keep their history.
+    #[graphql(guard = "RoleGuard::new(Role::Editor)")]
+    async fn meetup_url_history(&self, ctx: &Context<'_>, uri_uuid: String) -> Result<Vec<MeetupUrlHistoryEntry>> {
+        log!(Level::Info, "Received request history of {}", uri_uuid);
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let history = select_history(&server_context.db, uri_uuid)
+            .await
+            .map_err(ApiError::from)?;
+
+        Ok(history)
+    }
+
+    async fn meetup_url_count(&self, ctx: &Context<'_>, filter: MeetupUrlFilter) -> Result<MeetupUrlCount> {
         log!(Level::Info, "Received request count: {:?}", filter);
 
-        let _server_context = ctx.data_unchecked::<ServerContext>();
+        check_trash_access(ctx, &filter).await?;
+        validate_filter(&filter)?;
+
+        let server_context = ctx.data_unchecked::<ServerContext>();
+
+        let count = count_url(&server_context.db, filter)
+            .await
+            .map_err(ApiError::from)?;
+
+        Ok(MeetupUrlCount { count })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphql::testing::{claims, error_code, execute, meetup_url, schema};
+    use crate::repository::UrlCursor;
+    use async_graphql::connection::{CursorType, OpaqueCursor};
+    use crate::Schema;
+    use serde_json::json;
+
+    const QUERY: &str = r#"
+        query ($filter: MeetupUrlFilter!) {
+            meetupUrlList(filter: $filter) { result { uriUuid title } page { total } }
+            meetupUrlCount(filter: $filter) { count }
+        }
+    "#;
+
+    const LIST: &str = r#"
+        query ($filter: MeetupUrlFilter!) { meetupUrlList(filter: $filter) { result { uriUuid } } }
+    "#;
+
+    const COUNT: &str = r#"
+        query ($filter: MeetupUrlFilter!) { meetupUrlCount(filter: $filter) { count } }
+    "#;
+
+    async fn seeded() -> Schema {
+        schema(vec![
+            meetup_url("a", "actix.rs", "Actix Web"),
+            meetup_url("b", "leptos.dev", "Leptos"),
+        ]).await
+    }
+
+    async fn query(schema: &Schema, filter: serde_json::Value) -> serde_json::Value {
+        let response = execute(schema, QUERY, json!({ "filter": filter }), None).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        response.data.into_json().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hostile_filters_through_schema() {
+        let schema = seeded().await;
+
+        for hostile in [
+            "'); DELETE url; --",
+            "') OR true OR string::matches(title, '",
+            "Leptos'); REMOVE TABLE url; --",
+        ] {
+            for field in ["title", "domain", "url", "description"] {
+                // Broken patterns are rejected, the others match nothing.
+                // Each query on its own, so one's error does not hide the other's.
+                for (query, answer, empty) in [(LIST, "meetupUrlList", json!({ "result": [] })), (COUNT, "meetupUrlCount", json!({ "count": 0 }))] {
+                    let response = execute(&schema, query, json!({ "filter": { field: hostile } }), None).await;
+                    if response.errors.is_empty() {
+                        assert_eq!(response.data.into_json().unwrap()[answer], empty, "{} on {}", hostile, field);
+                    } else {
+                        assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"), "{} on {}", hostile, field);
+                    }
+                }
+            }
+
+            // Escaped, every pattern is valid and matches nothing
+            let escaped = regex::escape(hostile);
+            let response = execute(&schema, COUNT, json!({ "filter": { "title": escaped } }), None).await;
+            assert!(response.errors.is_empty(), "{:?}", response.errors);
+            assert_eq!(response.data.into_json().unwrap()["meetupUrlCount"]["count"], 0);
+        }
+
+        // A broken pattern is an error, not a count of 0
+        let response = execute(&schema, COUNT, json!({ "filter": { "title": "(" } }), None).await;
+        assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"));
+
+        let data = query(&schema, json!({})).await;
+        assert_eq!(data["meetupUrlCount"]["count"], 2);
+        assert_eq!(data["meetupUrlList"]["page"]["total"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_filter_through_schema() {
+        let schema = seeded().await;
+
+        let data = query(&schema, json!({ "title": "Lept" })).await;
+        assert_eq!(data["meetupUrlList"]["result"], json!([{ "uriUuid": "b", "title": "Leptos" }]));
+        assert_eq!(data["meetupUrlCount"]["count"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_page_metadata() {
+        let schema = schema(vec![
+            meetup_url("a", "actix.rs", "Actix Web"),
+            meetup_url("b", "leptos.dev", "Leptos"),
+            meetup_url("c", "rust-lang.org", "Rust"),
+        ]).await;
+
+        let page_query = r#"
+            query ($filter: MeetupUrlFilter!) {
+                meetupUrlList(filter: $filter) { result { uriUuid } page { current size total totalPages hasNext hasPrevious } }
+            }
+        "#;
+        let page = |current: i32, size: i32| json!({ "filter": { "pagination": { "current": current, "size": size } } });
+
+        let response = execute(&schema, page_query, page(1, 2), None).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap()["meetupUrlList"]["page"],
+            json!({ "current": 1, "size": 2, "total": 3, "totalPages": 2, "hasNext": false, "hasPrevious": true })
+        );
+
+        let response = execute(&schema, page_query, page(0, 2), None).await;
+        assert_eq!(response.data.into_json().unwrap()["meetupUrlList"]["page"]["hasNext"], true);
+
+        let response = execute(&schema, page_query, json!({ "filter": {} }), None).await;
+        assert_eq!(
+            response.data.into_json().unwrap()["meetupUrlList"]["page"],
+            json!({ "current": 0, "size": 3, "total": 3, "totalPages": 1, "hasNext": false, "hasPrevious": false })
+        );
+
+        for (current, size) in [(2, 2), (-1, 2), (0, 0), (0, -5), (i32::MAX, 2), (2, i32::MAX)] {
+            let response = execute(&schema, page_query, page(current, size), None).await;
+            assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"), "page {} size {}", current, size);
+        }
+
+        let response = execute(&schema, page_query, json!({ "filter": { "title": "nothing", "pagination": { "current": 0, "size": 2 } } }), None).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let response = execute(&schema, page_query, page(0, i32::MAX), None).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap()["meetupUrlList"]["page"],
+            json!({ "current": 0, "size": i32::MAX, "total": 3, "totalPages": 1, "hasNext": false, "hasPrevious": false })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connection() {
+        let schema = schema(vec![
+            meetup_url("a", "actix.rs", "Actix Web"),
+            meetup_url("b", "leptos.dev", "Leptos"),
+            meetup_url("c", "rust-lang.org", "Rust"),
+            meetup_url("d", "Leptos.dev", "Leptos Book"),
+        ]).await;
+
+        let connection_query = r#"
+            query ($filter: MeetupUrlFilter!, $first: Int, $after: String, $last: Int, $before: String) {
+                meetupUrlConnection(filter: $filter, first: $first, after: $after, last: $last, before: $before) {
+                    totalCount
+                    edges { cursor node { uriUuid } }
+                    pageInfo { hasNextPage hasPreviousPage startCursor endCursor }
+                }
+            }
+        "#;
+        let filter = json!({ "sort": [{ "field": "DOMAIN", "direction": "DESC" }, { "field": "TITLE" }] });
+
+        let page = |variables: serde_json::Value| {
+            let schema = &schema;
+            async move {
+                let response = execute(schema, connection_query, variables, None).await;
+                assert!(response.errors.is_empty(), "{:?}", response.errors);
+                response.data.into_json().unwrap()["meetupUrlConnection"].clone()
+            }
+        };
+        let uuids = |connection: &serde_json::Value| connection["edges"].as_array().unwrap()
+            .iter()
+            .map(|e| e["node"]["uriUuid"].as_str().unwrap().to_string())
+            .collect::<Vec<String>>();
+
+        let first = page(json!({ "filter": filter, "first": 2 })).await;
+        assert_eq!(first["totalCount"], 4);
+        assert_eq!(uuids(&first), vec!["c", "b"]);
+        assert_eq!(first["pageInfo"]["hasNextPage"], true);
+        assert_eq!(first["pageInfo"]["hasPreviousPage"], false);
+
+        // A row inserted before the cursor does not shift the next page
+        let insert = r#"mutation ($m: UpsertMeetupUrl!) { insertMeetupUrl(meetupUrl: $m) { uriUuid } }"#;
+        let zig = json!({ "m": { "url": "https://ziglang.org/", "host": "ziglang.org", "title": "Zig", "autoDescr": "" } });
+        let response = execute(&schema, insert, zig, Some(claims(&["ROLE_HNL_ADMIN"]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let second = page(json!({ "filter": filter, "first": 2, "after": first["pageInfo"]["endCursor"] })).await;
+        assert_eq!(uuids(&second), vec!["d", "a"]);
+        assert_eq!(second["pageInfo"]["hasNextPage"], false);
+        assert_eq!(second["pageInfo"]["hasPreviousPage"], true);
+
+        let back = page(json!({ "filter": filter, "last": 2, "before": second["pageInfo"]["startCursor"] })).await;
+        assert_eq!(uuids(&back), vec!["c", "b"]);
+        assert_eq!(back["pageInfo"]["hasPreviousPage"], true);
+
+        // An edited cursor missing a key would loosen the seek condition
+        let mut truncated = OpaqueCursor::<UrlCursor>::decode_cursor(first["pageInfo"]["endCursor"].as_str().unwrap()).unwrap();
+        truncated.0.keys.pop();
+        let truncated = truncated.encode_cursor();
+
+        for variables in [
+            json!({ "filter": filter, "after": truncated }),
+            json!({ "filter": filter, "first": 2, "last": 2 }),
+            json!({ "filter": filter, "first": 1000 }),
+            json!({ "filter": filter, "first": -1 }),
+            json!({ "filter": filter, "after": "not a cursor" }),
+            json!({ "filter": {}, "after": first["pageInfo"]["endCursor"] }),
+        ] {
+            let response = execute(&schema, connection_query, variables.clone(), None).await;
+            assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"), "{}", variables);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let mut rows = vec![
+            meetup_url("a", "docs.rs", "Axum tutorial for beginners"),
+            meetup_url("b", "tokio.rs", "Tokio tutorial"),
+            meetup_url("c", "github.com", "Axum examples"),
+        ];
+        // Enough unrelated rows for the terms to be rare
+        rows.extend((0..8).map(|i| meetup_url(&format!("filler{}", i), "example.org", &format!("Meetup notes {}", i))));
+        let schema = schema(rows).await;
+
+        let search_query = r#"
+            query ($query: String!, $filter: MeetupUrlFilter) {
+                search(query: $query, filter: $filter) {
+                    hits { meetupUrl { uriUuid } score highlights { field fragments { text matched } } }
+                    page { total }
+                }
+            }
+        "#;
+        let search = |query: &str, filter: serde_json::Value| {
+            let schema = &schema;
+            let variables = json!({ "query": query, "filter": filter });
+            async move {
+                let response = execute(schema, search_query, variables, None).await;
+                assert!(response.errors.is_empty(), "{:?}", response.errors);
+                response.data.into_json().unwrap()["search"].clone()
+            }
+        };
+
+        // Stemmed and case-insensitive, every term must match
+        let result = search("axum Tutorials", json!(null)).await;
+        assert_eq!(result["page"]["total"], 1);
+        let hit = &result["hits"][0];
+        assert_eq!(hit["meetupUrl"]["uriUuid"], "a");
+        assert_eq!(hit["highlights"][0]["field"], "TITLE");
+        assert_eq!(hit["highlights"][0]["fragments"][0], json!({ "text": "Axum", "matched": true }));
+
+        let result = search("axum", json!(null)).await;
+        assert_eq!(result["page"]["total"], 2);
+
+        // Terms in the host count too, column filters apply on top
+        let result = search("tokio", json!({ "domain": "tokio" })).await;
+        assert_eq!(result["hits"][0]["meetupUrl"]["uriUuid"], "b");
+        let result = search("tutorial", json!({ "domain": "github" })).await;
+        assert_eq!(result["page"]["total"], 0);
+
+        for (query, filter) in [
+            ("  ", json!(null)),
+            ("axum", json!({ "title": "(" })),
+            ("axum", json!({ "pagination": { "current": 3, "size": 1 } })),
+            ("axum", json!({ "pagination": { "current": i32::MAX, "size": i32::MAX } })),
+        ] {
+            let response = execute(&schema, search_query, json!({ "query": query, "filter": filter }), None).await;
+            assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"), "{} {}", query, filter);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_candidates() {
+        let mut docs = meetup_url("b", "actix.rs", "Actix Web");
+        docs.url = "https://actix.rs/docs/".to_string();
+        let schema = schema(vec![
+            meetup_url("a", "actix.rs", "Actix Web"),
+            docs,
+            meetup_url("c", "leptos.dev", "Leptos"),
+        ]).await;
+
+        let candidates_query = "{ duplicateCandidates { host meetupUrls { uriUuid } } }";
+
+        let response = execute(&schema, candidates_query, json!({}), None).await;
+        assert_eq!(error_code(&response).as_deref(), Some("UNAUTHENTICATED"));
+
+        let response = execute(&schema, candidates_query, json!({}), Some(claims(&["ROLE_HNL_ADMIN"]))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap()["duplicateCandidates"],
+            json!([{ "host": "actix.rs", "meetupUrls": [{ "uriUuid": "a" }, { "uriUuid": "b" }] }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let schema = seeded().await;
+        let export_query = r#"
+            query ($filter: MeetupUrlFilter!) { meetupUrlExport(filter: $filter, format: MARKDOWN) { fileName contentType content } }
+        "#;
 
-        let client = connect_db().await;
-        let result = count_url(&client, filter).await;
+        let response = execute(&schema, export_query, json!({ "filter": { "title": "Lept" } }), None).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.into_json().unwrap()["meetupUrlExport"], json!({
+            "fileName": "meetup-urls.md",
+            "contentType": "text/markdown; charset=utf-8",
+            "content": "# Meetup URLs\n\n## leptos.dev\n\n- [Leptos](https://leptos.dev/b): Leptos\n"
+        }));
 
-        MeetupUrlCount { count: result.unwrap_or(0) as i32 }
+        let response = execute(&schema, export_query, json!({ "filter": { "onlyDeleted": true } }), Some(claims(&["ROLE_HNL_USER"]))).await;
+        assert_eq!(error_code(&response).as_deref(), Some("FORBIDDEN"));
+        let response = execute(&schema, export_query, json!({ "filter": { "title": "(" } }), None).await;
+        assert_eq!(error_code(&response).as_deref(), Some("VALIDATION"));
     }
-}
\ No newline at end of file
+}
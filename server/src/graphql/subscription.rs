@@ -1,22 +1,135 @@
-use crate::graphql::ClockBox;
+use crate::graphql::{ClockBox, MeetupUrlChanged, MeetupUrlFilter, Role, RoleGuard, ServerContext};
+use crate::repository::Condition;
 use chrono::Utc;
 use async_graphql::{Context, Subscription};
 use tokio_stream::StreamExt as _;
 use std::time::Duration;
 use tokio::time::interval;
-use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::wrappers::{BroadcastStream, IntervalStream};
 use tracing::log::{log, Level};
 
 #[derive(Clone, Debug)]
 pub struct Subscription {}
 
+/// Fields without a guard are open to anonymous connections
 #[Subscription]
 impl Subscription {
+    #[graphql(guard = "RoleGuard::new(Role::Viewer)")]
     async fn clock(&self, _ctx: &Context<'_>) -> impl tokio_stream::Stream<Item = ClockBox> {
         log!(Level::Info, "Subscription to clock...");
 
         IntervalStream::new(interval(Duration::from_secs(1))).map(move |_| {
-            ClockBox { clock: format!("{}", Utc::now().to_string()) }
+            ClockBox { clock: Utc::now().to_string() }
         })
     }
-}
\ No newline at end of file
+
+    /// Created, updated and deleted urls matching `filter`. Pagination and
+    /// sort of the filter are ignored, a delete is reported whatever the
+    /// trash flags. `RELOAD` after imports and when the subscriber fell
+    /// behind, then the changes are not reported one by one.
+    #[graphql(guard = "RoleGuard::new(Role::Viewer)")]
+    async fn meetup_url_changed(&self, ctx: &Context<'_>, filter: Option<MeetupUrlFilter>) -> impl tokio_stream::Stream<Item = MeetupUrlChanged> {
+        log!(Level::Info, "Subscription to meetupUrlChanged: {:?}", filter);
+
+        // The deleted row is already in the trash when it is published
+        let condition = filter
+            .map(|f| Condition::from(&MeetupUrlFilter { include_deleted: Some(true), only_deleted: None, ..f }))
+            .unwrap_or(Condition::Always);
+        let receiver = ctx.data_unchecked::<ServerContext>().subscribe();
+
+        BroadcastStream::new(receiver).filter_map(move |change| match change {
+            Ok(change) => match &change.meetup_url {
+                Some(meetup_url) if !condition.matches(meetup_url) => None,
+                _ => Some(change),
+            },
+            Err(e) => {
+                log!(Level::Warn, "meetupUrlChanged subscriber lagging: {}", e);
+                Some(MeetupUrlChanged::reload())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphql::testing::{claims, execute, meetup_url, schema};
+    use async_graphql::{Request, Variables};
+    use serde_json::json;
+    use tokio_stream::StreamExt as _;
+
+    #[tokio::test]
+    async fn test_clock_requires_viewer() {
+        let schema = schema(vec![]).await;
+
+        let mut stream = schema.execute_stream(Request::new("subscription { clock { clock } }"));
+        let response = stream.next().await.unwrap();
+        assert_eq!(response.errors[0].message, "Authentication required");
+
+        let request = Request::new("subscription { clock { clock } }").data(claims(&["ROLE_HNL_USER"]));
+        let mut stream = schema.execute_stream(request);
+        let response = stream.next().await.unwrap();
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+
+    #[tokio::test]
+    async fn test_meetup_url_changed() {
+        let schema = schema(vec![meetup_url("a", "actix.rs", "Actix Web")]).await;
+
+        let subscription = Request::new(r#"
+            subscription ($f: MeetupUrlFilter) { meetupUrlChanged(filter: $f) { kind meetupUrl { uriUuid title } } }
+        "#)
+            .variables(Variables::from_json(json!({ "f": { "domain": "actix" } })))
+            .data(claims(&["ROLE_HNL_USER"]));
+        let mut stream = schema.execute_stream(subscription);
+
+        // The stream subscribes lazily, poll it once before mutating
+        let pending = tokio::time::timeout(std::time::Duration::from_millis(50), stream.next()).await;
+        assert!(pending.is_err());
+
+        let admin = Some(claims(&["ROLE_HNL_ADMIN"]));
+        let insert = r#"mutation ($m: UpsertMeetupUrl!) { insertMeetupUrl(meetupUrl: $m) { uriUuid } }"#;
+        let update = r#"mutation ($m: MeetupUrlPatch!) { updateMeetupUrl(meetupUrl: $m) { uriUuid } }"#;
+        let delete = r#"mutation ($id: String!) { deleteMeetupUrl(id: $id) { uriUuid } }"#;
+
+        let leptos = json!({ "m": { "url": "https://leptos.dev/", "host": "leptos.dev", "title": "Leptos", "autoDescr": "" } });
+        let actix = json!({ "m": { "uriUuid": "a", "url": "https://actix.rs/", "host": "actix.rs", "title": "Actix", "autoDescr": "" } });
+
+        // Filtered out by the domain
+        assert!(execute(&schema, insert, leptos, admin.clone()).await.errors.is_empty());
+        assert!(execute(&schema, update, actix, admin.clone()).await.errors.is_empty());
+        assert!(execute(&schema, delete, json!({ "id": "a" }), admin.clone()).await.errors.is_empty());
+
+        let mut events = Vec::new();
+        for _ in 0..2 {
+            let response = stream.next().await.unwrap();
+            assert!(response.errors.is_empty(), "{:?}", response.errors);
+            events.push(response.data.into_json().unwrap()["meetupUrlChanged"].clone());
+        }
+
+        assert_eq!(events, vec![
+            json!({ "kind": "UPDATED", "meetupUrl": { "uriUuid": "a", "title": "Actix" } }),
+            json!({ "kind": "DELETED", "meetupUrl": { "uriUuid": "a", "title": "Actix" } }),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_lagging_subscriber_reloads() {
+        use crate::graphql::testing::server_context;
+        use crate::graphql::domain::CHANGE_BUFFER;
+        use crate::graphql::{MeetupUrl, MeetupUrlChangeKind, Mutation, Query, Subscription};
+        use crate::Schema;
+
+        let context = server_context(vec![]).await;
+        let schema = Schema::build(Query, Mutation, Subscription {}).data(context.clone()).finish();
+        let subscription = Request::new("subscription { meetupUrlChanged { kind meetupUrl { uriUuid } } }")
+            .data(claims(&["ROLE_HNL_USER"]));
+        let mut stream = schema.execute_stream(subscription);
+        assert!(tokio::time::timeout(std::time::Duration::from_millis(50), stream.next()).await.is_err());
+
+        for n in 0..=CHANGE_BUFFER {
+            context.publish(MeetupUrlChangeKind::Created, MeetupUrl { uri_uuid: n.to_string(), ..MeetupUrl::default() });
+        }
+        let response = stream.next().await.unwrap();
+        assert_eq!(response.data.into_json().unwrap()["meetupUrlChanged"], json!({ "kind": "RELOAD", "meetupUrl": null }));
+    }
+}
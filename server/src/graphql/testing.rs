@@ -0,0 +1,99 @@
+use crate::auth::{Claims, RealmAccess};
+use crate::config::{connect_test_db, Settings};
+use crate::graphql::{Mutation, Query, ServerContext, Subscription};
+use crate::model::MeetupUrl as DbMeetupUrl;
+use crate::repository::insert_init_meetup_url;
+use crate::service::PageFetcher;
+use crate::Schema;
+use async_graphql::{Request, Response, Variables};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+use surrealdb::sql::Datetime;
+
+pub fn time(rfc3339: &str) -> Datetime {
+    Datetime::from(DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc))
+}
+
+/// Unique per `uri_uuid`, also on a shared host
+pub fn meetup_url(uri_uuid: &str, host: &str, title: &str) -> DbMeetupUrl {
+    DbMeetupUrl {
+        uri_uuid: uri_uuid.to_string(),
+        url: format!("https://{}/{}", host, uri_uuid),
+        scheme: "https".to_string(),
+        host: host.to_string(),
+        port: None,
+        path: format!("/{}", uri_uuid),
+        query: None,
+        live_status: "1".to_string(),
+        title: title.to_string(),
+        auto_descr: title.to_string(),
+        man_descr: "".to_string(),
+        crea_user: "api".to_string(),
+        crea_time: time("2024-08-29T17:48:10.384049834+02:00"),
+        modi_user: "api".to_string(),
+        modi_time: time("2024-08-29T17:48:10.384049834+02:00"),
+        url_key: None,
+        deleted_at: None,
+        deleted_by: None,
+        canonical_url: None,
+        language: None,
+        favicon: None,
+        image: None,
+        site_name: None,
+        metadata_time: None,
+    }
+}
+
+/// Context on a fresh in-memory database holding `rows`
+pub async fn server_context(rows: Vec<DbMeetupUrl>) -> ServerContext {
+    let db = connect_test_db().await;
+    for row in rows {
+        insert_init_meetup_url(row, &db).await.unwrap();
+    }
+
+    // Tests read pages only from local servers, and only when asked to
+    let mut settings = Settings::for_tests();
+    settings.metadata.fetch_on_insert = false;
+    settings.metadata.allow_private_addresses = true;
+    let pages = PageFetcher::new(&settings.metadata).unwrap();
+
+    ServerContext::new(db, Arc::new(settings), pages)
+}
+
+/// Schema on a fresh in-memory database holding `rows`
+pub async fn schema(rows: Vec<DbMeetupUrl>) -> Schema {
+    Schema::build(Query, Mutation, Subscription {})
+        .data(server_context(rows).await)
+        .finish()
+}
+
+pub fn claims(roles: &[&str]) -> Claims {
+    Claims {
+        sub: "0b7e7c4e-test".to_string(),
+        exp: usize::MAX,
+        iat: 0,
+        iss: "http://localhost:8888/realms/hackandlearn".to_string(),
+        aud: None,
+        preferred_username: Some("curator".to_string()),
+        email: None,
+        realm_access: Some(RealmAccess { roles: roles.iter().map(|r| r.to_string()).collect() }),
+        resource_access: None,
+    }
+}
+
+pub async fn execute(schema: &Schema, query: &str, variables: Value, claims: Option<Claims>) -> Response {
+    let mut request = Request::new(query).variables(Variables::from_json(variables));
+    if let Some(claims) = claims {
+        request = request.data(claims);
+    }
+    schema.execute(request).await
+}
+
+/// `extensions.code` of the first error
+pub fn error_code(response: &Response) -> Option<String> {
+    response.errors.first()
+        .and_then(|e| e.extensions.as_ref())
+        .and_then(|ext| ext.get("code"))
+        .map(|code| code.to_string().trim_matches('"').to_string())
+}
@@ -4,15 +4,22 @@ mod service;
 mod model;
 mod graphql;
 mod auth;
+mod cli;
+mod export;
 
+use crate::config::{connect_db, Settings};
 use crate::graphql::{Mutation, Query, ServerContext, Subscription};
-use crate::auth::{AuthState, auth_middleware};
-use axum::routing::{get, post, get_service};
+use crate::repository::migrate;
+use crate::service::{spawn_link_health_job, spawn_purge_job, PageFetcher};
+use crate::auth::{AuthState, auth_middleware, ws_connection_init};
+use crate::cli::{run_import, Command, USAGE};
+use crate::export::export_handler;
+use axum::extract::WebSocketUpgrade;
+use axum::routing::{get, post};
 use axum::{Extension, Router};
-use async_graphql::http::{GraphiQLSource, playground_source, GraphQLPlaygroundConfig};
+use async_graphql::http::{GraphiQLSource, playground_source, GraphQLPlaygroundConfig, ALL_WEBSOCKET_PROTOCOLS};
 use async_graphql::Schema as AsyncSchema;
-use async_graphql_axum::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
-use std::net::SocketAddr;
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
@@ -20,7 +27,7 @@ use tower_http::cors::{Any, CorsLayer};
 use axum::http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
 use axum::middleware;
 use tower_http::services::ServeDir;
-use axum::response::Html;
+use axum::response::{Html, Response};
 
 pub type Schema = AsyncSchema<Query, Mutation, Subscription>;
 
@@ -36,6 +43,21 @@ async fn graphql_handler(
     schema.execute(request).await.into()
 }
 
+async fn graphql_ws_handler(
+    Extension(schema): Extension<Schema>,
+    Extension(auth_state): Extension<Arc<AuthState>>,
+    protocol: GraphQLProtocol,
+    websocket: WebSocketUpgrade,
+) -> Response {
+    websocket
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            GraphQLWebSocket::new(stream, schema, protocol)
+                .on_connection_init(move |payload| ws_connection_init(auth_state, payload))
+                .serve()
+        })
+}
+
 async fn graphiql() -> Html<String> {
     Html(GraphiQLSource::build().endpoint("/graphql").subscription_endpoint("/subscriptions").finish())
 }
@@ -55,34 +77,66 @@ async fn main() {
         .allow_methods([axum::http::Method::GET, axum::http::Method::POST])
         .allow_headers([AUTHORIZATION, CONTENT_TYPE, ACCEPT]);
 
-    let server_context = ServerContext::default();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let command = Command::parse(&args).unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        std::process::exit(2)
+    });
+
+    let settings = Arc::new(Settings::load()
+        .unwrap_or_else(|e| panic!("{e}")));
+
+    let db = connect_db(&settings.database).await
+        .unwrap_or_else(|e| panic!("failed to connect to SurrealDB: {e}"));
+
+    migrate(&db).await
+        .unwrap_or_else(|e| panic!("failed to migrate the database: {e}"));
+
+    if let Command::Import { file, dry_run } = command {
+        let succeeded = run_import(&db, &settings, file, dry_run).await;
+        std::process::exit(if succeeded { 0 } else { 1 });
+    }
+
+    spawn_purge_job(db.clone(), settings.trash.clone());
+    if settings.link_health.enabled {
+        spawn_link_health_job(db.clone(), settings.link_health.clone())
+            .unwrap_or_else(|e| panic!("failed to build the link checker: {e}"));
+    }
+
+    let pages = PageFetcher::new(&settings.metadata)
+        .unwrap_or_else(|e| panic!("failed to build the metadata reader: {e}"));
+
+    let server_context = ServerContext::new(db, settings.clone(), pages);
     let schema = Schema::build(Query, Mutation, Subscription {})
         .data(server_context.clone())
         .finish();
 
     let auth_state = Arc::new(AuthState::new(
-        "http://localhost:8888/realms/hackandlearn".to_string(),
-        "http://localhost:8888/realms/hackandlearn/protocol/openid-connect/certs".to_string(),
-        "hackandlearn-client".to_string(),
+        settings.auth.issuer.clone(),
+        settings.auth.jwks_url.clone(),
+        settings.auth.audience.clone(),
     ));
 
-    // Protect only the HTTP GraphQL endpoint with auth middleware; leave WS subscriptions open
+    // HTTP GraphQL and the export require a token; subscriptions authenticate in `connection_init`
     let graphql_http = Router::new()
         .route("/graphql", post(graphql_handler))
+        .route("/export", get(export_handler))
         .layer(middleware::from_fn_with_state(auth_state.clone(), auth_middleware));
 
     let app = Router::new()
         .merge(graphql_http)
-        .route("/subscriptions", get_service(GraphQLSubscription::new(schema.clone())))
+        .route("/subscriptions", get(graphql_ws_handler))
         .route("/graphiql", get(graphiql))
         .route("/playground", get(playground))
         .route("/graphql", get(graphiql))
-        .nest_service("/public", ServeDir::new("static/public"))
-        .nest_service("/web", ServiceBuilder::new().service(ServeDir::new("static")))
+        .nest_service("/public", ServeDir::new(&settings.server.public_dir))
+        .nest_service("/web", ServiceBuilder::new().service(ServeDir::new(&settings.server.static_dir)))
         .layer(Extension(schema))
+        .layer(Extension(server_context))
+        .layer(Extension(auth_state))
         .layer(cors);
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+    let addr = settings.bind_addr();
     let listener = TcpListener::bind(addr).await
         .unwrap_or_else(|e| panic!("failed to listen on {addr}: {e}"));
 
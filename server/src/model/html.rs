@@ -0,0 +1,87 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?s)([^\s"'=<>/]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+))"#).unwrap()
+});
+static MARKUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// Attributes of a tag by lowercase name, the first of repeated ones wins
+pub fn tag_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    for attribute in ATTRIBUTE.captures_iter(tag) {
+        let value = attribute.get(2).or(attribute.get(3)).or(attribute.get(4)).map_or("", |value| value.as_str());
+        attributes.entry(attribute[1].to_ascii_lowercase()).or_insert(value.to_string());
+    }
+    attributes
+}
+
+/// Decoded, tags dropped and whitespace collapsed
+pub fn html_text(html: &str) -> String {
+    decode_html(&MARKUP.replace_all(html, " ")).split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Replaces the character references, unknown named ones are kept
+pub fn decode_html(html: &str) -> String {
+    let mut decoded = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let reference = rest[1..].find(';').filter(|end| *end <= 10).map(|end| &rest[1..=end]);
+        match reference.and_then(character) {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[reference.map_or(0, str::len) + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Text and attribute values safe to put between tags or in double quotes
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn character(reference: &str) -> Option<char> {
+    if let Some(number) = reference.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code).filter(|c| *c != '\0');
+    }
+    match reference {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        "ndash" => Some('–'),
+        "mdash" => Some('—'),
+        "hellip" => Some('…'),
+        "laquo" => Some('«'),
+        "raquo" => Some('»'),
+        "copy" => Some('©'),
+        _ => None,
+    }
+}
@@ -0,0 +1,162 @@
+use crate::model::html::{decode_html, html_text, tag_attributes};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use url::Url;
+
+/// Longest description kept, in characters
+const MAX_DESCRIPTION_LENGTH: usize = 1000;
+
+static TITLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<title\b[^>]*>(.*?)</title\s*>").unwrap());
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<(html|meta|link)\b([^>]*)>").unwrap());
+static COMMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+
+/// What a page says about itself in its `<head>`. Texts are decoded with
+/// whitespace collapsed, urls are absolute.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HtmlMetadata {
+    /// `og:title`, `twitter:title` or `<title>`
+    pub title: Option<String>,
+    /// `description`, `og:description` or `twitter:description`
+    pub description: Option<String>,
+    /// `<link rel="canonical">`
+    pub canonical_url: Option<String>,
+    /// `lang` of `<html>`, `content-language` or `og:locale`
+    pub language: Option<String>,
+    /// `<link rel="icon">` and its variants, `/favicon.ico` when the page names none
+    pub favicon: Option<String>,
+    /// `og:image` or `twitter:image`
+    pub image: Option<String>,
+    /// `og:site_name`
+    pub site_name: Option<String>,
+}
+
+impl HtmlMetadata {
+    /// `page` is where `html` was fetched from, relative urls are resolved
+    /// against it. Parses leniently, whatever is missing stays `None`.
+    pub fn extract(html: &str, page: &Url) -> Self {
+        let html = COMMENT.replace_all(html, "");
+        let end = html.find("</head").or_else(|| html.find("</HEAD")).unwrap_or(html.len());
+        let head = &html[..end];
+
+        let mut language = None;
+        let mut metas: HashMap<String, String> = HashMap::new();
+        let mut canonical = None;
+        let mut icons: Vec<(u8, String)> = Vec::new();
+
+        for tag in TAG.captures_iter(head) {
+            let attributes = tag_attributes(&tag[2]);
+            let get = |name: &str| attributes.get(name).map(String::as_str).filter(|v| !v.trim().is_empty());
+
+            match tag[1].to_ascii_lowercase().as_str() {
+                "html" => language = language.or(get("lang").map(str::to_string)),
+                "meta" => {
+                    let key = get("property").or(get("name")).or(get("http-equiv")).map(str::to_ascii_lowercase);
+                    if let (Some(key), Some(content)) = (key, get("content")) {
+                        metas.entry(key).or_insert(content.to_string());
+                    }
+                }
+                _ => {
+                    let (Some(rel), Some(href)) = (get("rel"), get("href")) else {
+                        continue;
+                    };
+                    let rel = rel.to_ascii_lowercase();
+                    let rels = rel.split_whitespace().collect::<Vec<&str>>();
+                    if rels.contains(&"canonical") {
+                        canonical = canonical.or(Some(href.to_string()));
+                    }
+                    // Plain icons first, touch icons are larger but meant for home screens
+                    if rels.contains(&"icon") {
+                        icons.push((0, href.to_string()));
+                    } else if rels.iter().any(|rel| rel.starts_with("apple-touch-icon")) {
+                        icons.push((1, href.to_string()));
+                    }
+                }
+            }
+        }
+
+        let meta = |keys: &[&str]| keys.iter().find_map(|key| metas.get(*key)).map(|value| html_text(value)).filter(|value| !value.is_empty());
+        let link = |href: Option<&String>| href.and_then(|href| page.join(decode_html(href.trim()).as_str()).ok()).map(String::from);
+
+        let title = meta(&["og:title", "twitter:title"])
+            .or_else(|| TITLE.captures(head).map(|title| html_text(&title[1])).filter(|title| !title.is_empty()));
+        let description = meta(&["description", "og:description", "twitter:description"])
+            .map(|description| shorten(description, MAX_DESCRIPTION_LENGTH));
+        let language = language.map(|language| html_text(&language))
+            .or_else(|| meta(&["content-language"]))
+            .or_else(|| meta(&["og:locale"]).map(|locale| locale.replace('_', "-")));
+
+        icons.sort_by_key(|(rank, _)| *rank);
+        let favicon = link(icons.first().map(|(_, href)| href))
+            .or_else(|| page.join("/favicon.ico").ok().filter(|_| page.scheme().starts_with("http")).map(String::from));
+
+        HtmlMetadata {
+            title,
+            description,
+            canonical_url: link(canonical.as_ref()),
+            language,
+            favicon,
+            image: link(metas.get("og:image").or(metas.get("twitter:image"))),
+            site_name: meta(&["og:site_name"]),
+        }
+    }
+}
+
+/// At most `max` characters, cut ones end in `…`
+fn shorten(text: String, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text;
+    }
+    let mut shortened = text.chars().take(max - 1).collect::<String>().trim_end().to_string();
+    shortened.push('…');
+    shortened
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HtmlMetadata;
+    use url::Url;
+
+    #[test]
+    fn test_extract() {
+        let page = Url::parse("https://leptos.dev/docs/intro").unwrap();
+        let html = r#"<!DOCTYPE html>
+            <HTML lang="en-US"><head>
+              <meta charset="utf-8">
+              <!-- <title>Old</title> -->
+              <title>
+                Leptos &amp; friends &#8211; Docs
+              </title>
+              <meta name="Description" content="Build &quot;fast&quot; web apps">
+              <meta property='og:site_name' content='Leptos'>
+              <meta property="og:image" content="/img/og.png">
+              <link rel="apple-touch-icon" href="/touch.png">
+              <link rel="shortcut icon" href="favicon.svg" type="image/svg+xml">
+              <link href="https://leptos.dev/docs/intro/" rel="canonical" />
+            </head><body><title>Not this</title></body></HTML>"#;
+
+        assert_eq!(HtmlMetadata::extract(html, &page), HtmlMetadata {
+            title: Some("Leptos & friends – Docs".to_string()),
+            description: Some("Build \"fast\" web apps".to_string()),
+            canonical_url: Some("https://leptos.dev/docs/intro/".to_string()),
+            language: Some("en-US".to_string()),
+            favicon: Some("https://leptos.dev/docs/favicon.svg".to_string()),
+            image: Some("https://leptos.dev/img/og.png".to_string()),
+            site_name: Some("Leptos".to_string()),
+        });
+    }
+
+    #[test]
+    fn test_extract_prefers_open_graph_title() {
+        let page = Url::parse("https://actix.rs/").unwrap();
+        let html = r#"<head><title>Actix | Home</title><meta name="twitter:title" content="Actix Web">
+            <meta property="og:locale" content="de_DE"><meta name="description" content=" "></head>"#;
+
+        let metadata = HtmlMetadata::extract(html, &page);
+        assert_eq!(metadata.title.as_deref(), Some("Actix Web"));
+        assert_eq!(metadata.description, None);
+        assert_eq!(metadata.language.as_deref(), Some("de-DE"));
+        assert_eq!(metadata.favicon.as_deref(), Some("https://actix.rs/favicon.ico"));
+        assert_eq!(HtmlMetadata::extract("no markup & no head", &page).title, None);
+    }
+}
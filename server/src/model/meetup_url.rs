@@ -1,18 +1,49 @@
 use serde::{Deserialize, Serialize};
+use surrealdb::sql::Datetime;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct MeetupUrl {
+    /// Derived from the url when empty, see `NormalizedUrl::uri_uuid`
+    #[serde(default)]
     pub uri_uuid: String,
     pub url: String,
     pub scheme: String,
     pub host: String,
+    /// Only when not the default port of the scheme
+    #[serde(default)]
+    pub port: Option<u16>,
     pub path: String,
+    #[serde(default)]
+    pub query: Option<String>,
     pub live_status: String,
     pub title: String,
     pub auto_descr: String,
     pub man_descr: String,
     pub crea_user: String,
-    pub crea_time: String,
+    /// RFC 3339 in import files, a datetime in the database
+    pub crea_time: Datetime,
     pub modi_user: String,
-    pub modi_time: String,
+    pub modi_time: Datetime,
+    /// `NormalizedUrl::key`, missing on rows duplicating an older one
+    #[serde(default)]
+    pub url_key: Option<String>,
+    /// Set while the row is in the trash
+    #[serde(default)]
+    pub deleted_at: Option<Datetime>,
+    #[serde(default)]
+    pub deleted_by: Option<String>,
+    /// Read from the page, see `HtmlMetadata`
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub favicon: Option<String>,
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default)]
+    pub site_name: Option<String>,
+    /// Last time the page was read, `None` before
+    #[serde(default)]
+    pub metadata_time: Option<Datetime>,
 }
\ No newline at end of file
@@ -1,3 +1,11 @@
+mod html;
+mod html_metadata;
 mod meetup_url;
+mod normalized_url;
+mod tag_name;
 
+pub use html::{decode_html, escape_html, html_text, tag_attributes};
+pub use html_metadata::HtmlMetadata;
 pub use meetup_url::MeetupUrl;
+pub use normalized_url::NormalizedUrl;
+pub use tag_name::tag_name;
@@ -0,0 +1,113 @@
+use sha2::{Digest, Sha256};
+use url::Url;
+
+/// Query parameters only used to track where a visitor came from
+const TRACKING_PARAMS: [&str; 6] = ["fbclid", "gclid", "mc_cid", "mc_eid", "ref_src", "igshid"];
+
+fn is_tracking_param(name: &str) -> bool {
+    name.to_ascii_lowercase().starts_with("utm_") || TRACKING_PARAMS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// A submitted http(s) URL in canonical form: lowercase scheme and host,
+/// no default port, no tracking parameters, and no trailing slash except
+/// for the root path. The fragment is kept, it may point into a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedUrl {
+    pub url: String,
+    pub scheme: String,
+    pub host: String,
+    pub port: Option<u16>,
+    pub path: String,
+    pub query: Option<String>,
+}
+
+impl NormalizedUrl {
+    /// Identifies the link regardless of its scheme, unique among all rows
+    pub fn key(&self) -> String {
+        self.url.trim_start_matches(&format!("{}://", self.scheme)).to_string()
+    }
+
+    /// Id of a new record holding this url, the hex SHA-256 of `key`. The
+    /// id is kept when the url changes later.
+    pub fn uri_uuid(&self) -> String {
+        Sha256::digest(self.key().as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// The error explains why `input` is not a usable link
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut url = Url::parse(input.trim()).map_err(|e| format!("'{}' is not a valid URL: {}", input, e))?;
+
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(format!("'{}' must use http or https", input));
+        }
+        let Some(host) = url.host_str().filter(|h| !h.is_empty()) else {
+            return Err(format!("'{}' has no host", input));
+        };
+        let host = host.to_string();
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(format!("'{}' must not contain credentials", input));
+        }
+
+        let params = url.query_pairs()
+            .filter(|(name, _)| !is_tracking_param(name))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect::<Vec<(String, String)>>();
+        if params.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(params);
+        }
+
+        let path = url.path().trim_end_matches('/').to_string();
+        url.set_path(if path.is_empty() { "/" } else { &path });
+        if url.fragment() == Some("") {
+            url.set_fragment(None);
+        }
+
+        Ok(NormalizedUrl {
+            scheme: url.scheme().to_string(),
+            host,
+            // `Url` already drops the default port of the scheme
+            port: url.port(),
+            path: url.path().to_string(),
+            query: url.query().map(str::to_string),
+            url: url.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NormalizedUrl;
+
+    #[test]
+    fn test_normalize() {
+        let url = NormalizedUrl::parse(" HTTPS://Docs.RS:443/axum/latest/?utm_source=x&q=Router&fbclid=1#routing ").unwrap();
+
+        assert_eq!(url.url, "https://docs.rs/axum/latest?q=Router#routing");
+        assert_eq!(url.scheme, "https");
+        assert_eq!(url.host, "docs.rs");
+        assert_eq!(url.port, None);
+        assert_eq!(url.path, "/axum/latest");
+        assert_eq!(url.query.as_deref(), Some("q=Router"));
+        assert_eq!(url.key(), "docs.rs/axum/latest?q=Router#routing");
+        assert_eq!(url.uri_uuid(), NormalizedUrl::parse("http://docs.rs/axum/latest/?q=Router#routing").unwrap().uri_uuid());
+        assert_eq!(url.uri_uuid().len(), 64);
+
+        let url = NormalizedUrl::parse("http://localhost:8080?utm_medium=mail").unwrap();
+        assert_eq!(url.url, "http://localhost:8080/");
+        assert_eq!(url.port, Some(8080));
+        assert_eq!(url.path, "/");
+        assert_eq!(url.query, None);
+    }
+
+    #[test]
+    fn test_reject() {
+        for input in ["", "leptos.dev", "ftp://leptos.dev/", "javascript:alert(1)", "https://", "https://user:pw@leptos.dev/"] {
+            assert!(NormalizedUrl::parse(input).is_err(), "{}", input);
+        }
+    }
+}
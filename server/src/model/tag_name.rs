@@ -0,0 +1,40 @@
+/// Longest tag, in characters
+const MAX_TAG_LENGTH: usize = 50;
+
+/// A tag as stored and matched: trimmed, lowercase, inner whitespace
+/// joined with `-`, so `Web  Framework` and `web-framework` are one tag.
+/// The name doubles as the id of the `tag` record.
+pub fn tag_name(raw: &str) -> Result<String, String> {
+    let name = raw
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>()
+        .join("-");
+
+    if name.is_empty() {
+        return Err("tag must not be blank".to_string());
+    }
+    if name.chars().count() > MAX_TAG_LENGTH {
+        return Err(format!("tag '{}' is longer than {} characters", name, MAX_TAG_LENGTH));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(format!("tag '{}' contains control characters", name.escape_default()));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tag_name;
+
+    #[test]
+    fn test_tag_name() {
+        assert_eq!(tag_name(" Web  Framework ").unwrap(), "web-framework");
+        assert_eq!(tag_name("Rust").unwrap(), "rust");
+        assert_eq!(tag_name("c++").unwrap(), "c++");
+
+        assert!(tag_name("  ").is_err());
+        assert!(tag_name(&"x".repeat(51)).is_err());
+        assert!(tag_name("a\u{7}b").is_err());
+    }
+}
@@ -0,0 +1,354 @@
+use crate::model::NormalizedUrl;
+use serde::Deserialize;
+use surrealdb::engine::any::Any;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use surrealdb::sql::{Datetime, Thing};
+use surrealdb::{Error, Surreal};
+use tracing::log::{log, Level};
+
+/// Schema of the `url` table. Every statement is idempotent, so the whole
+/// script runs on each start.
+const SCHEMA: &str = r#"
+    -- Full-text search over one field joining all searchable columns, so each
+    -- term of a query may match a different column. The unit separator lets
+    -- highlights be split back into columns.
+    DEFINE ANALYZER IF NOT EXISTS url_search TOKENIZERS blank, class, punct FILTERS lowercase, ascii, snowball(english);
+    DEFINE FIELD IF NOT EXISTS search_text ON url
+        VALUE string::join(' \u{1f} ', title ?? '', auto_descr ?? '', man_descr ?? '', host ?? '', url ?? '');
+    DEFINE INDEX IF NOT EXISTS url_search_text ON url FIELDS search_text SEARCH ANALYZER url_search BM25 HIGHLIGHTS;
+
+    -- Rows written before search_text existed
+    UPDATE url WHERE search_text = NONE;
+
+    -- One row per normalized url, the key is computed in Rust, see `NormalizedUrl::key`
+    DEFINE INDEX IF NOT EXISTS url_key ON url FIELDS url_key UNIQUE;
+"#;
+
+/// Every change of a `url` row, see `url_history::write_with_history`.
+/// Entries are never changed, a revert appends a new one.
+const HISTORY: &str = r#"
+    DEFINE TABLE IF NOT EXISTS url_history SCHEMALESS;
+    DEFINE INDEX IF NOT EXISTS url_history_version ON url_history FIELDS uri_uuid, version UNIQUE;
+    DEFINE EVENT IF NOT EXISTS append_only ON url_history WHEN $event != 'CREATE' THEN {
+        THROW 'url_history is append-only'
+    };
+"#;
+
+/// Tags are `tag` records named by their id, see `model::tag_name`, and
+/// `url->tagged->tag` edges
+const TAGS: &str = r#"
+    DEFINE TABLE IF NOT EXISTS tag SCHEMALESS;
+    DEFINE TABLE IF NOT EXISTS tagged TYPE RELATION IN url OUT tag;
+    DEFINE INDEX IF NOT EXISTS tagged_pair ON tagged FIELDS in, out UNIQUE;
+"#;
+
+/// Result of the last link check of each url, the record id is its
+/// `uri_uuid`, see `url_link_health`
+const LINK_HEALTH: &str = r#"
+    DEFINE TABLE IF NOT EXISTS link_health SCHEMALESS;
+"#;
+
+/// Runs after the legacy ids are rewritten, two inserts in the same instant
+/// got the same `UUID_<timestamp>` id. See `define_unique_ids` for ids
+/// shared otherwise.
+const UNIQUE_IDS: &str = r#"
+    DEFINE INDEX IF NOT EXISTS uri_uuid ON url FIELDS uri_uuid UNIQUE;
+"#;
+
+/// Runs after the string timestamps are converted
+const DATETIMES: &str = r#"
+    DEFINE FIELD IF NOT EXISTS crea_time ON url TYPE datetime;
+    DEFINE FIELD IF NOT EXISTS modi_time ON url TYPE datetime;
+"#;
+
+/// Format of `Utc::now().to_string()`, once used for timestamps
+const LEGACY_TIME: &str = "%Y-%m-%d %H:%M:%S%.f UTC";
+
+#[derive(Debug, Deserialize)]
+struct KeylessRow {
+    id: Thing,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StringTimeRow {
+    id: Thing,
+    crea_time: Option<String>,
+    modi_time: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SharedId {
+    uri_uuid: String,
+    rows: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LegacyIdRow {
+    id: Thing,
+    url: String,
+    uri_uuid: String,
+}
+
+/// Brings the database schema up to date, called once before serving
+pub async fn migrate(client: &Surreal<Any>) -> Result<(), Error> {
+    log!(Level::Info, "Migrating database schema");
+    client.query(SCHEMA).await?.check()?;
+    backfill_url_keys(client).await?;
+    rewrite_legacy_ids(client).await?;
+    define_unique_ids(client).await?;
+    convert_string_times(client).await?;
+    client.query(DATETIMES).await?.check()?;
+    client.query(HISTORY).await?.check()?;
+    client.query(TAGS).await?.check()?;
+    client.query(LINK_HEALTH).await?.check()?;
+    Ok(())
+}
+
+/// Rows written before `url_key` existed. A row duplicating an older one
+/// stays without key, `duplicateCandidates` lists it for the curators.
+async fn backfill_url_keys(client: &Surreal<Any>) -> Result<(), Error> {
+    let rows: Vec<KeylessRow> = client
+        // The unique index holds no NONE keys, it cannot answer this
+        .query("SELECT id, url FROM url WITH NOINDEX WHERE url_key = NONE ORDER BY id")
+        .await?.take(0)?;
+
+    for row in rows {
+        let Ok(url) = NormalizedUrl::parse(&row.url) else {
+            log!(Level::Warn, "Cannot normalize url of {}: {}", row.id, row.url);
+            continue;
+        };
+        let response = client
+            .query("UPDATE type::thing('url', $id) SET url_key = $url_key")
+            .bind(("id", row.id.id.to_raw()))
+            .bind(("url_key", url.key()))
+            .await?;
+        if let Err(e) = response.check() {
+            log!(Level::Warn, "Url of {} is a duplicate: {}", row.id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the `UUID_<timestamp>` ids of the first API inserts with the
+/// ids new records get, see `NormalizedUrl::uri_uuid`
+async fn rewrite_legacy_ids(client: &Surreal<Any>) -> Result<(), Error> {
+    let rows: Vec<LegacyIdRow> = client
+        .query("SELECT id, url, uri_uuid FROM url WITH NOINDEX WHERE string::starts_with(uri_uuid ?? '', 'UUID_') ORDER BY id")
+        .await?.take(0)?;
+
+    for row in rows {
+        let Ok(url) = NormalizedUrl::parse(&row.url) else {
+            log!(Level::Warn, "Cannot normalize url of {}, keeping id {}", row.id, row.uri_uuid);
+            continue;
+        };
+        let taken: Option<Thing> = client
+            .query("SELECT VALUE id FROM url WITH NOINDEX WHERE uri_uuid = $uri_uuid LIMIT 1")
+            .bind(("uri_uuid", url.uri_uuid()))
+            .await?.take(0)?;
+        if let Some(other) = taken {
+            log!(Level::Warn, "Id of {} is taken by {}, keeping id {}", row.id, other, row.uri_uuid);
+            continue;
+        }
+
+        log!(Level::Info, "Renaming {} to {}", row.uri_uuid, url.uri_uuid());
+        client
+            .query("UPDATE type::thing('url', $id) SET uri_uuid = $uri_uuid")
+            .bind(("id", row.id.id.to_raw()))
+            .bind(("uri_uuid", url.uri_uuid()))
+            .await?.check()?;
+    }
+    Ok(())
+}
+
+/// Defines the unique index of `uri_uuid` unless rows share an id, as the
+/// rows an `initDatabase` run twice copied do. Those stay without index,
+/// and are logged for the curators to remove the copies.
+async fn define_unique_ids(client: &Surreal<Any>) -> Result<(), Error> {
+    let shared: Vec<SharedId> = client
+        .query("SELECT uri_uuid, count() AS rows FROM url WITH NOINDEX WHERE uri_uuid != NONE GROUP BY uri_uuid")
+        .await?.take(0)?;
+    let shared = shared.into_iter()
+        .filter(|id| id.rows > 1)
+        .map(|id| format!("{} ({} rows)", id.uri_uuid, id.rows))
+        .collect::<Vec<String>>();
+    if !shared.is_empty() {
+        log!(Level::Warn, "Ids shared by several rows, not indexing uri_uuid: {}", shared.join(", "));
+        return Ok(());
+    }
+
+    client.query(UNIQUE_IDS).await?.check()?;
+    Ok(())
+}
+
+/// RFC 3339 from the import file or the legacy format
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(time, LEGACY_TIME).map(|t| t.and_utc()))
+        .ok()
+}
+
+/// Rows written while timestamps were stored as strings. An unreadable
+/// time fails the migration before any row is converted, it has to be set
+/// by hand rather than made up.
+async fn convert_string_times(client: &Surreal<Any>) -> Result<(), Error> {
+    let rows: Vec<StringTimeRow> = client
+        .query("SELECT id, \
+                    (IF type::is::string(crea_time) THEN crea_time END) AS crea_time, \
+                    (IF type::is::string(modi_time) THEN modi_time END) AS modi_time \
+                FROM url WHERE type::is::string(crea_time) OR type::is::string(modi_time)")
+        .await?.take(0)?;
+
+    let mut converted = Vec::new();
+    let mut unreadable = Vec::new();
+    for row in rows {
+        let mut times = Vec::new();
+        for (name, time) in [("crea_time", row.crea_time), ("modi_time", row.modi_time)] {
+            match time.map(|time| parse_time(&time).ok_or(time)).transpose() {
+                Ok(time) => times.push((name, time.map(Datetime::from))),
+                Err(time) => {
+                    log!(Level::Error, "Cannot read {} '{}' of {}", name, time, row.id);
+                    unreadable.push(format!("{} of {}", name, row.id));
+                }
+            }
+        }
+        converted.push((row.id, times));
+    }
+    if !unreadable.is_empty() {
+        return Err(Error::Api(surrealdb::error::Api::Query(format!("unreadable times, set them by hand: {}", unreadable.join(", ")))));
+    }
+
+    for (id, times) in converted {
+        let mut update = client
+            .query("UPDATE type::thing('url', $id) SET crea_time = $crea_time ?? crea_time, modi_time = $modi_time ?? modi_time")
+            .bind(("id", id.id.to_raw()));
+        for time in times {
+            update = update.bind(time);
+        }
+        update.await?.check()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::migrate;
+    use crate::config::connect_test_db;
+    use crate::model::NormalizedUrl;
+    use surrealdb::sql::Datetime;
+
+    #[tokio::test]
+    async fn test_backfill_url_keys() {
+        let db = connect_test_db().await;
+        db.query("CREATE url SET crea_time = time::now(), modi_time = time::now(), url = 'https://github.com/rust-lang/rust-clippy/'; \
+                  CREATE url SET crea_time = time::now(), modi_time = time::now(), url = 'http://github.com/rust-lang/rust-clippy'; \
+                  CREATE url SET crea_time = time::now(), modi_time = time::now(), url = 'not a url';")
+            .await.unwrap().check().unwrap();
+
+        migrate(&db).await.unwrap();
+        // Idempotent, the duplicate stays without key
+        migrate(&db).await.unwrap();
+
+        let keys: Vec<Option<String>> = db.query("SELECT VALUE url_key FROM url").await.unwrap().take(0).unwrap();
+        let mut keys = keys.into_iter().flatten().collect::<Vec<String>>();
+        keys.sort();
+        assert_eq!(keys, vec!["github.com/rust-lang/rust-clippy"]);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_legacy_ids() {
+        let db = connect_test_db().await;
+        db.query("REMOVE INDEX uri_uuid ON url; \
+                  CREATE url SET crea_time = time::now(), modi_time = time::now(), uri_uuid = 'UUID_2024-08-29 17:48:10 UTC', url = 'https://leptos.dev/'; \
+                  CREATE url SET crea_time = time::now(), modi_time = time::now(), uri_uuid = 'UUID_2024-08-29 17:48:10 UTC', url = 'https://actix.rs/'; \
+                  CREATE url SET crea_time = time::now(), modi_time = time::now(), uri_uuid = 'c750fdb6b6b4f69d9e1d293775e917da5257be09260174e25f0fb89e4cba8d0f', url = 'https://2024.rustnl.org/live/';")
+            .await.unwrap().check().unwrap();
+
+        migrate(&db).await.unwrap();
+
+        let ids: Vec<String> = db.query("SELECT VALUE uri_uuid FROM (SELECT uri_uuid, url FROM url ORDER BY url)").await.unwrap().take(0).unwrap();
+        assert_eq!(ids, vec![
+            "c750fdb6b6b4f69d9e1d293775e917da5257be09260174e25f0fb89e4cba8d0f".to_string(),
+            NormalizedUrl::parse("https://actix.rs/").unwrap().uri_uuid(),
+            NormalizedUrl::parse("https://leptos.dev/").unwrap().uri_uuid(),
+        ]);
+
+        let duplicate = db.query("CREATE url SET crea_time = time::now(), modi_time = time::now(), uri_uuid = $uri_uuid, url = 'https://other.example/'")
+            .bind(("uri_uuid", ids[0].clone()))
+            .await.unwrap().check();
+        assert!(duplicate.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shared_ids() {
+        let db = connect_test_db().await;
+        db.query("REMOVE INDEX uri_uuid ON url; \
+                  CREATE url:first SET crea_time = time::now(), modi_time = time::now(), uri_uuid = 'a', url = 'https://leptos.dev/'; \
+                  CREATE url:copy SET crea_time = time::now(), modi_time = time::now(), uri_uuid = 'a', url = 'https://leptos.dev/'; \
+                  CREATE url:other SET crea_time = time::now(), modi_time = time::now(), uri_uuid = 'b', url = 'https://actix.rs/';")
+            .await.unwrap().check().unwrap();
+
+        // Starts without the index while the copy is there
+        migrate(&db).await.unwrap();
+        let unindexed = db.query("CREATE url SET crea_time = time::now(), modi_time = time::now(), uri_uuid = 'b', url = 'https://tokio.rs/'")
+            .await.unwrap().check();
+        assert!(unindexed.is_ok());
+
+        db.query("DELETE url:copy; DELETE url WHERE url = 'https://tokio.rs/'").await.unwrap().check().unwrap();
+        migrate(&db).await.unwrap();
+        let duplicate = db.query("CREATE url SET crea_time = time::now(), modi_time = time::now(), uri_uuid = 'b', url = 'https://tokio.rs/'")
+            .await.unwrap().check();
+        assert!(duplicate.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_convert_string_times() {
+        let db = connect_test_db().await;
+        db.query("REMOVE FIELD crea_time ON url; REMOVE FIELD modi_time ON url; \
+                  CREATE url:imported SET url = 'https://leptos.dev/', crea_time = '2024-08-29T17:48:10.384049834+02:00', modi_time = '2024-08-29T17:48:10.384049834+02:00'; \
+                  CREATE url:legacy SET url = 'https://actix.rs/', crea_time = '2024-09-01 10:00:00.5 UTC', modi_time = d'2024-09-02T10:00:00Z'; \
+                  CREATE url:broken SET url = 'https://tokio.rs/', crea_time = 'yesterday', modi_time = d'2024-09-02T10:00:00Z';")
+            .await.unwrap().check().unwrap();
+
+        // Nothing is converted while a time is unreadable
+        let error = migrate(&db).await.unwrap_err();
+        assert_eq!(error.to_string(), "unreadable times, set them by hand: crea_time of url:broken");
+        let strings: Vec<String> = db.query("SELECT VALUE crea_time FROM url:imported").await.unwrap().take(0).unwrap();
+        assert_eq!(strings.len(), 1);
+
+        db.query("UPDATE url:broken SET crea_time = d'2024-09-01T08:00:00Z'").await.unwrap().check().unwrap();
+        migrate(&db).await.unwrap();
+
+        let times: Vec<Datetime> = db.query("SELECT VALUE [crea_time, modi_time] FROM [url:imported, url:legacy]")
+            .await.unwrap().take::<Vec<Vec<Datetime>>>(0).unwrap()
+            .concat();
+        let times = times.iter().map(|t| t.0.to_rfc3339()).collect::<Vec<String>>();
+        assert_eq!(times, vec![
+            "2024-08-29T15:48:10.384049834+00:00",
+            "2024-08-29T15:48:10.384049834+00:00",
+            "2024-09-01T10:00:00.500+00:00",
+            "2024-09-02T10:00:00+00:00",
+        ]);
+
+        let typed = db.query("CREATE url SET crea_time = 'yesterday'").await.unwrap().check();
+        assert!(typed.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_history_is_append_only() {
+        let db = connect_test_db().await;
+        db.query("CREATE url_history SET uri_uuid = 'a', version = 1, operation = 'CREATE', actor = 'api', time = time::now()")
+            .await.unwrap().check().unwrap();
+
+        let duplicate = db.query("CREATE url_history SET uri_uuid = 'a', version = 1").await.unwrap().check();
+        assert!(duplicate.is_err());
+        let updated = db.query("UPDATE url_history SET actor = 'someone'").await.unwrap().check();
+        assert!(updated.is_err());
+        let deleted = db.query("DELETE url_history").await.unwrap().check();
+        assert!(deleted.is_err());
+
+        let actors: Vec<String> = db.query("SELECT VALUE actor FROM url_history").await.unwrap().take(0).unwrap();
+        assert_eq!(actors, vec!["api"]);
+    }
+}
@@ -1,8 +1,34 @@
+mod migration;
+mod url_filter;
+mod url_history;
+mod url_link_health;
 mod url_repository;
+mod url_search;
+mod url_tags;
+
+pub use migration::migrate;
+pub use url_filter::{Condition, OrderBy, UrlCursor};
+pub use url_history::{select_history, select_history_snapshot};
+pub use url_link_health::{delete_orphaned_link_health, select_link_targets, store_link_health};
 
 pub use url_repository::count_url;
 pub use url_repository::delete_by_uri_uuid;
+pub use url_repository::find_by_url_key;
+pub use url_repository::find_import_target;
+pub use url_repository::find_live_url;
+#[cfg(test)]
 pub use url_repository::insert_init_meetup_url;
 pub use url_repository::insert_meetup_url;
-pub use url_repository::select_url;
+pub use url_repository::ImportWrite;
+pub use url_repository::Patched;
+pub use url_repository::purge_deleted;
+pub use url_repository::restore_meetup_url;
+pub use url_repository::revert_meetup_url;
+pub use url_repository::select_url_slice;
+pub use url_repository::select_url_with_count;
+pub use url_repository::Seek;
+pub use url_repository::store_metadata;
 pub use url_repository::update_meetup_url;
+pub use url_repository::write_imported;
+pub use url_search::search_url;
+pub use url_tags::{add_tags, remove_tags, select_tags};
@@ -0,0 +1,490 @@
+use crate::graphql::{LinkState, MeetupUrl, MeetupUrlFilter, MeetupUrlOrder, MeetupUrlSort, SortDirection, TagMatch};
+use crate::model::tag_name;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Datetimes in sort keys, fixed width so the strings sort like the times
+const SORTABLE_TIME: &str = "%Y-%m-%dT%H:%M:%S%.9fZ";
+
+/// The description the clients and exports show: the curator's one when
+/// there is one, else the one read from the page
+const SHOWN_DESCRIPTION: &str = "(IF string::trim(man_descr ?? '') != '' THEN man_descr ELSE auto_descr END)";
+
+/// Columns of the `url` table a filter may address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlField {
+    Host,
+    Title,
+    Url,
+    AutoDescr,
+    /// `man_descr` or `auto_descr` as shown, only sorted by
+    Description,
+    CreaTime,
+    ModiTime,
+}
+
+impl UrlField {
+    pub fn column(&self) -> &'static str {
+        match self {
+            UrlField::Host => "host",
+            UrlField::Title => "title",
+            UrlField::Url => "url",
+            UrlField::AutoDescr => "auto_descr",
+            UrlField::Description => "description",
+            UrlField::CreaTime => "crea_time",
+            UrlField::ModiTime => "modi_time",
+        }
+    }
+
+    /// Text of the column, times in RFC 3339
+    pub fn value(&self, url: &MeetupUrl) -> String {
+        match self {
+            UrlField::Host => url.host.clone(),
+            UrlField::Title => url.title.clone(),
+            UrlField::Url => url.url.clone(),
+            UrlField::AutoDescr => url.auto_descr.clone(),
+            UrlField::Description if url.man_descr.trim().is_empty() => url.auto_descr.clone(),
+            UrlField::Description => url.man_descr.clone(),
+            UrlField::CreaTime => url.crea_time.to_rfc3339(),
+            UrlField::ModiTime => url.modi_time.to_rfc3339(),
+        }
+    }
+
+    fn time(&self, url: &MeetupUrl) -> Option<DateTime<Utc>> {
+        match self {
+            UrlField::CreaTime => Some(url.crea_time),
+            UrlField::ModiTime => Some(url.modi_time),
+            _ => None,
+        }
+    }
+
+    /// What the column sorts by: lowercased text, or the time as a string
+    pub fn sort_expression(&self) -> String {
+        match self {
+            UrlField::CreaTime | UrlField::ModiTime => format!("time::format({}, '{}')", self.column(), SORTABLE_TIME),
+            UrlField::Description => format!("string::lowercase({})", SHOWN_DESCRIPTION),
+            _ => format!("string::lowercase({})", self.column()),
+        }
+    }
+
+    /// `sort_expression` evaluated in memory
+    pub fn sort_value(&self, url: &MeetupUrl) -> String {
+        match self.time(url) {
+            Some(time) => time.format(SORTABLE_TIME).to_string(),
+            None => self.value(url).to_lowercase(),
+        }
+    }
+}
+
+impl From<MeetupUrlSort> for UrlField {
+    fn from(sort: MeetupUrlSort) -> Self {
+        match sort {
+            MeetupUrlSort::Domain => UrlField::Host,
+            MeetupUrlSort::Title => UrlField::Title,
+            MeetupUrlSort::Url => UrlField::Url,
+            MeetupUrlSort::Description => UrlField::Description,
+            MeetupUrlSort::Created => UrlField::CreaTime,
+            MeetupUrlSort::Modified => UrlField::ModiTime,
+        }
+    }
+}
+
+/// Typed WHERE clause. User input only ever ends up in the bindings,
+/// never in the query text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Always,
+    Matches(UrlField, String),
+    /// At or after the time
+    Since(UrlField, DateTime<Utc>),
+    /// Strictly before the time
+    Until(UrlField, DateTime<Utc>),
+    /// In the trash, or not
+    Deleted(bool),
+    /// Carries any or all of the tags, by name
+    Tagged(Vec<String>, TagMatch),
+    /// Last checked in the state
+    Link(LinkState),
+    And(Vec<Condition>),
+}
+
+impl Condition {
+    pub fn to_surql(&self, bindings: &mut Bindings) -> String {
+        match self {
+            Condition::Always => "true".to_string(),
+            Condition::Matches(field, pattern) => {
+                let param = bindings.push(Value::String(pattern.clone()));
+                format!("string::matches({}, {})", field.column(), param)
+            }
+            Condition::Since(field, time) => {
+                let param = bindings.push(Value::String(time.to_rfc3339()));
+                format!("{} >= <datetime> {}", field.column(), param)
+            }
+            Condition::Until(field, time) => {
+                let param = bindings.push(Value::String(time.to_rfc3339()));
+                format!("{} < <datetime> {}", field.column(), param)
+            }
+            Condition::Deleted(true) => "deleted_at != NONE".to_string(),
+            Condition::Deleted(false) => "deleted_at = NONE".to_string(),
+            Condition::Tagged(names, mode) => {
+                let param = bindings.push(Value::from(names.clone()));
+                let operator = match mode {
+                    TagMatch::Any => "ANYINSIDE",
+                    TagMatch::All => "ALLINSIDE",
+                };
+                format!("{} {} ->tagged->tag.name", param, operator)
+            }
+            Condition::Link(state) => {
+                let param = bindings.push(serde_json::to_value(state).unwrap_or_default());
+                format!("type::thing('link_health', uri_uuid).state = {}", param)
+            }
+            Condition::And(conditions) if conditions.is_empty() => "true".to_string(),
+            Condition::And(conditions) => conditions
+                .iter()
+                .map(|c| format!("({})", c.to_surql(bindings)))
+                .collect::<Vec<String>>()
+                .join(" AND "),
+        }
+    }
+
+    /// Evaluates the condition against a single record, with the semantics
+    /// of `string::matches`. An invalid pattern matches nothing, as in the database.
+    pub fn matches(&self, url: &MeetupUrl) -> bool {
+        match self {
+            Condition::Always => true,
+            Condition::Matches(field, pattern) => Regex::new(pattern)
+                .map(|re| re.is_match(&field.value(url)))
+                .unwrap_or(false),
+            Condition::Since(field, time) => field.time(url).is_some_and(|t| t >= *time),
+            Condition::Until(field, time) => field.time(url).is_some_and(|t| t < *time),
+            Condition::Deleted(deleted) => url.deleted_at.is_some() == *deleted,
+            Condition::Tagged(names, mode) => {
+                let carries = |name: &String| url.tags.iter().any(|tag| &tag.name == name);
+                match mode {
+                    TagMatch::Any => names.iter().any(carries),
+                    TagMatch::All => names.iter().all(carries),
+                }
+            }
+            Condition::Link(state) => url.link_health.as_ref().is_some_and(|health| health.state == *state),
+            Condition::And(conditions) => conditions.iter().all(|c| c.matches(url)),
+        }
+    }
+}
+
+impl From<&MeetupUrlFilter> for Condition {
+    fn from(filter: &MeetupUrlFilter) -> Self {
+        let conditions = [
+            (UrlField::AutoDescr, &filter.description),
+            (UrlField::Host, &filter.domain),
+            (UrlField::Url, &filter.url),
+            (UrlField::Title, &filter.title),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| value.clone().map(|v| Condition::Matches(field, v)))
+        .chain([
+            filter.created_after.map(|t| Condition::Since(UrlField::CreaTime, t)),
+            filter.created_before.map(|t| Condition::Until(UrlField::CreaTime, t)),
+            filter.modified_after.map(|t| Condition::Since(UrlField::ModiTime, t)),
+            filter.modified_before.map(|t| Condition::Until(UrlField::ModiTime, t)),
+            filter.tags.as_ref()
+                .map(|tags| tags.names.iter().filter_map(|name| tag_name(name).ok()).collect::<Vec<String>>())
+                .filter(|names| !names.is_empty())
+                .map(|names| Condition::Tagged(names, filter.tags.as_ref().map(|tags| tags.mode).unwrap_or_default())),
+            filter.link_state.map(Condition::Link),
+            match (filter.only_deleted, filter.include_deleted) {
+                (Some(true), _) => Some(Condition::Deleted(true)),
+                (_, Some(true)) => None,
+                _ => Some(Condition::Deleted(false)),
+            },
+        ].into_iter().flatten())
+        .collect::<Vec<Condition>>();
+
+        if conditions.is_empty() {
+            Condition::Always
+        } else {
+            Condition::And(conditions)
+        }
+    }
+}
+
+/// Typed ORDER BY clause. Strings sort case-insensitively on lowercased
+/// copies selected as `sort_N`, SurrealDB only orders by selected fields.
+/// Times sort on fixed-width strings, so cursors only ever hold strings.
+/// Always ends on the record id, so rows with equal sort keys keep their
+/// order from one page to the next.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OrderBy(Vec<(UrlField, SortDirection)>);
+
+impl OrderBy {
+    /// Fields to add to `SELECT *`
+    pub fn projection(&self) -> String {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(i, (field, _))| format!(", {} AS sort_{}", field.sort_expression(), i))
+            .collect()
+    }
+
+    /// `reverse` flips every direction, used to read a page backwards
+    pub fn to_surql(&self, reverse: bool) -> String {
+        let keyword = |direction: SortDirection| match (direction, reverse) {
+            (SortDirection::Asc, false) | (SortDirection::Desc, true) => "ASC",
+            (SortDirection::Desc, false) | (SortDirection::Asc, true) => "DESC",
+        };
+
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(i, (_, direction))| format!("sort_{} {}", i, keyword(*direction)))
+            .chain(std::iter::once(format!("id {}", keyword(SortDirection::Asc))))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Identifies the sort order a cursor was taken in
+    pub fn signature(&self) -> String {
+        self.0
+            .iter()
+            .map(|(field, direction)| format!("{}:{:?}", field.column(), direction))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    pub fn cursor(&self, url: &MeetupUrl, id: String) -> UrlCursor {
+        UrlCursor {
+            order: self.signature(),
+            keys: self.0.iter().map(|(field, _)| field.sort_value(url)).collect(),
+            id,
+        }
+    }
+
+    /// Whether `cursor` was taken in this order and holds a key for each
+    /// field, as `seek` relies on. Cursors come back from clients, which may
+    /// have edited them.
+    pub fn check_cursor(&self, cursor: &UrlCursor) -> Result<(), String> {
+        if cursor.order != self.signature() {
+            return Err("cursor belongs to a different sort order".to_string());
+        }
+        if cursor.keys.len() != self.0.len() {
+            return Err(format!("cursor holds {} sort keys, the order has {}", cursor.keys.len(), self.0.len()));
+        }
+        Ok(())
+    }
+
+    /// Rows strictly after `cursor` in this order, or strictly before it when
+    /// `before` is set, see `check_cursor`. Expands the tuple comparison of the sort keys and the
+    /// id into `k0 > v0 OR (k0 = v0 AND k1 > v1) OR ...`, each key with the
+    /// operator of its own direction.
+    pub fn seek(&self, cursor: &UrlCursor, before: bool, bindings: &mut Bindings) -> String {
+        let operator = |direction: SortDirection| match (direction, before) {
+            (SortDirection::Asc, false) | (SortDirection::Desc, true) => ">",
+            (SortDirection::Desc, false) | (SortDirection::Asc, true) => "<",
+        };
+
+        let mut keys = self.0
+            .iter()
+            .zip(&cursor.keys)
+            .map(|((field, direction), value)| {
+                let param = bindings.push(Value::String(value.clone()));
+                (field.sort_expression(), param, *direction)
+            })
+            .collect::<Vec<(String, String, SortDirection)>>();
+        let id = bindings.push(Value::String(cursor.id.clone()));
+        keys.push(("id".to_string(), format!("type::thing('url', {})", id), SortDirection::Asc));
+
+        (0..keys.len())
+            .map(|i| {
+                let (column, param, direction) = &keys[i];
+                keys[..i]
+                    .iter()
+                    .map(|(c, p, _)| format!("{} = {}", c, p))
+                    .chain(std::iter::once(format!("{} {} {}", column, operator(*direction), param)))
+                    .collect::<Vec<String>>()
+                    .join(" AND ")
+            })
+            .map(|clause| format!("({})", clause))
+            .collect::<Vec<String>>()
+            .join(" OR ")
+    }
+}
+
+/// A field named twice is sorted by its first occurrence
+impl From<&[MeetupUrlOrder]> for OrderBy {
+    fn from(orders: &[MeetupUrlOrder]) -> Self {
+        let mut keys: Vec<(UrlField, SortDirection)> = Vec::new();
+        for order in orders {
+            let field = UrlField::from(order.field);
+            if !keys.iter().any(|(f, _)| *f == field) {
+                keys.push((field, order.direction));
+            }
+        }
+        OrderBy(keys)
+    }
+}
+
+/// Position of a row in a sorted list, handed to clients as an opaque cursor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlCursor {
+    /// `OrderBy::signature` of the list the cursor belongs to
+    pub order: String,
+    /// `UrlField::sort_value` of each key
+    pub keys: Vec<String>,
+    /// Record id without the table
+    pub id: String,
+}
+
+/// Named parameters collected while rendering a query, passed to `.bind(...)`.
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    values: BTreeMap<String, Value>,
+}
+
+impl Bindings {
+    pub fn push(&mut self, value: Value) -> String {
+        let name = format!("p{}", self.values.len());
+        self.values.insert(name.clone(), value);
+        format!("${}", name)
+    }
+
+    pub fn into_inner(self) -> BTreeMap<String, Value> {
+        self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bindings, Condition, OrderBy, UrlField};
+    use crate::graphql::{MeetupUrl, MeetupUrlFilter, MeetupUrlOrder, MeetupUrlSort, SortDirection, Tag, TagFilter, TagMatch};
+    use serde_json::Value;
+
+    fn filter() -> MeetupUrlFilter {
+        MeetupUrlFilter::default()
+    }
+
+    #[test]
+    fn test_empty_filter() {
+        let mut bindings = Bindings::default();
+        let condition = Condition::from(&MeetupUrlFilter { include_deleted: Some(true), ..filter() });
+
+        assert_eq!(condition, Condition::Always);
+        assert_eq!(condition.to_surql(&mut bindings), "true");
+        assert!(bindings.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_tags() {
+        let tags = |names: &[&str], mode| Some(TagFilter { names: names.iter().map(|n| n.to_string()).collect(), mode });
+        let condition = Condition::from(&MeetupUrlFilter { tags: tags(&["Web Framework", "rust"], TagMatch::All), include_deleted: Some(true), ..filter() });
+
+        let mut bindings = Bindings::default();
+        assert_eq!(condition.to_surql(&mut bindings), "($p0 ALLINSIDE ->tagged->tag.name)");
+        assert_eq!(bindings.into_inner().get("p0"), Some(&serde_json::json!(["web-framework", "rust"])));
+
+        let url = MeetupUrl { tags: vec![Tag { name: "rust".to_string(), count: 1 }], ..MeetupUrl::default() };
+        assert!(!condition.matches(&url));
+        let any = Condition::from(&MeetupUrlFilter { tags: tags(&["Web Framework", "rust"], TagMatch::Any), include_deleted: Some(true), ..filter() });
+        assert!(any.matches(&url));
+
+        let blank = Condition::from(&MeetupUrlFilter { tags: tags(&[" "], TagMatch::Any), include_deleted: Some(true), ..filter() });
+        assert_eq!(blank, Condition::Always);
+    }
+
+    #[test]
+    fn test_trash() {
+        let mut bindings = Bindings::default();
+        assert_eq!(Condition::from(&filter()).to_surql(&mut bindings), "(deleted_at = NONE)");
+
+        let only = Condition::from(&MeetupUrlFilter { only_deleted: Some(true), include_deleted: Some(false), ..filter() });
+        assert_eq!(only, Condition::And(vec![Condition::Deleted(true)]));
+
+        let deleted = MeetupUrl { deleted_at: Some(chrono::Utc::now()), ..MeetupUrl::default() };
+        assert!(only.matches(&deleted));
+        assert!(!Condition::from(&filter()).matches(&deleted));
+        assert!(!only.matches(&MeetupUrl::default()));
+    }
+
+    #[test]
+    fn test_values_are_bound() {
+        let hostile = "'); DELETE url; --".to_string();
+        let mut bindings = Bindings::default();
+        let condition = Condition::from(&MeetupUrlFilter {
+            title: Some(hostile.clone()),
+            domain: Some("rust".to_string()),
+            ..filter()
+        });
+
+        assert_eq!(
+            condition,
+            Condition::And(vec![
+                Condition::Matches(UrlField::Host, "rust".to_string()),
+                Condition::Matches(UrlField::Title, hostile.clone()),
+                Condition::Deleted(false),
+            ])
+        );
+
+        let surql = condition.to_surql(&mut bindings);
+        assert_eq!(surql, "(string::matches(host, $p0)) AND (string::matches(title, $p1)) AND (deleted_at = NONE)");
+
+        let values = bindings.into_inner();
+        assert_eq!(values.get("p1"), Some(&Value::String(hostile)));
+    }
+
+    #[test]
+    fn test_matches_in_memory() {
+        let url = MeetupUrl {
+            host: "leptos.dev".to_string(),
+            title: "Leptos".to_string(),
+            ..MeetupUrl::default()
+        };
+
+        assert!(Condition::from(&filter()).matches(&url));
+        assert!(Condition::from(&MeetupUrlFilter { domain: Some("^leptos".to_string()), ..filter() }).matches(&url));
+        assert!(!Condition::from(&MeetupUrlFilter {
+            domain: Some("leptos".to_string()),
+            title: Some("Actix".to_string()),
+            ..filter()
+        }).matches(&url));
+        assert!(!Condition::from(&MeetupUrlFilter { title: Some("(".to_string()), ..filter() }).matches(&url));
+    }
+
+    #[test]
+    fn test_order_by() {
+        assert_eq!(OrderBy::default().projection(), "");
+        assert_eq!(OrderBy::default().to_surql(false), "id ASC");
+
+        let order = OrderBy::from(&[
+            MeetupUrlOrder { field: MeetupUrlSort::Domain, direction: SortDirection::Desc },
+            MeetupUrlOrder { field: MeetupUrlSort::Title, direction: SortDirection::Asc },
+            MeetupUrlOrder { field: MeetupUrlSort::Domain, direction: SortDirection::Asc },
+        ][..]);
+
+        assert_eq!(order.projection(), ", string::lowercase(host) AS sort_0, string::lowercase(title) AS sort_1");
+        assert_eq!(order.to_surql(false), "sort_0 DESC, sort_1 ASC, id ASC");
+        assert_eq!(order.to_surql(true), "sort_0 ASC, sort_1 DESC, id DESC");
+    }
+
+    #[test]
+    fn test_seek() {
+        let order = OrderBy::from(&[
+            MeetupUrlOrder { field: MeetupUrlSort::Domain, direction: SortDirection::Desc },
+        ][..]);
+        let url = MeetupUrl { host: "Leptos.dev".to_string(), ..MeetupUrl::default() };
+        let cursor = order.cursor(&url, "x1".to_string());
+
+        assert_eq!(cursor.keys, vec!["leptos.dev".to_string()]);
+
+        let mut bindings = Bindings::default();
+        assert_eq!(
+            order.seek(&cursor, false, &mut bindings),
+            "(string::lowercase(host) < $p0) OR (string::lowercase(host) = $p0 AND id > type::thing('url', $p1))"
+        );
+        assert_eq!(
+            order.seek(&cursor, true, &mut Bindings::default()),
+            "(string::lowercase(host) > $p0) OR (string::lowercase(host) = $p0 AND id < type::thing('url', $p1))"
+        );
+        assert_eq!(bindings.into_inner().get("p1"), Some(&Value::String("x1".to_string())));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::graphql::{FieldChange, HistoryOperation, MeetupUrlHistoryEntry};
+use crate::model::MeetupUrl as DbMeetupUrl;
+use crate::repository::url_link_health::LINK_HEALTH_PROJECTION;
+use crate::repository::url_tags::TAG_PROJECTION;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::engine::any::Any;
+use surrealdb::method::Query;
+use surrealdb::sql::Datetime;
+use surrealdb::{Error, Surreal};
+
+/// Entry of the append-only `url_history` table. The snapshots allow a
+/// revert, the field changes are derived from them when read.
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryRecord {
+    uri_uuid: String,
+    version: i32,
+    operation: HistoryOperation,
+    actor: String,
+    time: Datetime,
+    before: Option<DbMeetupUrl>,
+    after: Option<DbMeetupUrl>,
+    /// Entries written before tags had a history have none
+    tags: Option<TagChange>,
+}
+
+/// A change of one `url` row and who made it
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub operation: HistoryOperation,
+    pub actor: String,
+    pub time: Datetime,
+    pub before: Option<DbMeetupUrl>,
+    pub after: Option<DbMeetupUrl>,
+    /// Only set by `HistoryOperation::Tag`, the rows are kept as they are
+    pub tags: Option<TagChange>,
+}
+
+/// Tag names of the row before and after a change, sorted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagChange {
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+/// A change of a batch, see `write_batch_with_history`
+#[derive(Debug, Clone)]
+pub(super) struct Write {
+    /// Sees the same parameters as in `write_with_history`
+    pub statement: &'static str,
+    pub id: Option<String>,
+    pub uri_uuid: String,
+    pub change: Change,
+}
+
+/// Parameters of a change, named with `suffix`
+const PARAMETERS: [&str; 9] = ["row", "id", "uri_uuid", "operation", "actor", "time", "before", "after", "tags"];
+
+/// Makes the change with `statement` and appends its entry, sets `$changed`
+fn change_statements(statement: &str) -> String {
+    format!(
+        "LET $changed = ({statement}); \
+         LET $version = (math::max((SELECT VALUE version FROM url_history WHERE uri_uuid = $uri_uuid)) ?? 0) + 1; \
+         CREATE url_history CONTENT {{ uri_uuid: $uri_uuid, version: $version, operation: $operation, actor: $actor, time: $time, before: $before, after: $after, tags: $tags }};"
+    )
+}
+
+fn bind_change<'a>(query: Query<'a, Any>, suffix: &str, write: Write) -> Query<'a, Any> {
+    let change = write.change;
+    query
+        .bind((format!("row{suffix}"), change.after.clone()))
+        .bind((format!("id{suffix}"), write.id))
+        .bind((format!("uri_uuid{suffix}"), write.uri_uuid))
+        .bind((format!("operation{suffix}"), change.operation))
+        .bind((format!("actor{suffix}"), change.actor))
+        .bind((format!("time{suffix}"), change.time))
+        .bind((format!("before{suffix}"), change.before))
+        .bind((format!("after{suffix}"), change.after))
+        .bind((format!("tags{suffix}"), change.tags))
+}
+
+/// Makes the change with `statement` and appends its entry in one
+/// transaction, so a failed change leaves no entry and vice versa. The
+/// statement sees `$row`, `$id`, `$uri_uuid` and `$tags`; the result of the
+/// transaction is the changed row with its tags and link health, `None`
+/// once it is gone.
+pub(super) async fn write_with_history<T>(client: &Surreal<Any>, statement: &'static str, id: Option<String>, uri_uuid: &str, change: Change) -> Result<Option<T>, Error>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let query = format!(
+        "BEGIN TRANSACTION; \
+         {} \
+         RETURN (SELECT *{TAG_PROJECTION}{LINK_HEALTH_PROJECTION} FROM ONLY $changed.id); \
+         COMMIT TRANSACTION;",
+        change_statements(statement)
+    );
+
+    let write = Write { statement, id, uri_uuid: uri_uuid.to_string(), change };
+    let changed: Option<T> = bind_change(client.query(query), "", write).await?.take(0)?;
+
+    Ok(changed)
+}
+
+/// `write_with_history` for several changes in one transaction: all are
+/// made or none. Each change sees its own parameters under the usual names.
+pub(super) async fn write_batch_with_history(client: &Surreal<Any>, writes: Vec<Write>) -> Result<(), Error> {
+    let mut query = "BEGIN TRANSACTION; ".to_string();
+    for (i, write) in writes.iter().enumerate() {
+        for parameter in PARAMETERS {
+            query.push_str(&format!("LET ${parameter} = ${parameter}_{i}; "));
+        }
+        query.push_str(&change_statements(write.statement));
+        query.push(' ');
+    }
+    query.push_str("COMMIT TRANSACTION;");
+
+    let mut bound = client.query(query);
+    for (i, write) in writes.into_iter().enumerate() {
+        bound = bind_change(bound, &format!("_{i}"), write);
+    }
+    bound.await?.check()?;
+
+    Ok(())
+}
+
+/// Oldest first
+pub async fn select_history(client: &Surreal<Any>, uri_uuid: String) -> Result<Vec<MeetupUrlHistoryEntry>, Error> {
+    let records: Vec<HistoryRecord> = client
+        .query("SELECT * FROM url_history WHERE uri_uuid = $uri_uuid ORDER BY version")
+        .bind(("uri_uuid", uri_uuid))
+        .await?.take(0)?;
+
+    Ok(records.into_iter().map(|record| MeetupUrlHistoryEntry {
+        changes: changes(record.before.as_ref(), record.after.as_ref())
+            .into_iter()
+            .chain(record.tags.as_ref().and_then(tag_change))
+            .collect(),
+        uri_uuid: record.uri_uuid,
+        version: record.version,
+        operation: record.operation,
+        actor: record.actor,
+        time: record.time.0,
+    }).collect())
+}
+
+/// The row as it was at `version`. For a delete that is the row before it
+/// went to the trash, so a revert to it takes the row out again. Tags are
+/// not part of the row and stay as they are.
+pub async fn select_history_snapshot(client: &Surreal<Any>, uri_uuid: String, version: i32) -> Result<Option<DbMeetupUrl>, Error> {
+    let record: Option<HistoryRecord> = client
+        .query("SELECT * FROM ONLY url_history WHERE uri_uuid = $uri_uuid AND version = $version LIMIT 1")
+        .bind(("uri_uuid", uri_uuid))
+        .bind(("version", version))
+        .await?.take(0)?;
+
+    Ok(record.and_then(|record| match record.operation {
+        HistoryOperation::Delete => record.before,
+        _ => record.after.or(record.before),
+    }))
+}
+
+fn columns(row: Option<&DbMeetupUrl>) -> serde_json::Map<String, Value> {
+    match row.map(serde_json::to_value) {
+        Some(Ok(Value::Object(columns))) => columns,
+        _ => serde_json::Map::new(),
+    }
+}
+
+fn text(value: Option<&Value>) -> Option<String> {
+    match value {
+        None | Some(Value::Null) => None,
+        Some(Value::String(text)) => Some(text.clone()),
+        Some(other) => Some(other.to_string()),
+    }
+}
+
+/// The tags as one field, `None` where the row carried none
+fn tag_change(tags: &TagChange) -> Option<FieldChange> {
+    let names = |names: &[String]| Some(names.join(", ")).filter(|names| !names.is_empty());
+    Some(FieldChange { field: "tags".to_string(), before: names(&tags.before), after: names(&tags.after) })
+        .filter(|change| change.before != change.after)
+}
+
+/// Columns that differ, in alphabetical order. `url_key` follows `url`
+/// and is left out.
+fn changes(before: Option<&DbMeetupUrl>, after: Option<&DbMeetupUrl>) -> Vec<FieldChange> {
+    let (before, after) = (columns(before), columns(after));
+    let mut fields = before.keys().chain(after.keys()).cloned().collect::<Vec<String>>();
+    fields.sort();
+    fields.dedup();
+
+    fields.into_iter()
+        .filter(|field| field != "url_key")
+        .map(|field| FieldChange {
+            before: text(before.get(&field)),
+            after: text(after.get(&field)),
+            field,
+        })
+        .filter(|change| change.before != change.after)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::changes;
+    use crate::graphql::testing::meetup_url;
+    use crate::graphql::FieldChange;
+
+    #[test]
+    fn test_changes() {
+        let before = meetup_url("a", "actix.rs", "Actix Web");
+        let after = crate::model::MeetupUrl { title: "Actix".to_string(), port: Some(8080), ..before.clone() };
+
+        assert_eq!(changes(Some(&before), Some(&after)), vec![
+            FieldChange { field: "port".to_string(), before: None, after: Some("8080".to_string()) },
+            FieldChange { field: "title".to_string(), before: Some("Actix Web".to_string()), after: Some("Actix".to_string()) },
+        ]);
+
+        let deleted = changes(Some(&before), None);
+        assert!(deleted.iter().all(|change| change.after.is_none()));
+        assert!(deleted.iter().any(|change| change.field == "crea_time"
+            && change.before.as_deref() == Some("2024-08-29T15:48:10.384049834Z")));
+    }
+}
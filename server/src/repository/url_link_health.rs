@@ -0,0 +1,90 @@
+use crate::graphql::{LinkHealth, LinkState};
+use serde::{Deserialize, Serialize};
+use surrealdb::engine::any::Any;
+use surrealdb::sql::Datetime;
+use surrealdb::{Error, Surreal};
+
+/// Added to `SELECT *` on `url`: the `link_health` record named by the
+/// `uri_uuid` of the row, `NONE` until the row was checked
+pub(super) const LINK_HEALTH_PROJECTION: &str =
+    ", (SELECT * OMIT id FROM ONLY type::thing('link_health', $parent.uri_uuid)) AS link_health";
+
+/// A `link_health` row. Kept apart from `url`, so checks neither change
+/// `modi_time` nor show up in the history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct HealthRecord {
+    state: LinkState,
+    status: Option<i32>,
+    final_url: String,
+    redirects: i32,
+    latency_ms: i32,
+    error: Option<String>,
+    checked_at: Datetime,
+}
+
+impl From<HealthRecord> for LinkHealth {
+    fn from(record: HealthRecord) -> Self {
+        LinkHealth {
+            state: record.state,
+            status: record.status,
+            final_url: record.final_url,
+            redirects: record.redirects,
+            latency_ms: record.latency_ms,
+            error: record.error,
+            checked_at: record.checked_at.0,
+        }
+    }
+}
+
+impl From<LinkHealth> for HealthRecord {
+    fn from(health: LinkHealth) -> Self {
+        HealthRecord {
+            state: health.state,
+            status: health.status,
+            final_url: health.final_url,
+            redirects: health.redirects,
+            latency_ms: health.latency_ms,
+            error: health.error,
+            checked_at: Datetime::from(health.checked_at),
+        }
+    }
+}
+
+/// A url to check
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LinkTarget {
+    pub uri_uuid: String,
+    pub url: String,
+}
+
+/// Rows outside the trash, the least recently checked first
+pub async fn select_link_targets(client: &Surreal<Any>) -> Result<Vec<LinkTarget>, Error> {
+    let targets: Vec<LinkTarget> = client
+        .query("SELECT uri_uuid, url, type::thing('link_health', uri_uuid).checked_at AS checked_at FROM url \
+                WHERE deleted_at = NONE ORDER BY checked_at, uri_uuid")
+        .await?.take(0)?;
+
+    Ok(targets)
+}
+
+/// Replaces the last result for `uri_uuid` and sets the `live_status` of
+/// the row to the state
+pub async fn store_link_health(client: &Surreal<Any>, uri_uuid: String, health: LinkHealth) -> Result<(), Error> {
+    client
+        .query("UPSERT type::thing('link_health', $uri_uuid) CONTENT $health; \
+                UPDATE url SET live_status = $health.state WHERE uri_uuid = $uri_uuid;")
+        .bind(("uri_uuid", uri_uuid))
+        .bind(("health", HealthRecord::from(health)))
+        .await?.check()?;
+
+    Ok(())
+}
+
+/// Results of rows purged since, returns how many were dropped
+pub async fn delete_orphaned_link_health(client: &Surreal<Any>) -> Result<usize, Error> {
+    let deleted: Vec<HealthRecord> = client
+        .query("DELETE link_health WHERE record::id(id) NOTINSIDE (SELECT VALUE uri_uuid FROM url) RETURN BEFORE")
+        .await?.take(0)?;
+
+    Ok(deleted.len())
+}
@@ -1,215 +1,831 @@
-use crate::graphql::MeetupUrlFilter;
-use crate::graphql::{MeetupUrl as GraphMeetupUrl, MeetupUrl, UpsertMeetupUrl};
-use crate::model::MeetupUrl as DbMeetupUrl;
+use crate::graphql::{HistoryOperation, LinkHealth, MeetupUrlFilter, PageMetadata, Tag};
+use crate::graphql::{MeetupUrl as GraphMeetupUrl, MeetupUrl, MeetupUrlPatch, UpsertMeetupUrl};
+use crate::model::{HtmlMetadata, MeetupUrl as DbMeetupUrl, NormalizedUrl};
+use crate::repository::url_filter::{Bindings, Condition, OrderBy, UrlCursor};
+use crate::repository::url_history::{write_batch_with_history, write_with_history, Change, Write};
+use crate::repository::url_link_health::{HealthRecord, LINK_HEALTH_PROJECTION};
+use crate::repository::url_tags::TAG_PROJECTION;
+use async_graphql::MaybeUndefined;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use surrealdb::engine::remote::ws::Client;
-use surrealdb::sql::{Id, Strand, Thing};
+use surrealdb::engine::any::Any;
+use surrealdb::sql::{Datetime, Strand, Thing, Uuid};
 use surrealdb::{Error, Surreal};
 use tracing::log::{log, Level};
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Record {
+pub(super) struct Record {
     id: Thing,
     uri_uuid: Strand,
     url: Strand,
     scheme: Strand,
     host: Strand,
+    port: Option<u16>,
     path: Strand,
+    query: Option<Strand>,
     live_status: Strand,
     title: Strand,
     auto_descr: Strand,
     man_descr: Strand,
     crea_user: Strand,
-    crea_time: Strand,
+    crea_time: Datetime,
     modi_user: Strand,
-    modi_time: Strand,
+    modi_time: Datetime,
+    deleted_at: Option<Datetime>,
+    deleted_by: Option<Strand>,
+    canonical_url: Option<Strand>,
+    language: Option<Strand>,
+    favicon: Option<Strand>,
+    image: Option<Strand>,
+    site_name: Option<Strand>,
+    metadata_time: Option<Datetime>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    #[serde(default)]
+    link_health: Option<HealthRecord>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct UpdateRecord {
-    url: String,
-    host: String,
-    path: String,
-    title: String,
-    auto_descr: String,
-    man_descr: String,
-    modi_user: String,
-    modi_time: String,
+const CREATE: &str = "CREATE ONLY url CONTENT $row";
+const UPDATE: &str = "UPDATE ONLY type::thing('url', $id) CONTENT $row";
+const DELETE: &str = "DELETE ONLY type::thing('url', $id) RETURN BEFORE";
+/// `UPDATE` unless the row changed since `$before` was read
+const UPDATE_UNCHANGED: &str = "IF type::thing('url', $id).modi_time != $before.modi_time OR type::thing('url', $id).deleted_at != NONE \
+     { THROW 'modified in the meantime' } ELSE { (UPDATE ONLY type::thing('url', $id) CONTENT $row) }";
+const CONFLICT: &str = "modified in the meantime";
+
+/// Result of `update_meetup_url` and `store_metadata`
+#[derive(Debug)]
+pub enum Patched {
+    Updated(Box<MeetupUrl>),
+    /// No record carries the `uri_uuid` or it is in the trash
+    NotFound,
+    /// Modified since the expected `modi_time` or while the patch was applied
+    Conflict,
 }
 
-pub async fn insert_init_meetup_url(url: DbMeetupUrl, client: &Surreal<Client>) -> Result<(), Error> {
-    let _created: Vec<Record> = client
-        .insert("url")
-        .content(url)
-        .await.expect("Error adding Record");
+/// Fails on the unique index when the url is already stored. The history
+/// entry names the creator of the row. Seeds the tests, imports go
+/// through `write_imported`.
+#[cfg(test)]
+pub async fn insert_init_meetup_url(mut url: DbMeetupUrl, client: &Surreal<Any>) -> Result<Option<MeetupUrl>, Error> {
+    let normalized = NormalizedUrl::parse(&url.url).ok();
+    url.url_key = normalized.as_ref().map(NormalizedUrl::key);
+    if url.uri_uuid.trim().is_empty()
+        && let Some(normalized) = &normalized
+    {
+        url.uri_uuid = normalized.uri_uuid();
+    }
+
+    let uri_uuid = url.uri_uuid.clone();
+    let change = Change {
+        operation: HistoryOperation::Create,
+        actor: url.crea_user.clone(),
+        time: url.crea_time.clone(),
+        before: None,
+        after: Some(url),
+        tags: None,
+    };
+    let created: Option<Record> = write_with_history(client, CREATE, None, &uri_uuid, change).await?;
 
-    Ok(())
+    Ok(created.as_ref().map(map_record_to_graph_meetup_url()))
 }
 
-pub async fn count_url(client: &Surreal<Client>, filter: MeetupUrlFilter) -> Result<i32, Error> {
-    let cond = query_builder(filter, false);
+pub async fn count_url(client: &Surreal<Any>, filter: MeetupUrlFilter) -> Result<i32, Error> {
+    let (cond, _, bindings) = query_builder(&filter);
 
-    let query = format!("SELECT count() FROM url WHERE 1 = 1 {} GROUP BY count", cond);
-    log!(Level::Info, "Query: {}", query);
+    let query = format!("SELECT count() FROM url WHERE {} GROUP ALL", cond);
+    log!(Level::Info, "Query: {} {:?}", query, bindings);
 
     let count: Option<i32> = client
-        .query(query).await?.take("count")?;
+        .query(query)
+        .bind(bindings.into_inner())
+        .await?.take("count")?;
+
+    Ok(group_all_count(count))
+}
+
+/// `SELECT count() ... GROUP ALL` answers no row at all when nothing
+/// matches, not a count of 0
+pub(super) fn group_all_count(count: Option<i32>) -> i32 {
+    count.unwrap_or(0)
+}
+
+/// Moves the row to the trash, marked with `user` and the time. `None`
+/// when no record carries `uri_uuid` or it is in the trash already.
+pub async fn delete_by_uri_uuid(client: &Surreal<Any>, uuid_id: String, user: &str) -> Result<Option<MeetupUrl>, Error> {
+    let now = Datetime::from(Utc::now());
+
+    let Some((id, before)) = find_by_uri_uuid(client, uuid_id.clone()).await? else {
+        return Ok(None);
+    };
+    if before.deleted_at.is_some() {
+        return Ok(None);
+    }
+
+    let after = DbMeetupUrl {
+        deleted_at: Some(now.clone()),
+        deleted_by: Some(user.to_string()),
+        ..before.clone()
+    };
+    let change = Change {
+        operation: HistoryOperation::Delete,
+        actor: user.to_string(),
+        time: now,
+        before: Some(before),
+        after: Some(after),
+        tags: None,
+    };
+    let deleted: Option<Record> = write_with_history(client, UPDATE, Some(id), &uuid_id, change).await?;
 
-    Ok(count.unwrap_or(0))
+    Ok(deleted.as_ref().map(map_record_to_graph_meetup_url()))
 }
 
-pub async fn delete_by_uri_uuid(client: &Surreal<Client>, uuid_id: String) -> Result<(), Error> {
-    let id = find_by_uri_uuid(client, uuid_id).await?;
+/// Takes the row out of the trash. `None` when no record carries
+/// `uri_uuid` or it is not in the trash.
+pub async fn restore_meetup_url(client: &Surreal<Any>, uuid_id: String, user: &str) -> Result<Option<MeetupUrl>, Error> {
+    let Some((id, before)) = find_by_uri_uuid(client, uuid_id.clone()).await? else {
+        return Ok(None);
+    };
+    if before.deleted_at.is_none() {
+        return Ok(None);
+    }
+
+    let after = DbMeetupUrl { deleted_at: None, deleted_by: None, ..before.clone() };
+    let change = Change {
+        operation: HistoryOperation::Restore,
+        actor: user.to_string(),
+        time: Datetime::from(Utc::now()),
+        before: Some(before),
+        after: Some(after),
+        tags: None,
+    };
+    let restored: Option<Record> = write_with_history(client, UPDATE, Some(id), &uuid_id, change).await?;
+
+    Ok(restored.as_ref().map(map_record_to_graph_meetup_url()))
+}
 
-    let _result: Option<Record> = client
-        .delete(("url", id.to_string()))
+/// Removes the rows put in the trash before `deleted_before` for good. Their
+/// history stays, a revert brings them back. Returns the purged `uri_uuid`s.
+pub async fn purge_deleted(client: &Surreal<Any>, deleted_before: Datetime, actor: &str) -> Result<Vec<String>, Error> {
+    let mut response = client
+        .query("SELECT * FROM url WHERE deleted_at != NONE AND deleted_at < $deleted_before ORDER BY deleted_at")
+        .bind(("deleted_before", deleted_before))
         .await?;
 
-    Ok(())
+    let ids: Vec<Thing> = response.take((0, "id"))?;
+    let rows: Vec<DbMeetupUrl> = response.take(0)?;
+
+    let mut purged = Vec::new();
+    for (id, row) in ids.into_iter().zip(rows) {
+        let uri_uuid = row.uri_uuid.clone();
+        let change = Change {
+            operation: HistoryOperation::Purge,
+            actor: actor.to_string(),
+            time: Datetime::from(Utc::now()),
+            before: Some(row),
+            after: None,
+            tags: None,
+        };
+        let _: Option<Record> = write_with_history(client, DELETE, Some(id.id.to_raw()), &uri_uuid, change).await?;
+        purged.push(uri_uuid);
+    }
+    Ok(purged)
 }
 
-pub async fn insert_meetup_url(client: &Surreal<Client>, data: UpsertMeetupUrl) -> Result<MeetupUrl, Error> {
-    let now = Utc::now().to_string();
+/// `user` is recorded as creator and last modifier. The row gets the id
+/// its url hashes to, or a fresh UUIDv7 when a row created at that url and
+/// moved to another one still holds it.
+pub async fn insert_meetup_url(client: &Surreal<Any>, data: UpsertMeetupUrl, url: NormalizedUrl, user: &str) -> Result<Option<MeetupUrl>, Error> {
+    let now = Datetime::from(Utc::now());
 
+    let uri_uuid = match find_by_uri_uuid(client, url.uri_uuid()).await? {
+        Some(_) => Uuid::new_v7().to_raw(),
+        None => url.uri_uuid(),
+    };
+    let key = url.key();
     let meetup_url = DbMeetupUrl {
-        uri_uuid: format!("UUID_{}", now),
-        url: data.url.clone(),
-        scheme: "SCHEMA".to_string(),
-        host: data.host.clone(),
-        path: "PATH".to_string(),
+        uri_uuid,
+        url: url.url,
+        scheme: url.scheme,
+        host: url.host,
+        port: url.port,
+        path: url.path,
+        query: url.query,
         live_status: "OK".to_string(),
         title: data.title.clone(),
         auto_descr: data.auto_descr.clone(),
-        man_descr: data.auto_descr.clone(),
-        crea_user: "API".to_string(),
+        man_descr: data.man_descr.clone().unwrap_or_default(),
+        crea_user: user.to_string(),
         crea_time: now.clone(),
-        modi_user: "API".to_string(),
+        modi_user: user.to_string(),
+        modi_time: now,
+        url_key: Some(key),
+        deleted_at: None,
+        deleted_by: None,
+        canonical_url: None,
+        language: None,
+        favicon: None,
+        image: None,
+        site_name: None,
+        metadata_time: None,
+    };
+
+    let uri_uuid = meetup_url.uri_uuid.clone();
+    let change = Change {
+        operation: HistoryOperation::Create,
+        actor: user.to_string(),
+        time: meetup_url.crea_time.clone(),
+        before: None,
+        after: Some(meetup_url),
+        tags: None,
+    };
+    let created: Option<Record> = write_with_history(client, CREATE, None, &uri_uuid, change).await?;
+
+    Ok(created.as_ref().map(map_record_to_graph_meetup_url()))
+}
+
+/// Applies `patch`, with `url` normalized from it. `user` is recorded as
+/// last modifier.
+pub async fn update_meetup_url(client: &Surreal<Any>, patch: MeetupUrlPatch, url: Option<NormalizedUrl>, user: &str) -> Result<Patched, Error> {
+    let now = Datetime::from(Utc::now());
+    let uri_uuid = patch.uri_uuid;
+
+    let Some((id, before)) = find_by_uri_uuid(client, uri_uuid.clone()).await?
+        .filter(|(_, before)| before.deleted_at.is_none()) else {
+        return Ok(Patched::NotFound);
+    };
+    if patch.expected_modi_time.is_some_and(|expected| expected != before.modi_time.0) {
+        return Ok(Patched::Conflict);
+    }
+
+    let mut after = DbMeetupUrl {
+        modi_user: user.to_string(),
         modi_time: now.clone(),
+        ..before.clone()
+    };
+    if let Some(url) = url {
+        after.url_key = Some(url.key());
+        after.url = url.url;
+        after.scheme = url.scheme;
+        after.host = url.host;
+        after.port = url.port;
+        after.path = url.path;
+        after.query = url.query;
+    }
+    apply(&mut after.title, patch.title);
+    apply(&mut after.auto_descr, patch.auto_descr);
+    apply(&mut after.man_descr, patch.man_descr);
+
+    log!(Level::Info, "Update Record {:?} [{:?}]", id, after);
+    let change = Change {
+        operation: HistoryOperation::Update,
+        actor: user.to_string(),
+        time: now,
+        before: Some(before),
+        after: Some(after),
+        tags: None,
     };
+    update_unchanged(client, id, &uri_uuid, change).await
+}
 
-    let created: Vec<Record> = client
-        .insert("url")
-        .content(meetup_url)
-        .await.expect("Error adding Record");
+/// Url and `modi_time` of the row, `None` when no record carries
+/// `uri_uuid` or it is in the trash
+pub async fn find_live_url(client: &Surreal<Any>, uri_uuid: String) -> Result<Option<(String, Datetime)>, Error> {
+    Ok(find_by_uri_uuid(client, uri_uuid).await?
+        .filter(|(_, row)| row.deleted_at.is_none())
+        .map(|(_, row)| (row.url, row.modi_time)))
+}
 
-    let rtn = created.get(0)
-        .map(map_record_to_graph_meetup_url())
-        .unwrap();
+/// Takes over what was read from the page. A title or description found
+/// replaces `title` or `auto_descr`, `man_descr` stays as it is. Conflicts
+/// unless the row still has the `modi_time` of when the page was requested.
+pub async fn store_metadata(client: &Surreal<Any>, uri_uuid: String, modi_time: Datetime, metadata: HtmlMetadata, user: &str) -> Result<Patched, Error> {
+    let now = Datetime::from(Utc::now());
+
+    let Some((id, before)) = find_by_uri_uuid(client, uri_uuid.clone()).await?
+        .filter(|(_, before)| before.deleted_at.is_none()) else {
+        return Ok(Patched::NotFound);
+    };
+    if before.modi_time != modi_time {
+        return Ok(Patched::Conflict);
+    }
 
-    Ok(rtn)
+    let after = DbMeetupUrl {
+        title: metadata.title.unwrap_or(before.title.clone()),
+        auto_descr: metadata.description.unwrap_or(before.auto_descr.clone()),
+        canonical_url: metadata.canonical_url,
+        language: metadata.language,
+        favicon: metadata.favicon,
+        image: metadata.image,
+        site_name: metadata.site_name,
+        metadata_time: Some(now.clone()),
+        modi_user: user.to_string(),
+        modi_time: now.clone(),
+        ..before.clone()
+    };
+    let change = Change {
+        operation: HistoryOperation::Update,
+        actor: user.to_string(),
+        time: now,
+        before: Some(before),
+        after: Some(after),
+        tags: None,
+    };
+    update_unchanged(client, id, &uri_uuid, change).await
 }
 
-pub async fn update_meetup_url(client: &Surreal<Client>, data: UpsertMeetupUrl) -> Result<MeetupUrl, Error> {
-    let now = Utc::now().to_string();
+/// Writes `change` unless the row was changed since its `before` was read
+async fn update_unchanged(client: &Surreal<Any>, id: String, uri_uuid: &str, change: Change) -> Result<Patched, Error> {
+    let updated: Option<Record> = match write_with_history(client, UPDATE_UNCHANGED, Some(id), uri_uuid, change).await {
+        Ok(updated) => updated,
+        Err(e) if e.to_string().contains(CONFLICT) => return Ok(Patched::Conflict),
+        Err(e) => return Err(e),
+    };
 
-    let query = format!("SELECT * FROM url WHERE uri_uuid = '{}'", data.uri_uuid.clone().unwrap());
+    Ok(updated.as_ref().map(map_record_to_graph_meetup_url()).map_or(Patched::NotFound, |updated| Patched::Updated(Box::new(updated))))
+}
 
-    let records: Vec<Record> = client.query(query).await?.take(0)?;
+/// A `null` clears the text, an omitted value keeps it
+fn apply(text: &mut String, patch: MaybeUndefined<String>) {
+    match patch {
+        MaybeUndefined::Undefined => {}
+        MaybeUndefined::Null => text.clear(),
+        MaybeUndefined::Value(value) => *text = value,
+    }
+}
 
-    let record = records.first().unwrap();
+/// Stores `snapshot`, an earlier version from the history, as the current
+/// row. A purged row is created again, the flag tells which happened.
+pub async fn revert_meetup_url(client: &Surreal<Any>, mut snapshot: DbMeetupUrl, user: &str) -> Result<(Option<MeetupUrl>, bool), Error> {
+    let now = Datetime::from(Utc::now());
+    let uri_uuid = snapshot.uri_uuid.clone();
+    let current = find_by_uri_uuid(client, uri_uuid.clone()).await?;
 
-    let id = record.id.id.to_raw();
+    snapshot.modi_user = user.to_string();
+    snapshot.modi_time = now.clone();
 
-    let update = UpdateRecord {
-        url: data.url.clone(),
-        host: data.host.clone(),
-        path: data.url.clone(),
-        title: data.title.clone(),
-        auto_descr: data.auto_descr.clone(),
-        man_descr: data.auto_descr.clone(),
-        modi_user: "API_UPDATE".to_string(),
-        modi_time: now,
+    let (statement, id, before) = match current {
+        Some((id, before)) => (UPDATE, Some(id), Some(before)),
+        None => (CREATE, None, None),
+    };
+    let recreated = id.is_none();
+
+    let change = Change {
+        operation: HistoryOperation::Revert,
+        actor: user.to_string(),
+        time: now,
+        before,
+        after: Some(snapshot),
+        tags: None,
     };
+    let reverted: Option<Record> = write_with_history(client, statement, id, &uri_uuid, change).await?;
 
-    println!("Update Record {:?} [{:?}]", id, update);
-    let updated: Option<Record> = client
-        .update(("url", id))
-        .merge(update)
-        .await
-        .map_err(|e| {
-            eprintln!("Error Updating Record: {:?}", e);
-            e
-        })?;
+    Ok((reverted.as_ref().map(map_record_to_graph_meetup_url()), recreated))
+}
 
-    let rtn = updated
-        .as_ref()
-        .map(map_record_to_graph_meetup_url())
-        .unwrap();
+/// Record id and row of `uuid_id`
+pub(super) async fn find_by_uri_uuid(client: &Surreal<Any>, uuid_id: String) -> Result<Option<(String, DbMeetupUrl)>, Error> {
+    find_row(client, "uri_uuid", uuid_id).await
+}
 
-    Ok(rtn)
+/// Record id and row whose `field` holds `value`, `field` names a unique index
+async fn find_row(client: &Surreal<Any>, field: &'static str, value: String) -> Result<Option<(String, DbMeetupUrl)>, Error> {
+    let mut response = client
+        .query(format!("SELECT * FROM ONLY url WHERE {} = $value LIMIT 1", field))
+        .bind(("value", value))
+        .await?;
+
+    let id: Option<Thing> = response.take((0, "id"))?;
+    let row: Option<DbMeetupUrl> = response.take(0)?;
+
+    Ok(id.zip(row).map(|(id, row)| (id.id.to_raw(), row)))
 }
 
-pub async fn find_by_uri_uuid(client: &Surreal<Client>, uuid_id: String) -> Result<Id, Error> {
-    let query = format!("SELECT * FROM url WHERE uri_uuid = '{}'", uuid_id);
+/// A row of an import, see `write_imported`
+#[derive(Debug, Clone)]
+pub enum ImportWrite {
+    Insert(Box<DbMeetupUrl>),
+    /// `id` is the record of the stored row `before`
+    Update { id: String, before: Box<DbMeetupUrl>, after: Box<DbMeetupUrl> },
+}
+
+/// Record id and stored row an imported row refers to: the one with its
+/// `uri_uuid`, else the one with its `url_key`
+pub async fn find_import_target(client: &Surreal<Any>, uri_uuid: String, url_key: Option<String>) -> Result<Option<(String, DbMeetupUrl)>, Error> {
+    if let Some(found) = find_by_uri_uuid(client, uri_uuid).await? {
+        return Ok(Some(found));
+    }
+    match url_key {
+        Some(url_key) => find_row(client, "url_key", url_key).await,
+        None => Ok(None),
+    }
+}
 
-    let records: Vec<Record> = client.query(query).await?.take(0)?;
+/// Writes the rows with their history entries in one transaction, all or
+/// none. Inserts name the creator of the row, updates `actor`. Returns the
+/// written rows.
+pub async fn write_imported(client: &Surreal<Any>, writes: Vec<ImportWrite>, actor: &str) -> Result<Vec<MeetupUrl>, Error> {
+    let now = Datetime::from(Utc::now());
+
+    let writes = writes.into_iter()
+        .map(|write| match write {
+            ImportWrite::Insert(row) => Write {
+                statement: CREATE,
+                id: None,
+                uri_uuid: row.uri_uuid.clone(),
+                change: Change {
+                    operation: HistoryOperation::Create,
+                    actor: row.crea_user.clone(),
+                    time: row.crea_time.clone(),
+                    before: None,
+                    after: Some(*row),
+                    tags: None,
+                },
+            },
+            ImportWrite::Update { id, before, after } => Write {
+                statement: UPDATE_UNCHANGED,
+                id: Some(id),
+                uri_uuid: before.uri_uuid.clone(),
+                change: Change {
+                    operation: HistoryOperation::Update,
+                    actor: actor.to_string(),
+                    time: now.clone(),
+                    before: Some(*before),
+                    after: Some(*after),
+                    tags: None,
+                },
+            },
+        })
+        .collect::<Vec<Write>>();
+    let uri_uuids = writes.iter().map(|write| write.uri_uuid.clone()).collect::<Vec<String>>();
+
+    write_batch_with_history(client, writes).await?;
+
+    let records: Vec<Record> = client
+        .query(format!("SELECT *{}{} FROM url WHERE uri_uuid IN $uri_uuids", TAG_PROJECTION, LINK_HEALTH_PROJECTION))
+        .bind(("uri_uuids", uri_uuids))
+        .await?.take(0)?;
+
+    Ok(records.iter().map(map_record_to_graph_meetup_url()).collect())
+}
 
-    let record = records.first().unwrap();
+/// `uri_uuid` of the row holding `url_key`
+pub async fn find_by_url_key(client: &Surreal<Any>, url_key: String) -> Result<Option<String>, Error> {
+    let uri_uuid: Option<String> = client
+        .query("SELECT VALUE uri_uuid FROM url WHERE url_key = $url_key LIMIT 1")
+        .bind(("url_key", url_key))
+        .await?.take(0)?;
 
-    Ok(record.id.id.clone())
+    Ok(uri_uuid)
 }
 
-pub async fn select_url(client: &Surreal<Client>, filter: MeetupUrlFilter) -> Result<Vec<GraphMeetupUrl>, Error> {
-    let cond = query_builder(filter, true);
+/// Rows of the requested page and the number of rows matching the filter,
+/// both in one round trip
+pub async fn select_url_with_count(client: &Surreal<Any>, filter: MeetupUrlFilter) -> Result<(Vec<GraphMeetupUrl>, i32), Error> {
+    let (cond, select, bindings) = query_builder(&filter);
 
-    let query = format!("SELECT * FROM url WHERE 1 = 1 {}", cond);
-    log!(Level::Info, "Query: {}", query);
+    let query = format!("SELECT count() FROM url WHERE {} GROUP ALL; {}", cond, select);
+    log!(Level::Info, "Query: {} {:?}", query, bindings);
 
-    let records: Vec<Record> = client.query(query).await?.take(0)?;
+    let mut response = client
+        .query(query)
+        .bind(bindings.into_inner())
+        .await?;
 
+    let count: Option<i32> = response.take((0, "count"))?;
+    let records: Vec<Record> = response.take(1)?;
 
     let urls = records.iter()
         .map(map_record_to_graph_meetup_url())
         .collect::<Vec<GraphMeetupUrl>>();
 
-    Ok(urls)
+    Ok((urls, group_all_count(count)))
+}
+
+/// Bounds of a keyset page. Cursors must come from the same sort order.
+#[derive(Debug, Clone)]
+pub struct Seek {
+    pub after: Option<UrlCursor>,
+    pub before: Option<UrlCursor>,
+    pub limit: usize,
+    /// Take the `limit` rows closest to `before` instead of those closest to `after`
+    pub backward: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrlSlice {
+    /// In list order, also when read backward
+    pub rows: Vec<(GraphMeetupUrl, UrlCursor)>,
+    /// More rows lie beyond the slice in the reading direction
+    pub has_more: bool,
+    /// Rows matching the filter, ignoring the cursors
+    pub total: i32,
 }
 
-fn map_record_to_graph_meetup_url() -> fn(&Record) -> MeetupUrl {
+/// Keyset pagination: seeks past the cursors instead of skipping an offset,
+/// so concurrent edits do not shift the following pages
+pub async fn select_url_slice(client: &Surreal<Any>, filter: MeetupUrlFilter, seek: Seek) -> Result<UrlSlice, Error> {
+    let mut bindings = Bindings::default();
+    let cond = Condition::from(&filter).to_surql(&mut bindings);
+    let order = OrderBy::from(filter.sort.as_deref().unwrap_or_default());
+
+    let mut bounds = vec![format!("({})", cond)];
+    if let Some(after) = &seek.after {
+        bounds.push(format!("({})", order.seek(after, false, &mut bindings)));
+    }
+    if let Some(before) = &seek.before {
+        bounds.push(format!("({})", order.seek(before, true, &mut bindings)));
+    }
+    let limit = bindings.push((seek.limit + 1).into());
+
+    let query = format!(
+        "SELECT count() FROM url WHERE {} GROUP ALL; SELECT *{}{}{} FROM url WHERE {} ORDER BY {} LIMIT {}",
+        cond,
+        order.projection(),
+        TAG_PROJECTION,
+        LINK_HEALTH_PROJECTION,
+        bounds.join(" AND "),
+        order.to_surql(seek.backward),
+        limit
+    );
+    log!(Level::Info, "Query: {} {:?}", query, bindings);
+
+    let mut response = client
+        .query(query)
+        .bind(bindings.into_inner())
+        .await?;
+
+    let count: Option<i32> = response.take((0, "count"))?;
+    let mut records: Vec<Record> = response.take(1)?;
+
+    let has_more = records.len() > seek.limit;
+    records.truncate(seek.limit);
+    if seek.backward {
+        records.reverse();
+    }
+
+    let rows = records.iter()
+        .map(|record| {
+            let url = map_record_to_graph_meetup_url()(record);
+            let cursor = order.cursor(&url, record.id.id.to_raw());
+            (url, cursor)
+        })
+        .collect();
+
+    Ok(UrlSlice { rows, has_more, total: group_all_count(count) })
+}
+
+pub(super) fn map_record_to_graph_meetup_url() -> fn(&Record) -> MeetupUrl {
     |x| GraphMeetupUrl {
         uri_uuid: x.uri_uuid.clone().as_string(),
         url: x.url.clone().as_string(),
         scheme: x.scheme.clone().as_string(),
         host: x.host.clone().as_string(),
+        port: x.port.map(i32::from),
         path: x.path.clone().as_string(),
+        query: x.query.clone().map(Strand::as_string),
         live_status: x.live_status.clone().as_string(),
         title: x.title.clone().as_string(),
         auto_descr: x.auto_descr.clone().as_string(),
         man_descr: x.man_descr.clone().as_string(),
         crea_user: x.crea_user.clone().as_string(),
-        crea_time: x.crea_time.clone().as_string(),
+        crea_time: x.crea_time.0,
         modi_user: x.modi_user.clone().as_string(),
-        modi_time: x.modi_time.clone().as_string(),
+        modi_time: x.modi_time.0,
+        deleted_at: x.deleted_at.clone().map(|t| t.0),
+        deleted_by: x.deleted_by.clone().map(Strand::as_string),
+        tags: x.tags.clone(),
+        link_health: x.link_health.clone().map(LinkHealth::from),
+        metadata: x.metadata_time.clone().map(|fetched_at| PageMetadata {
+            canonical_url: x.canonical_url.clone().map(Strand::as_string),
+            language: x.language.clone().map(Strand::as_string),
+            favicon: x.favicon.clone().map(Strand::as_string),
+            image: x.image.clone().map(Strand::as_string),
+            site_name: x.site_name.clone().map(Strand::as_string),
+            fetched_at: fetched_at.0,
+        }),
     }
 }
 
-fn query_builder(filter: MeetupUrlFilter, pagination: bool) -> String {
-    let mut cond = "".to_string();
-
-    if let Some(description) = filter.description {
-        cond = format!(" {} AND string::matches(auto_descr,'{}') ", cond, description)
+/// WHERE condition, and the select of the requested page built on it.
+/// Both share the bindings.
+fn query_builder(filter: &MeetupUrlFilter) -> (String, String, Bindings) {
+    let mut bindings = Bindings::default();
+    let cond = Condition::from(filter).to_surql(&mut bindings);
+
+    let order = OrderBy::from(filter.sort.as_deref().unwrap_or_default());
+    let mut select = format!(
+        "SELECT *{}{}{} FROM url WHERE {} ORDER BY {}",
+        order.projection(),
+        TAG_PROJECTION,
+        LINK_HEALTH_PROJECTION,
+        cond,
+        order.to_surql(false)
+    );
+
+    if let Some(page) = &filter.pagination
+        && let Some(current) = page.current
+        && let Some(size) = page.size
+    {
+        let limit = bindings.push(size.into());
+        // Pages past the last are empty, however far
+        let start = bindings.push(current.saturating_mul(size).into());
+        select = format!("{} LIMIT {} START {}", select, limit, start);
     }
 
-    if let Some(domain) = filter.domain {
-        cond = format!(" {} AND string::matches(host,'{}') ", cond, domain)
+    (cond, select, bindings)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::connect_test_db;
+    use crate::graphql::testing::{meetup_url, time};
+    use crate::graphql::{MeetupUrlFilter, MeetupUrlOrder, MeetupUrlSort, Pagination, SortDirection};
+    use crate::repository::url_repository::{count_url, insert_init_meetup_url, select_url_with_count};
+    use surrealdb::engine::any::Any;
+    use surrealdb::{Error, Surreal};
+
+    const HOSTILE: [&str; 5] = [
+        "'); DELETE url; --",
+        "') OR true OR string::matches(title, '",
+        "x'); REMOVE TABLE url; --",
+        "$p0",
+        "\\'; UPDATE url SET title = 'pwned'; --",
+    ];
+
+    fn filter() -> MeetupUrlFilter {
+        MeetupUrlFilter::default()
     }
 
-    if let Some(url) = filter.url {
-        cond = format!(" {} AND string::matches(url,'{}') ", cond, url)
+    async fn select_url(client: &Surreal<Any>, filter: MeetupUrlFilter) -> Result<Vec<crate::graphql::MeetupUrl>, Error> {
+        select_url_with_count(client, filter).await.map(|(urls, _)| urls)
     }
 
-    if let Some(title) = filter.title {
-        cond = format!(" {} AND string::matches(title,'{}') ", cond, title)
+    async fn seeded_db() -> Surreal<Any> {
+        let client = connect_test_db().await;
+        insert_init_meetup_url(meetup_url("a", "actix.rs", "Actix Web"), &client).await.unwrap();
+        insert_init_meetup_url(meetup_url("b", "leptos.dev", "Leptos"), &client).await.unwrap();
+        insert_init_meetup_url(meetup_url("c", "rust-lang.org", "Rust's Book"), &client).await.unwrap();
+        client
     }
 
-    if pagination {
-        if let Some(page) = filter.pagination {
-            if let Some(current) = page.current && let Some(size) = page.size {
-                cond = format!(" {} START AT {}", cond, current * size);
-                cond = format!(" {} LIMIT BY {}", cond, size);
+    #[tokio::test]
+    async fn test_hostile_filters_are_plain_data() {
+        let client = seeded_db().await;
+
+        // Escaped they are valid patterns that match nothing
+        for hostile in HOSTILE.map(regex::escape) {
+            for f in [
+                MeetupUrlFilter { title: Some(hostile.clone()), ..filter() },
+                MeetupUrlFilter { domain: Some(hostile.clone()), ..filter() },
+                MeetupUrlFilter { url: Some(hostile.clone()), ..filter() },
+                MeetupUrlFilter { description: Some(hostile.clone()), ..filter() },
+            ] {
+                let urls = select_url(&client, f.clone()).await.unwrap();
+                assert!(urls.is_empty(), "{} matched {:?}", hostile, urls);
+
+                let count = count_url(&client, f).await.unwrap();
+                assert_eq!(count, 0, "{} counted rows", hostile);
             }
         }
+
+        // As they are, broken patterns fail and nothing else runs
+        for hostile in HOSTILE {
+            let f = MeetupUrlFilter { title: Some(hostile.to_string()), ..filter() };
+            match select_url(&client, f).await {
+                Ok(urls) => assert!(urls.is_empty(), "{} matched {:?}", hostile, urls),
+                Err(e) => assert!(regex::Regex::new(hostile).is_err(), "{} failed: {}", hostile, e),
+            }
+        }
+
+        let urls = select_url(&client, filter()).await.unwrap();
+        assert_eq!(urls.len(), 3);
+        assert!(urls.iter().all(|u| u.title != "pwned"));
+        assert_eq!(count_url(&client, filter()).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_quotes_match_literally() {
+        let client = seeded_db().await;
+
+        let f = MeetupUrlFilter { title: Some("Rust's".to_string()), ..filter() };
+        let urls = select_url(&client, f.clone()).await.unwrap();
+
+        assert_eq!(urls.len(), 1);
+        assert_eq!(urls[0].uri_uuid, "c");
+        assert_eq!(count_url(&client, f).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_count_without_matches() {
+        let client = seeded_db().await;
+
+        let rows: Vec<serde_json::Value> = client.query("SELECT count() FROM url WHERE title = 'nothing' GROUP ALL").await.unwrap().take(0).unwrap();
+        assert!(rows.is_empty());
+
+        let f = MeetupUrlFilter { title: Some("nothing".to_string()), ..filter() };
+        assert_eq!(count_url(&client, f.clone()).await.unwrap(), 0);
+        assert_eq!(select_url_with_count(&client, f).await.unwrap().1, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let client = seeded_db().await;
+
+        let f = MeetupUrlFilter {
+            pagination: Some(Pagination { current: Some(1), size: Some(2) }),
+            ..filter()
+        };
+
+        assert_eq!(select_url(&client, f.clone()).await.unwrap().len(), 1);
+        assert_eq!(count_url(&client, f.clone()).await.unwrap(), 3);
+
+        let (urls, total) = select_url_with_count(&client, f).await.unwrap();
+        assert_eq!(urls.len(), 1);
+        assert_eq!(total, 3);
+
+        let far = MeetupUrlFilter {
+            pagination: Some(Pagination { current: Some(i32::MAX), size: Some(i32::MAX) }),
+            ..filter()
+        };
+        assert!(select_url(&client, far).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sort() {
+        let client = seeded_db().await;
+        let curated = crate::model::MeetupUrl { man_descr: "A book about Leptos".to_string(), ..meetup_url("d", "leptos.dev", "leptos book") };
+        insert_init_meetup_url(curated, &client).await.unwrap();
+
+        let order = |field, direction| MeetupUrlOrder { field, direction };
+        let uuids = |urls: Vec<crate::graphql::MeetupUrl>| urls.into_iter().map(|u| u.uri_uuid).collect::<Vec<String>>();
+
+        let f = MeetupUrlFilter { sort: Some(vec![order(MeetupUrlSort::Title, SortDirection::Desc)]), ..filter() };
+        assert_eq!(uuids(select_url(&client, f).await.unwrap()), vec!["c", "d", "b", "a"]);
+
+        let f = MeetupUrlFilter {
+            sort: Some(vec![
+                order(MeetupUrlSort::Domain, SortDirection::Asc),
+                order(MeetupUrlSort::Title, SortDirection::Asc),
+            ]),
+            ..filter()
+        };
+        assert_eq!(uuids(select_url(&client, f).await.unwrap()), vec!["a", "b", "d", "c"]);
+
+        // By the description shown, the curator's one where set
+        let f = MeetupUrlFilter { sort: Some(vec![order(MeetupUrlSort::Description, SortDirection::Asc)]), ..filter() };
+        assert_eq!(uuids(select_url(&client, f).await.unwrap()), vec!["d", "a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_sort_ties_are_stable_across_pages() {
+        let client = seeded_db().await;
+        insert_init_meetup_url(meetup_url("d", "leptos.dev", "Leptos"), &client).await.unwrap();
+
+        let mut seen = Vec::new();
+        for current in 0..4 {
+            let f = MeetupUrlFilter {
+                sort: Some(vec![MeetupUrlOrder { field: MeetupUrlSort::Domain, direction: SortDirection::Desc }]),
+                pagination: Some(Pagination { current: Some(current), size: Some(1) }),
+                ..filter()
+            };
+            seen.extend(select_url(&client, f).await.unwrap().into_iter().map(|u| u.uri_uuid));
+        }
+
+        assert_eq!(seen[0], "c");
+        assert_eq!(seen[3], "a");
+        seen.sort();
+        assert_eq!(seen, vec!["a", "b", "c", "d"]);
     }
-    cond
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_filter_and_sort_by_time() {
+        let client = connect_test_db().await;
+        for (uri_uuid, created, modified) in [
+            ("a", "2024-01-01T10:00:00Z", "2024-06-01T10:00:00Z"),
+            ("b", "2024-03-01T10:00:00.5+02:00", "2024-03-01T10:00:00Z"),
+            ("c", "2024-03-01T10:00:00Z", "2024-04-01T10:00:00Z"),
+        ] {
+            let mut row = meetup_url(uri_uuid, "leptos.dev", "Leptos");
+            row.crea_time = time(created);
+            row.modi_time = time(modified);
+            insert_init_meetup_url(row, &client).await.unwrap();
+        }
+        let uuids = |urls: Vec<crate::graphql::MeetupUrl>| urls.into_iter().map(|u| u.uri_uuid).collect::<Vec<String>>();
+        let at = |rfc3339| time(rfc3339).0;
+
+        let f = MeetupUrlFilter {
+            created_after: Some(at("2024-03-01T08:00:00.5Z")),
+            modified_before: Some(at("2024-05-01T00:00:00Z")),
+            sort: Some(vec![MeetupUrlOrder { field: MeetupUrlSort::Created, direction: SortDirection::Desc }]),
+            ..filter()
+        };
+        assert_eq!(uuids(select_url(&client, f).await.unwrap()), vec!["c", "b"]);
+
+        let f = MeetupUrlFilter {
+            sort: Some(vec![MeetupUrlOrder { field: MeetupUrlSort::Modified, direction: SortDirection::Asc }]),
+            ..filter()
+        };
+        assert_eq!(uuids(select_url(&client, f).await.unwrap()), vec!["b", "c", "a"]);
+    }
+}
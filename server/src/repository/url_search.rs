@@ -0,0 +1,154 @@
+use crate::graphql::{MeetupUrlFilter, SearchField, SearchHighlight, SearchHit, TextFragment};
+use crate::repository::url_filter::{Bindings, Condition};
+use crate::repository::url_repository::{group_all_count, map_record_to_graph_meetup_url, Record};
+use crate::repository::url_link_health::LINK_HEALTH_PROJECTION;
+use crate::repository::url_tags::TAG_PROJECTION;
+use serde_json::Value;
+use surrealdb::engine::any::Any;
+use surrealdb::{Error, Surreal};
+use tracing::log::{log, Level};
+
+/// Columns joined into `search_text`, in order, see `migration.rs`
+const SEARCH_FIELDS: [SearchField; 5] = [
+    SearchField::Title,
+    SearchField::AutoDescr,
+    SearchField::ManDescr,
+    SearchField::Host,
+    SearchField::Url,
+];
+const FIELD_SEPARATOR: &str = " \u{1f} ";
+
+/// Control characters around matched terms, never part of stored text
+const MATCH_OPEN: &str = "\u{2}";
+const MATCH_CLOSE: &str = "\u{3}";
+
+/// Unmatched characters kept around the matches of a long column
+const SNIPPET_CONTEXT: usize = 60;
+
+/// Best matches first, with the number of all matches. All terms of `query`
+/// must occur, each in any column; `filter` narrows the matches further.
+pub async fn search_url(client: &Surreal<Any>, query: &str, filter: &MeetupUrlFilter, current: i32, size: i32) -> Result<(Vec<SearchHit>, i32), Error> {
+    let mut bindings = Bindings::default();
+    let cond = Condition::from(filter).to_surql(&mut bindings);
+    let terms = bindings.push(Value::String(query.to_string()));
+    let open = bindings.push(Value::String(MATCH_OPEN.to_string()));
+    let close = bindings.push(Value::String(MATCH_CLOSE.to_string()));
+    let limit = bindings.push(size.into());
+    // Pages past the last are empty, however far
+    let start = bindings.push(current.saturating_mul(size).into());
+
+    let query = format!(
+        "SELECT count() FROM url WHERE search_text @@ {terms} AND ({cond}) GROUP ALL; \
+         SELECT *{TAG_PROJECTION}{LINK_HEALTH_PROJECTION}, search::score(0) AS score, search::highlight({open}, {close}, 0) AS highlight \
+         FROM url WHERE search_text @0@ {terms} AND ({cond}) \
+         ORDER BY score DESC, id ASC LIMIT {limit} START {start}"
+    );
+    log!(Level::Info, "Query: {} {:?}", query, bindings);
+
+    let mut response = client
+        .query(query)
+        .bind(bindings.into_inner())
+        .await?;
+
+    let count: Option<i32> = response.take((0, "count"))?;
+    let scores: Vec<f64> = response.take((1, "score"))?;
+    let highlighted: Vec<Option<String>> = response.take((1, "highlight"))?;
+    let records: Vec<Record> = response.take(1)?;
+
+    let hits = records.iter()
+        .zip(scores)
+        .zip(highlighted)
+        .map(|((record, score), highlighted)| SearchHit {
+            meetup_url: map_record_to_graph_meetup_url()(record),
+            score,
+            highlights: highlighted.as_deref().map(highlights).unwrap_or_default(),
+        })
+        .collect();
+
+    Ok((hits, group_all_count(count)))
+}
+
+/// Splits the highlighted `search_text` back into its columns, keeping those with a match
+fn highlights(highlighted: &str) -> Vec<SearchHighlight> {
+    highlighted
+        .split(FIELD_SEPARATOR)
+        .zip(SEARCH_FIELDS)
+        .filter_map(|(text, field)| {
+            let fragments = fragments(text);
+            fragments.iter().any(|f| f.matched).then(|| SearchHighlight { field, fragments: snippet(fragments) })
+        })
+        .collect()
+}
+
+fn fragments(text: &str) -> Vec<TextFragment> {
+    let mut fragments = Vec::new();
+    let mut push = |text: &str, matched: bool| {
+        if !text.is_empty() {
+            fragments.push(TextFragment { text: text.to_string(), matched });
+        }
+    };
+
+    let mut parts = text.split(MATCH_OPEN);
+    push(parts.next().unwrap_or_default(), false);
+    for part in parts {
+        let (matched, rest) = part.split_once(MATCH_CLOSE).unwrap_or((part, ""));
+        push(matched, true);
+        push(rest, false);
+    }
+    fragments
+}
+
+/// Shortens unmatched text to `SNIPPET_CONTEXT` characters next to each match
+fn snippet(fragments: Vec<TextFragment>) -> Vec<TextFragment> {
+    let last = fragments.len().saturating_sub(1);
+    fragments
+        .into_iter()
+        .enumerate()
+        .map(|(i, fragment)| {
+            let length = fragment.text.chars().count();
+            if fragment.matched || length <= 2 * SNIPPET_CONTEXT {
+                return fragment;
+            }
+            let head = fragment.text.chars().take(SNIPPET_CONTEXT).collect::<String>();
+            let tail = fragment.text.chars().skip(length - SNIPPET_CONTEXT).collect::<String>();
+            let text = match (i == 0, i == last) {
+                (true, true) => head + "…",
+                (true, false) => "…".to_string() + &tail,
+                (false, true) => head + "…",
+                (false, false) => head + " … " + &tail,
+            };
+            TextFragment { text, matched: false }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{highlights, MATCH_CLOSE, MATCH_OPEN};
+    use crate::graphql::{SearchField, TextFragment};
+
+    fn marked(term: &str) -> String {
+        format!("{}{}{}", MATCH_OPEN, term, MATCH_CLOSE)
+    }
+
+    #[test]
+    fn test_highlights_per_column() {
+        let long = "x".repeat(200);
+        let text = format!(
+            "{} web \u{1f} {} a {} \u{1f}  \u{1f} docs.rs \u{1f} https://docs.rs/",
+            marked("Axum"), long, marked("tutorial")
+        );
+
+        let highlights = highlights(&text);
+
+        assert_eq!(highlights.len(), 2);
+        assert_eq!(highlights[0].field, SearchField::Title);
+        assert_eq!(highlights[0].fragments, vec![
+            TextFragment { text: "Axum".to_string(), matched: true },
+            TextFragment { text: " web".to_string(), matched: false },
+        ]);
+        assert_eq!(highlights[1].field, SearchField::AutoDescr);
+        assert_eq!(highlights[1].fragments[0].text, format!("…{} a ", "x".repeat(57)));
+        assert!(highlights[1].fragments[1].matched);
+    }
+}
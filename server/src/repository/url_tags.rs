@@ -0,0 +1,89 @@
+use crate::graphql::{HistoryOperation, MeetupUrl, Tag};
+use crate::repository::url_history::{write_with_history, Change, TagChange};
+use crate::repository::url_link_health::LINK_HEALTH_PROJECTION;
+use crate::repository::url_repository::{find_by_uri_uuid, map_record_to_graph_meetup_url, Record};
+use chrono::Utc;
+use surrealdb::engine::any::Any;
+use surrealdb::sql::Datetime;
+use surrealdb::{Error, Surreal};
+
+/// Added to `SELECT *` on `url`: the tags of the row, each with the number
+/// of rows outside the trash carrying it
+pub(super) const TAG_PROJECTION: &str =
+    ", (SELECT name, count(<-tagged<-url[WHERE deleted_at = NONE]) AS count FROM $parent->tagged->tag ORDER BY name) AS tags";
+
+/// Sets the tags of the row `$id` to `$tags.after`. Tags of `$tags.before`
+/// no row carries any more are dropped.
+const SET_TAGS: &str = "{ \
+    LET $url = type::thing('url', $id); \
+    FOR $name IN $tags.after { \
+        LET $tag = type::thing('tag', $name); \
+        UPSERT $tag SET name = $name; \
+        IF $tag NOTINSIDE $url->tagged->tag { RELATE $url->tagged->$tag; }; \
+    }; \
+    DELETE tagged WHERE in = $url AND out.name NOTINSIDE $tags.after; \
+    DELETE tag WHERE name INSIDE $tags.before AND count(<-tagged) = 0; \
+    RETURN SELECT * FROM ONLY $url; \
+}";
+
+/// Tags `uri_uuid` with `names`, which must be tag names already, see
+/// `model::tag_name`. Tags it carries already are skipped. `None` when no
+/// record outside the trash carries `uri_uuid`.
+pub async fn add_tags(client: &Surreal<Any>, uri_uuid: String, names: Vec<String>, user: &str) -> Result<Option<MeetupUrl>, Error> {
+    change_tags(client, uri_uuid, user, |tags| tags.extend(names)).await
+}
+
+/// Removes `names` from `uri_uuid`, tags it does not carry are skipped.
+/// Tags no row carries any more are dropped.
+pub async fn remove_tags(client: &Surreal<Any>, uri_uuid: String, names: Vec<String>, user: &str) -> Result<Option<MeetupUrl>, Error> {
+    change_tags(client, uri_uuid, user, |tags| tags.retain(|tag| !names.contains(tag))).await
+}
+
+/// Changes the tags of the row outside the trash with `edit` and records
+/// the change in its history, by `user`. A change leaving the tags as they
+/// were is not recorded.
+async fn change_tags(client: &Surreal<Any>, uri_uuid: String, user: &str, edit: impl FnOnce(&mut Vec<String>)) -> Result<Option<MeetupUrl>, Error> {
+    let Some((id, row)) = find_by_uri_uuid(client, uri_uuid.clone()).await?
+        .filter(|(_, row)| row.deleted_at.is_none()) else {
+        return Ok(None);
+    };
+
+    let before: Vec<String> = client
+        .query("SELECT VALUE name FROM type::thing('url', $id)->tagged->tag ORDER BY name")
+        .bind(("id", id.clone()))
+        .await?.take(0)?;
+    let mut after = before.clone();
+    edit(&mut after);
+    after.sort();
+    after.dedup();
+
+    if after != before {
+        let change = Change {
+            operation: HistoryOperation::Tag,
+            actor: user.to_string(),
+            time: Datetime::from(Utc::now()),
+            before: Some(row.clone()),
+            after: Some(row),
+            tags: Some(TagChange { before, after }),
+        };
+        let _: Option<Record> = write_with_history(client, SET_TAGS, Some(id.clone()), &uri_uuid, change).await?;
+    }
+
+    // Read back after the commit, inside the transaction the traversal
+    // may miss the remaining edges once some were deleted
+    let record: Option<Record> = client
+        .query(format!("SELECT *{TAG_PROJECTION}{LINK_HEALTH_PROJECTION} FROM ONLY type::thing('url', $id)"))
+        .bind(("id", id))
+        .await?.take(0)?;
+
+    Ok(record.as_ref().map(map_record_to_graph_meetup_url()))
+}
+
+/// All tags, the most used first
+pub async fn select_tags(client: &Surreal<Any>) -> Result<Vec<Tag>, Error> {
+    let tags: Vec<Tag> = client
+        .query("SELECT name, count(<-tagged<-url[WHERE deleted_at = NONE]) AS count FROM tag ORDER BY count DESC, name")
+        .await?.take(0)?;
+
+    Ok(tags)
+}
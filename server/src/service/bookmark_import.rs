@@ -0,0 +1,80 @@
+use crate::graphql::ImportFormat;
+use crate::model::{decode_html, html_text, tag_attributes};
+use crate::service::file_import::{parse_time, ImportRows, Importer, Link};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static DOCTYPE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^\s*<!DOCTYPE\s+NETSCAPE-Bookmark-file-1\s*>").unwrap());
+/// A bookmark and the `<DD>` describing it, up to the next tag
+static BOOKMARK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<a\b([^>]*)>(.*?)</a\s*>(?:\s*<dd>([^<]*))?").unwrap());
+
+/// Bookmarks HTML as exported by Firefox and Chrome. Folders are ignored,
+/// so are bookmarks without `HREF`.
+pub struct BookmarkImporter;
+
+impl Importer for BookmarkImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::NetscapeBookmarks
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        DOCTYPE.is_match(content)
+    }
+
+    fn read(&self, content: &str, user: &str) -> Result<ImportRows, String> {
+        Ok(BOOKMARK.captures_iter(content)
+            .filter_map(|bookmark| {
+                let attributes = tag_attributes(&bookmark[1]);
+                let url = decode_html(attributes.get("href")?.trim());
+                let time = |name: &str| attributes.get(name).filter(|time| !time.trim().is_empty()).map(|time| parse_time(time)).transpose();
+
+                Some(time("add_date").and_then(|created| {
+                    Link {
+                        url,
+                        title: Some(html_text(&bookmark[2])),
+                        description: bookmark.get(3).map(|description| html_text(description.as_str())),
+                        note: None,
+                        created,
+                        modified: time("last_modified")?,
+                    }.row(user)
+                }))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BookmarkImporter;
+    use crate::service::file_import::Importer;
+
+    #[test]
+    fn test_read() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+            <META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+            <TITLE>Bookmarks</TITLE>
+            <H1>Bookmarks Menu</H1>
+            <DL><p>
+                <DT><H3 ADD_DATE="1700000000">Rust</H3>
+                <DL><p>
+                    <DT><A HREF="https://blog.rust-lang.org/?utm_source=feed" ADD_DATE="1700000000" LAST_MODIFIED="1700003600" ICON="data:image/png;base64,AA==">Rust &amp; Blog</A>
+                    <DD>News from the
+                    Rust team
+                    <DT><A HREF="place:sort=8&amp;maxResults=10">Recent Tags</A>
+                    <DT><A HREF="https://this-week-in-rust.org/">This Week in Rust</A>
+                </DL><p>
+                <DT><A>No link</A>
+            </DL><p>"#;
+        let rows = BookmarkImporter.read(html, "curator").unwrap();
+        assert_eq!(rows.len(), 3);
+
+        let blog = &rows[0].as_ref().unwrap().row;
+        assert_eq!((blog.url.as_str(), blog.title.as_str()), ("https://blog.rust-lang.org/", "Rust & Blog"));
+        assert_eq!(blog.auto_descr, "News from the Rust team");
+        assert_eq!(blog.crea_time.to_string(), "d'2023-11-14T22:13:20Z'");
+        assert_eq!(blog.modi_time.to_string(), "d'2023-11-14T23:13:20Z'");
+
+        assert!(rows[1].as_ref().unwrap_err().contains("must use http or https"));
+        assert_eq!(rows[2].as_ref().unwrap().row.auto_descr, "");
+    }
+}
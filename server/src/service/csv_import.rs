@@ -0,0 +1,141 @@
+use crate::graphql::ImportFormat;
+use crate::service::file_import::{parse_time, ImportRows, Importer, Link};
+
+const DELIMITERS: [char; 3] = [',', ';', '\t'];
+
+/// Header names of the columns read, compared lowercase with spaces and
+/// dashes as underscores. Other columns are ignored.
+const URL: &[&str] = &["url", "link", "href", "address", "uri"];
+const TITLE: &[&str] = &["title", "name"];
+const DESCRIPTION: &[&str] = &["description", "auto_descr", "descr", "summary"];
+const NOTE: &[&str] = &["man_descr", "note", "notes", "comment"];
+const CREATED: &[&str] = &["created", "crea_time", "date", "added", "date_added"];
+const MODIFIED: &[&str] = &["modified", "modi_time", "updated", "last_modified"];
+
+/// Spreadsheet exports: a header row naming the columns, one link per row
+pub struct CsvImporter;
+
+impl Importer for CsvImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::Csv
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        let header = content.lines().next().unwrap_or_default();
+        column(&header_names(header), URL).is_some()
+    }
+
+    fn read(&self, content: &str, user: &str) -> Result<ImportRows, String> {
+        let mut records = records(content, delimiter(content.lines().next().unwrap_or_default())).into_iter();
+        let header = records.next().unwrap_or_default().iter().map(|name| header_name(name)).collect::<Vec<String>>();
+
+        let url = column(&header, URL).ok_or("no url column")?;
+        let [title, description, note, created, modified] = [TITLE, DESCRIPTION, NOTE, CREATED, MODIFIED].map(|names| column(&header, names));
+
+        Ok(records
+            .filter(|record| record.iter().any(|field| !field.trim().is_empty()))
+            .map(|record| {
+                let field = |column: Option<usize>| column.and_then(|column| record.get(column))
+                    .map(|field| field.trim().to_string())
+                    .filter(|field| !field.is_empty());
+                let time = |column: Option<usize>| field(column).map(|time| parse_time(&time)).transpose();
+
+                Link {
+                    url: field(Some(url)).ok_or("no url")?,
+                    title: field(title),
+                    description: field(description),
+                    note: field(note),
+                    created: time(created)?,
+                    modified: time(modified)?,
+                }.row(user)
+            })
+            .collect())
+    }
+}
+
+/// The one of `DELIMITERS` found most often in the header, a comma on a tie
+fn delimiter(header: &str) -> char {
+    let count = |delimiter: char| header.matches(delimiter).count();
+    DELIMITERS.into_iter().fold(',', |best, delimiter| if count(delimiter) > count(best) { delimiter } else { best })
+}
+
+fn header_names(header: &str) -> Vec<String> {
+    records(header, delimiter(header)).into_iter().next().unwrap_or_default().iter().map(|name| header_name(name)).collect()
+}
+
+fn header_name(name: &str) -> String {
+    name.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+/// Index of the first of `names` found in `header`
+fn column(header: &[String], names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| header.iter().position(|column| column == name))
+}
+
+/// Fields by record as of RFC 4180: quoted fields may hold the delimiter,
+/// line breaks and `""` for a quote
+fn records(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            c if quoted => field.push(c),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{records, CsvImporter};
+    use crate::service::file_import::Importer;
+
+    #[test]
+    fn test_read() {
+        let csv = "Name;Link;Notes;Date Added\r\n\
+            \"Tokio; the runtime\";https://tokio.rs/;\"Said \"\"async\"\"\nand more\";2024-03-01\r\n\
+            \r\n\
+            Axum;;;\n\
+            ;https://leptos.dev;;yesterday\n\
+            ;https://actix.rs";
+        let rows = CsvImporter.read(csv, "curator").unwrap();
+        assert_eq!(rows.len(), 4);
+
+        let tokio = &rows[0].as_ref().unwrap().row;
+        assert_eq!((tokio.url.as_str(), tokio.title.as_str()), ("https://tokio.rs/", "Tokio; the runtime"));
+        assert_eq!(tokio.man_descr, "Said \"async\"\nand more");
+        assert_eq!(tokio.crea_time.to_string(), "d'2024-03-01T00:00:00Z'");
+        assert_eq!(tokio.crea_user, "curator");
+
+        assert_eq!(rows[1], Err("no url".to_string()));
+        assert_eq!(rows[2], Err("'yesterday' is not a time".to_string()));
+        assert_eq!(rows[3].as_ref().unwrap().row.title, "actix.rs");
+
+        assert_eq!(records("a\tb\n", '\t'), vec![vec!["a", "b"]]);
+        assert!(CsvImporter.read("title\nTokio", "curator").is_err());
+    }
+}
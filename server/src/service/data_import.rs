@@ -0,0 +1,343 @@
+use crate::graphql::{ImportFormat, ImportReport, ImportRowError, MeetupUrl, MeetupUrlChangeKind};
+use crate::model::{MeetupUrl as DbMeetupUrl, NormalizedUrl};
+use crate::repository::{find_import_target, write_imported, ImportWrite};
+use crate::service::file_import::{ImportRow, ImportRows};
+use crate::service::{import_data, read_rows};
+use std::collections::HashMap;
+use std::fmt;
+use surrealdb::engine::any::Any;
+use surrealdb::{Error, Surreal};
+use tracing::log::{log, Level};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImportOptions {
+    /// Only report what would be written
+    pub dry_run: bool,
+    /// Rows written per transaction
+    pub batch_size: usize,
+}
+
+/// Report of an import and the rows it wrote, none on a dry run
+#[derive(Debug, Clone)]
+pub struct Imported {
+    pub report: ImportReport,
+    pub changed: Vec<(MeetupUrlChangeKind, MeetupUrl)>,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    /// The file, not a row of it
+    Read(String),
+    Storage(Error),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Read(reason) => write!(f, "{}", reason),
+            ImportError::Storage(e) => write!(f, "import failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// `import_rows` of `data_file`, see `read_rows` for the formats read
+pub async fn import_file(client: &Surreal<Any>, data_file: &str, options: ImportOptions, actor: &str) -> Result<Imported, ImportError> {
+    let (format, rows) = import_data(data_file, actor).map_err(ImportError::Read)?;
+    imported(client, format, rows, options, actor).await
+}
+
+/// `import_rows` of an uploaded file, rows naming no creator are created
+/// by `actor`
+pub async fn import_content(client: &Surreal<Any>, content: &[u8], options: ImportOptions, actor: &str) -> Result<Imported, ImportError> {
+    let (format, rows) = read_rows(content, actor).map_err(ImportError::Read)?;
+    imported(client, format, rows, options, actor).await
+}
+
+async fn imported(client: &Surreal<Any>, format: ImportFormat, rows: ImportRows, options: ImportOptions, actor: &str) -> Result<Imported, ImportError> {
+    let mut imported = import_rows(client, rows, options, actor).await.map_err(ImportError::Storage)?;
+    imported.report.format = format;
+    Ok(imported)
+}
+
+/// Upserts the rows: a row is matched with the stored one carrying its
+/// `uri_uuid`, else its normalized url. Stored rows are updated only when
+/// the row was modified after them, so importing the same rows again
+/// changes nothing. Rows in the trash stay there. Updates are recorded as
+/// made by `actor`.
+pub async fn import_rows(client: &Surreal<Any>, rows: ImportRows, options: ImportOptions, actor: &str) -> Result<Imported, Error> {
+    let mut importer = ImportRun {
+        client,
+        options,
+        actor,
+        report: ImportReport { dry_run: options.dry_run, ..ImportReport::default() },
+        changed: Vec::new(),
+        seen: HashMap::new(),
+        batch: Vec::new(),
+    };
+
+    for (index, row) in rows.into_iter().enumerate() {
+        importer.add(index as i32 + 1, row).await?;
+        if importer.batch.len() >= options.batch_size.max(1) {
+            importer.flush().await;
+        }
+    }
+    importer.flush().await;
+
+    let report = importer.report;
+    log!(Level::Info, "Imported {} rows, updated {}, skipped {}, failed {}{}",
+        report.inserted, report.updated, report.skipped, report.failed, if report.dry_run { " (dry run)" } else { "" });
+    Ok(Imported { report, changed: importer.changed })
+}
+
+struct ImportRun<'a> {
+    client: &'a Surreal<Any>,
+    options: ImportOptions,
+    actor: &'a str,
+    report: ImportReport,
+    changed: Vec<(MeetupUrlChangeKind, MeetupUrl)>,
+    /// Row numbers by `uri_uuid` and `url_key`
+    seen: HashMap<String, i32>,
+    /// Writes not made yet, with their row numbers
+    batch: Vec<(i32, ImportWrite)>,
+}
+
+impl ImportRun<'_> {
+    async fn add(&mut self, number: i32, row: Result<ImportRow, String>) -> Result<(), Error> {
+        let row = match row.and_then(prepare) {
+            Ok(row) => row,
+            Err(reason) => {
+                self.fail(number, None, reason);
+                return Ok(());
+            }
+        };
+
+        let keys = [Some(row.row.uri_uuid.clone()), row.row.url_key.clone()].into_iter().flatten().collect::<Vec<String>>();
+        if let Some(earlier) = keys.iter().find_map(|key| self.seen.get(key)) {
+            let reason = format!("same url as row {}", earlier);
+            self.fail(number, Some(row.row.uri_uuid), reason);
+            return Ok(());
+        }
+        for key in keys {
+            self.seen.insert(key, number);
+        }
+
+        let write = match find_import_target(self.client, row.row.uri_uuid.clone(), row.row.url_key.clone()).await? {
+            None => ImportWrite::Insert(Box::new(row.row)),
+            Some((_, stored)) if stored.deleted_at.is_some() || !newer(&row, &stored) => {
+                self.report.skipped += 1;
+                return Ok(());
+            }
+            Some((id, stored)) => ImportWrite::Update { id, after: Box::new(update(&stored, row)), before: Box::new(stored) },
+        };
+        self.count(&write, 1);
+        self.batch.push((number, write));
+        Ok(())
+    }
+
+    /// Writes the batch in one transaction. When that fails the rows are
+    /// written one by one, to tell which of them failed.
+    async fn flush(&mut self) {
+        let batch = std::mem::take(&mut self.batch);
+        if self.options.dry_run || batch.is_empty() {
+            return;
+        }
+
+        let writes = batch.iter().map(|(_, write)| write.clone()).collect::<Vec<ImportWrite>>();
+        let failure = match write_imported(self.client, writes, self.actor).await {
+            Ok(written) => {
+                self.written(&batch, written);
+                return;
+            }
+            Err(e) => e,
+        };
+        if batch.len() == 1 {
+            self.unwrite(&batch[0], failure.to_string());
+            return;
+        }
+
+        log!(Level::Warn, "Import batch failed, writing its rows one by one: {}", failure);
+        for entry in batch {
+            match write_imported(self.client, vec![entry.1.clone()], self.actor).await {
+                Ok(written) => self.written(&[entry], written),
+                Err(e) => self.unwrite(&entry, e.to_string()),
+            }
+        }
+    }
+
+    fn written(&mut self, batch: &[(i32, ImportWrite)], written: Vec<MeetupUrl>) {
+        let inserted = batch.iter()
+            .filter_map(|(_, write)| match write {
+                ImportWrite::Insert(row) => Some(row.uri_uuid.as_str()),
+                ImportWrite::Update { .. } => None,
+            })
+            .collect::<Vec<&str>>();
+        for url in written {
+            let kind = if inserted.contains(&url.uri_uuid.as_str()) { MeetupUrlChangeKind::Created } else { MeetupUrlChangeKind::Updated };
+            self.changed.push((kind, url));
+        }
+    }
+
+    /// Moves a row counted as written to the failed ones
+    fn unwrite(&mut self, (number, write): &(i32, ImportWrite), reason: String) {
+        self.count(write, -1);
+        let uri_uuid = match write {
+            ImportWrite::Insert(row) => row.uri_uuid.clone(),
+            ImportWrite::Update { before, .. } => before.uri_uuid.clone(),
+        };
+        self.fail(*number, Some(uri_uuid), reason);
+    }
+
+    fn count(&mut self, write: &ImportWrite, by: i32) {
+        match write {
+            ImportWrite::Insert(_) => self.report.inserted += by,
+            ImportWrite::Update { .. } => self.report.updated += by,
+        }
+    }
+
+    fn fail(&mut self, row: i32, uri_uuid: Option<String>, reason: String) {
+        self.report.failed += 1;
+        self.report.errors.push(ImportRowError { row, uri_uuid, reason });
+    }
+}
+
+/// Derives `url_key`, and `uri_uuid` when the row has none. Rows with an
+/// invalid url are kept as they are, like before the key existed.
+fn prepare(mut import: ImportRow) -> Result<ImportRow, String> {
+    let row = &mut import.row;
+    match NormalizedUrl::parse(&row.url) {
+        Ok(url) => {
+            row.url_key = Some(url.key());
+            if row.uri_uuid.trim().is_empty() {
+                row.uri_uuid = url.uri_uuid();
+            }
+        }
+        Err(reason) if row.uri_uuid.trim().is_empty() => return Err(reason),
+        Err(_) => row.url_key = None,
+    }
+    Ok(import)
+}
+
+/// Modified after `stored` by the time the file tells, and differing from
+/// it. Rows of files telling no time never are.
+fn newer(import: &ImportRow, stored: &DbMeetupUrl) -> bool {
+    let Some(modi_time) = &import.modi_time else {
+        return false;
+    };
+    let updated = update(stored, import.clone());
+    *modi_time > stored.modi_time
+        && DbMeetupUrl { modi_user: stored.modi_user.clone(), modi_time: stored.modi_time.clone(), ..updated } != *stored
+}
+
+/// `stored` with the columns the file gives. The `uri_uuid`, the creation,
+/// the link health and the page metadata stay, so do the texts the file
+/// leaves out, above all the curator's `man_descr`.
+fn update(stored: &DbMeetupUrl, import: ImportRow) -> DbMeetupUrl {
+    let row = import.row;
+    DbMeetupUrl {
+        url: row.url,
+        scheme: row.scheme,
+        host: row.host,
+        port: row.port,
+        path: row.path,
+        query: row.query,
+        title: import.title.unwrap_or(stored.title.clone()),
+        auto_descr: import.auto_descr.unwrap_or(stored.auto_descr.clone()),
+        man_descr: import.man_descr.unwrap_or(stored.man_descr.clone()),
+        modi_user: row.modi_user,
+        modi_time: import.modi_time.unwrap_or(row.modi_time),
+        url_key: row.url_key.or(stored.url_key.clone()),
+        ..stored.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{import_file, import_rows, ImportOptions};
+    use crate::config::connect_test_db;
+    use crate::service::file_import::{read_rows, ImportRow};
+    use crate::graphql::testing::{meetup_url, time};
+    use crate::graphql::MeetupUrlFilter;
+    use crate::repository::{count_url, delete_by_uri_uuid, insert_init_meetup_url, select_url_with_count};
+
+    const SAMPLE: &str = "testdata/data_sample.json";
+
+    fn options(dry_run: bool) -> ImportOptions {
+        ImportOptions { dry_run, batch_size: 10 }
+    }
+
+    #[tokio::test]
+    async fn test_import_is_idempotent() {
+        let client = connect_test_db().await;
+
+        let dry = import_file(&client, SAMPLE, options(true), "curator").await.unwrap();
+        assert_eq!((dry.report.inserted, dry.report.failed), (25, 0));
+        assert!(dry.changed.is_empty());
+        assert_eq!(count_url(&client, MeetupUrlFilter::default()).await.unwrap(), 0);
+
+        let first = import_file(&client, SAMPLE, options(false), "curator").await.unwrap();
+        assert_eq!((first.report.inserted, first.report.updated, first.report.skipped, first.report.failed), (25, 0, 0, 0));
+        assert_eq!(first.changed.len(), 25);
+
+        let second = import_file(&client, SAMPLE, options(false), "curator").await.unwrap();
+        assert_eq!((second.report.inserted, second.report.updated, second.report.skipped, second.report.failed), (0, 0, 25, 0));
+        assert_eq!(count_url(&client, MeetupUrlFilter::default()).await.unwrap(), 25);
+
+        assert!(import_file(&client, "testdata/missing.json", options(false), "curator").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_rows() {
+        let client = connect_test_db().await;
+        for (uri_uuid, host) in [("a", "actix.rs"), ("b", "leptos.dev"), ("c", "tokio.rs"), ("e", "rocket.rs")] {
+            insert_init_meetup_url(meetup_url(uri_uuid, host, "Stored"), &client).await.unwrap();
+        }
+        delete_by_uri_uuid(&client, "c".to_string(), "curator").await.unwrap();
+
+        let later = time("2025-01-01T00:00:00Z");
+        let rows = vec![
+            // Changed in the file since, matched by its url
+            Ok(crate::model::MeetupUrl { uri_uuid: "other".to_string(), title: "Actix".to_string(), modi_time: later.clone(), ..meetup_url("a", "actix.rs", "") }),
+            Ok(crate::model::MeetupUrl { title: "Older".to_string(), ..meetup_url("b", "leptos.dev", "") }),
+            Ok(crate::model::MeetupUrl { title: "Tokio".to_string(), modi_time: later.clone(), ..meetup_url("c", "tokio.rs", "") }),
+            Ok(meetup_url("d", "axum.rs", "New")),
+            Ok(meetup_url("d", "axum.rs", "Again")),
+            Err("missing field `url`".to_string()),
+            // Takes the url of "e", the unique index fails the whole batch
+            Ok(crate::model::MeetupUrl { url: "https://rocket.rs/e".to_string(), modi_time: later, ..meetup_url("b", "leptos.dev", "Leptos") }),
+        ];
+        let rows = rows.into_iter().map(|row| row.map(ImportRow::from)).collect();
+        let imported = import_rows(&client, rows, options(false), "curator").await.unwrap();
+
+        let report = imported.report;
+        assert_eq!((report.inserted, report.updated, report.skipped, report.failed), (1, 1, 2, 3), "{:?}", report.errors);
+        let failed = report.errors.iter().map(|e| (e.row, e.uri_uuid.as_deref())).collect::<Vec<(i32, Option<&str>)>>();
+        assert_eq!(failed, vec![(5, Some("d")), (6, None), (7, Some("b"))]);
+        assert_eq!(report.errors[0].reason, "same url as row 4");
+
+        let (rows, _) = select_url_with_count(&client, MeetupUrlFilter { include_deleted: Some(true), ..MeetupUrlFilter::default() }).await.unwrap();
+        let mut titles = rows.iter().map(|u| (u.uri_uuid.as_str(), u.title.as_str())).collect::<Vec<(&str, &str)>>();
+        titles.sort();
+        assert_eq!(titles, vec![("a", "Actix"), ("b", "Stored"), ("c", "Stored"), ("d", "New"), ("e", "Stored")]);
+    }
+
+    #[tokio::test]
+    async fn test_reimport_keeps_curated_texts() {
+        let client = connect_test_db().await;
+        let stored = crate::model::MeetupUrl { man_descr: "Async runtime, see the tutorial".to_string(), ..meetup_url("t", "tokio.rs", "Tokio") };
+        insert_init_meetup_url(stored, &client).await.unwrap();
+
+        // Without a time the file tells nothing newer, with one only the title
+        for (csv, title) in [
+            ("url,title,note\nhttps://tokio.rs/t,Tokio Runtime,", "Tokio"),
+            ("url,title,note,modified\nhttps://tokio.rs/t,Tokio Runtime,,2025-01-01", "Tokio Runtime"),
+        ] {
+            let (_, rows) = read_rows(csv.as_bytes(), "importer").unwrap();
+            import_rows(&client, rows, options(false), "importer").await.unwrap();
+
+            let (rows, _) = select_url_with_count(&client, MeetupUrlFilter::default()).await.unwrap();
+            assert_eq!((rows[0].title.as_str(), rows[0].man_descr.as_str()), (title, "Async runtime, see the tutorial"), "{}", csv);
+        }
+    }
+}
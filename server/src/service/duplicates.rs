@@ -0,0 +1,157 @@
+use crate::graphql::{DuplicateGroup, MeetupUrl, MeetupUrlFilter};
+use crate::model::NormalizedUrl;
+use crate::repository::select_url_with_count;
+use std::collections::{BTreeMap, HashMap};
+use surrealdb::engine::any::Any;
+use surrealdb::{Error, Surreal};
+
+/// Rows sharing a host whose pages or titles match, for curators to merge.
+/// Each group lists its oldest row first.
+pub async fn duplicate_candidates(client: &Surreal<Any>) -> Result<Vec<DuplicateGroup>, Error> {
+    let (mut urls, _) = select_url_with_count(client, MeetupUrlFilter::default()).await?;
+    urls.sort_by(|a, b| a.crea_time.cmp(&b.crea_time).then_with(|| a.uri_uuid.cmp(&b.uri_uuid)));
+    Ok(group_candidates(urls))
+}
+
+fn host_key(url: &MeetupUrl) -> String {
+    let host = url.host.trim().to_lowercase();
+    host.strip_prefix("www.").map(str::to_string).unwrap_or(host)
+}
+
+/// The page without scheme, fragment, trailing slash or index file, its
+/// query parameters in any order
+fn page_key(url: &MeetupUrl) -> String {
+    let Ok(normalized) = NormalizedUrl::parse(&url.url) else {
+        return url.url.trim().to_lowercase();
+    };
+    let path = normalized.path.to_lowercase();
+    let path = path.trim_end_matches("index.html").trim_end_matches("index.htm").trim_end_matches('/');
+    let mut params = normalized.query.as_deref().unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>();
+    params.sort();
+    format!("{}?{}", path, params.join("&"))
+}
+
+/// Words of the title, ignoring case and punctuation
+fn title_key(url: &MeetupUrl) -> String {
+    url.title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[i] = root;
+    root
+}
+
+/// Within each host, rows are grouped transitively when their pages or
+/// their titles are equal. Groups keep the order of `urls`.
+fn group_candidates(urls: Vec<MeetupUrl>) -> Vec<DuplicateGroup> {
+    let mut by_host: BTreeMap<String, Vec<MeetupUrl>> = BTreeMap::new();
+    for url in urls {
+        by_host.entry(host_key(&url)).or_default().push(url);
+    }
+
+    let mut groups = Vec::new();
+    for (host, urls) in by_host {
+        let mut parents = (0..urls.len()).collect::<Vec<usize>>();
+        let mut seen: HashMap<(bool, String), usize> = HashMap::new();
+        for (i, url) in urls.iter().enumerate() {
+            let title = title_key(url);
+            let keys = [(false, page_key(url)), (true, title)];
+            for key in keys.into_iter().filter(|(is_title, key)| !(*is_title && key.is_empty())) {
+                let first = *seen.entry(key).or_insert(i);
+                let (a, b) = (find(&mut parents, first), find(&mut parents, i));
+                parents[b] = a.min(b);
+                parents[a] = a.min(b);
+            }
+        }
+
+        let mut members: BTreeMap<usize, Vec<MeetupUrl>> = BTreeMap::new();
+        for (i, url) in urls.into_iter().enumerate() {
+            let root = find(&mut parents, i);
+            members.entry(root).or_default().push(url);
+        }
+        groups.extend(
+            members.into_values()
+                .filter(|meetup_urls| meetup_urls.len() > 1)
+                .map(|meetup_urls| DuplicateGroup { host: host.clone(), meetup_urls }),
+        );
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::group_candidates;
+    use crate::graphql::MeetupUrl;
+
+    fn url(uri_uuid: &str, url: &str, host: &str, title: &str) -> MeetupUrl {
+        MeetupUrl {
+            uri_uuid: uri_uuid.to_string(),
+            url: url.to_string(),
+            host: host.to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_candidates() {
+        let groups = group_candidates(vec![
+            url("a", "https://github.com/rust-lang/rust-clippy/", "github.com", "Clippy"),
+            url("b", "http://github.com/rust-lang/rust-clippy", "github.com", "rust-lang/rust-clippy"),
+            url("c", "https://github.com/rust-lang/rust", "github.com", "Rust"),
+            url("d", "https://www.meetup.com/rust-noris/", "www.meetup.com", "Rust Noris"),
+            url("e", "https://meetup.com/rust-noris/events", "meetup.com", "rust noris!"),
+            url("f", "https://doc.rust-lang.org/std/string/struct.String.html#method.leak", "doc.rust-lang.org", "String"),
+            url("g", "https://doc.rust-lang.org/std/string/struct.String.html#method.into_bytes", "doc.rust-lang.org", "String in std"),
+            url("h", "https://leptos.dev/", "leptos.dev", ""),
+            url("i", "https://leptos.dev/book", "leptos.dev", ""),
+        ]);
+
+        let uuids = groups.iter()
+            .map(|g| (g.host.as_str(), g.meetup_urls.iter().map(|u| u.uri_uuid.as_str()).collect::<Vec<&str>>()))
+            .collect::<Vec<(&str, Vec<&str>)>>();
+        assert_eq!(uuids, vec![
+            ("doc.rust-lang.org", vec!["f", "g"]),
+            ("github.com", vec!["a", "b"]),
+            ("meetup.com", vec!["d", "e"]),
+        ]);
+    }
+
+    #[test]
+    fn test_url_variants() {
+        for (first, second) in [
+            ("https://tokio.rs/blog", "https://tokio.rs/blog/"),
+            ("https://tokio.rs/blog", "https://www.tokio.rs/blog"),
+            ("https://tokio.rs/blog?year=2024&tag=io", "https://tokio.rs/blog?tag=io&year=2024"),
+            ("https://tokio.rs/blog", "http://tokio.rs/blog"),
+            ("https://tokio.rs/blog/", "https://tokio.rs/blog/index.html"),
+            ("https://tokio.rs/Blog", "https://tokio.rs/blog?utm_source=mail"),
+        ] {
+            let host = |url: &str| url.split('/').nth(2).unwrap().to_string();
+            let groups = group_candidates(vec![
+                url("a", first, &host(first), "Blog"),
+                url("b", second, &host(second), "Tokio news"),
+            ]);
+            assert_eq!(groups.len(), 1, "{} {}", first, second);
+        }
+
+        let groups = group_candidates(vec![
+            url("a", "https://tokio.rs/blog?year=2024", "tokio.rs", "Blog"),
+            url("b", "https://tokio.rs/blog?year=2023", "tokio.rs", "Tokio news"),
+        ]);
+        assert!(groups.is_empty());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::graphql::{ExportFormat, MeetupUrl, MeetupUrlExport, MeetupUrlFilter, MeetupUrlOrder, MeetupUrlSort, SortDirection};
+use crate::model::escape_html;
+use crate::repository::{select_url_slice, Seek};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use surrealdb::engine::any::Any;
+use surrealdb::{Error, Surreal};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+/// Rows read per query
+const EXPORT_BATCH: usize = 200;
+
+const CSV_HEADER: [&str; 11] = ["uri_uuid", "url", "title", "auto_descr", "man_descr", "host", "tags", "crea_user", "crea_time", "modi_user", "modi_time"];
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::NetscapeBookmarks => "text/html; charset=utf-8",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "meetup-urls.json",
+            ExportFormat::Csv => "meetup-urls.csv",
+            ExportFormat::Markdown => "meetup-urls.md",
+            ExportFormat::NetscapeBookmarks => "meetup-urls.html",
+        }
+    }
+}
+
+/// The rows matching `filter` in its sort order, Markdown sorts by host
+/// first. Rows are read in batches and sent as soon as they are written,
+/// `filter.pagination` is ignored.
+pub fn export_stream(client: Surreal<Any>, filter: MeetupUrlFilter, format: ExportFormat) -> ReceiverStream<Result<String, Error>> {
+    let (sender, receiver) = mpsc::channel(2);
+    tokio::spawn(async move {
+        if let Err(e) = write_export(&client, filter, format, &sender).await {
+            let _ = sender.send(Err(e)).await;
+        }
+    });
+    ReceiverStream::new(receiver)
+}
+
+/// `export_stream` as a whole
+pub async fn export(client: &Surreal<Any>, filter: MeetupUrlFilter, format: ExportFormat) -> Result<MeetupUrlExport, Error> {
+    let mut stream = export_stream(client.clone(), filter, format);
+    let mut content = String::new();
+    while let Some(chunk) = stream.next().await {
+        content.push_str(&chunk?);
+    }
+    Ok(MeetupUrlExport { file_name: format.file_name().to_string(), content_type: format.content_type().to_string(), content })
+}
+
+/// Stops early when the receiver is gone
+async fn write_export(client: &Surreal<Any>, mut filter: MeetupUrlFilter, format: ExportFormat, sender: &mpsc::Sender<Result<String, Error>>) -> Result<(), Error> {
+    filter.pagination = None;
+    if format == ExportFormat::Markdown {
+        let by_host = MeetupUrlOrder { field: MeetupUrlSort::Domain, direction: SortDirection::Asc };
+        filter.sort = Some([vec![by_host], filter.sort.unwrap_or_default()].concat());
+    }
+
+    let mut writer = ExportWriter::new(format);
+    if sender.send(Ok(writer.start())).await.is_err() {
+        return Ok(());
+    }
+
+    let mut after = None;
+    loop {
+        let slice = select_url_slice(client, filter.clone(), Seek { after, before: None, limit: EXPORT_BATCH, backward: false }).await?;
+        let chunk = slice.rows.iter().map(|(url, _)| writer.write(url)).collect::<String>();
+        if sender.send(Ok(chunk)).await.is_err() {
+            return Ok(());
+        }
+        after = match slice.rows.into_iter().last() {
+            Some((_, cursor)) if slice.has_more => Some(cursor),
+            _ => break,
+        };
+    }
+
+    let _ = sender.send(Ok(writer.finish())).await;
+    Ok(())
+}
+
+/// The columns of `data/data.json`
+#[derive(Serialize)]
+struct HalreslibRow<'a> {
+    uri_uuid: &'a str,
+    url: &'a str,
+    scheme: &'a str,
+    host: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port: Option<i32>,
+    path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<&'a str>,
+    live_status: &'a str,
+    title: &'a str,
+    auto_descr: &'a str,
+    man_descr: &'a str,
+    crea_user: &'a str,
+    crea_time: String,
+    modi_user: &'a str,
+    modi_time: String,
+}
+
+/// Writes an export row by row
+struct ExportWriter {
+    format: ExportFormat,
+    rows: usize,
+    /// Lowercase host of the last Markdown section
+    host: Option<String>,
+}
+
+impl ExportWriter {
+    fn new(format: ExportFormat) -> Self {
+        ExportWriter { format, rows: 0, host: None }
+    }
+
+    fn start(&self) -> String {
+        match self.format {
+            ExportFormat::Json => "[".to_string(),
+            ExportFormat::Csv => format!("{}\r\n", CSV_HEADER.join(",")),
+            ExportFormat::Markdown => "# Meetup URLs\n".to_string(),
+            ExportFormat::NetscapeBookmarks => concat!(
+                "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n",
+                "<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n",
+                "<TITLE>Meetup URLs</TITLE>\n",
+                "<H1>Meetup URLs</H1>\n",
+                "<DL><p>\n",
+            ).to_string(),
+        }
+    }
+
+    fn write(&mut self, url: &MeetupUrl) -> String {
+        self.rows += 1;
+        match self.format {
+            ExportFormat::Json => self.json(url),
+            ExportFormat::Csv => csv(url),
+            ExportFormat::Markdown => self.markdown(url),
+            ExportFormat::NetscapeBookmarks => bookmark(url),
+        }
+    }
+
+    fn finish(&self) -> String {
+        match self.format {
+            ExportFormat::Json if self.rows == 0 => "]\n".to_string(),
+            ExportFormat::Json => "\n]\n".to_string(),
+            ExportFormat::Csv | ExportFormat::Markdown => String::new(),
+            ExportFormat::NetscapeBookmarks => "</DL><p>\n".to_string(),
+        }
+    }
+
+    fn json(&self, url: &MeetupUrl) -> String {
+        let row = HalreslibRow {
+            uri_uuid: &url.uri_uuid,
+            url: &url.url,
+            scheme: &url.scheme,
+            host: &url.host,
+            port: url.port,
+            path: &url.path,
+            query: url.query.as_deref(),
+            live_status: &url.live_status,
+            title: &url.title,
+            auto_descr: &url.auto_descr,
+            man_descr: &url.man_descr,
+            crea_user: &url.crea_user,
+            crea_time: rfc3339(url.crea_time),
+            modi_user: &url.modi_user,
+            modi_time: rfc3339(url.modi_time),
+        };
+        let json = serde_json::to_string_pretty(&row).expect("rows serialize");
+        let separator = if self.rows > 1 { "," } else { "" };
+        format!("{}\n  {}", separator, json.replace('\n', "\n  "))
+    }
+
+    fn markdown(&mut self, url: &MeetupUrl) -> String {
+        let mut written = String::new();
+        let host = url.host.to_lowercase();
+        if self.host.as_ref() != Some(&host) {
+            written.push_str(&format!("\n## {}\n\n", markdown_text(&host)));
+            self.host = Some(host);
+        }
+
+        let link = url.url.replace('(', "%28").replace(')', "%29").replace(' ', "%20");
+        written.push_str(&format!("- [{}]({})", markdown_text(&url.title), link));
+        let description = description(url);
+        if !description.is_empty() {
+            written.push_str(&format!(": {}", markdown_text(&description)));
+        }
+        written.push('\n');
+        written
+    }
+}
+
+fn csv(url: &MeetupUrl) -> String {
+    let tags = url.tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<&str>>().join(" ");
+    let fields = [
+        url.uri_uuid.clone(), url.url.clone(), url.title.clone(), url.auto_descr.clone(), url.man_descr.clone(), url.host.clone(), tags,
+        url.crea_user.clone(), rfc3339(url.crea_time), url.modi_user.clone(), rfc3339(url.modi_time),
+    ];
+    format!("{}\r\n", fields.iter().map(|field| csv_field(field)).collect::<Vec<String>>().join(","))
+}
+
+/// Quoted when holding a comma, a quote or a line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn bookmark(url: &MeetupUrl) -> String {
+    let tags = url.tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<&str>>().join(",");
+    let mut written = format!(
+        "    <DT><A HREF=\"{}\" ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\"{}>{}</A>\n",
+        escape_html(&url.url),
+        url.crea_time.timestamp(),
+        url.modi_time.timestamp(),
+        if tags.is_empty() { String::new() } else { format!(" TAGS=\"{}\"", escape_html(&tags)) },
+        escape_html(&url.title),
+    );
+    let description = description(url);
+    if !description.is_empty() {
+        written.push_str(&format!("    <DD>{}\n", escape_html(&description)));
+    }
+    written
+}
+
+/// The curator's description when there is one, else the fetched one
+fn description(url: &MeetupUrl) -> String {
+    let curated = single_line(&url.man_descr);
+    if curated.is_empty() { single_line(&url.auto_descr) } else { curated }
+}
+
+fn rfc3339(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Brackets and the like stay text instead of becoming markup
+fn markdown_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in single_line(text).chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '<' | '>' | '#') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::export;
+    use crate::config::connect_test_db;
+    use crate::graphql::testing::meetup_url;
+    use crate::graphql::{ExportFormat, MeetupUrlFilter, MeetupUrlOrder, MeetupUrlSort, SortDirection};
+    use crate::repository::{add_tags, insert_init_meetup_url};
+    use crate::service::read_rows;
+
+    #[tokio::test]
+    async fn test_export() {
+        let client = connect_test_db().await;
+        let rows = [
+            meetup_url("a", "tokio.rs", "Tokio, the [async] runtime"),
+            crate::model::MeetupUrl { auto_descr: "Web \"framework\"\nfor Rust".to_string(), ..meetup_url("b", "actix.rs", "Actix <Web>") },
+            meetup_url("c", "Tokio.rs", "Tutorial"),
+            crate::model::MeetupUrl { man_descr: "Actors and\nHTTP".to_string(), ..meetup_url("d", "actix.rs", "Actix actors") },
+        ];
+        for row in rows {
+            insert_init_meetup_url(row, &client).await.unwrap();
+        }
+        add_tags(&client, "b".to_string(), vec!["web".to_string(), "http".to_string()], "curator").await.unwrap();
+        let filter = || MeetupUrlFilter {
+            sort: Some(vec![MeetupUrlOrder { field: MeetupUrlSort::Title, direction: SortDirection::Desc }]),
+            ..MeetupUrlFilter::default()
+        };
+
+        let markdown = export(&client, filter(), ExportFormat::Markdown).await.unwrap().content;
+        assert_eq!(markdown, "# Meetup URLs\n\
+            \n## actix.rs\n\n\
+            - [Actix actors](https://actix.rs/d): Actors and HTTP\n\
+            - [Actix \\<Web\\>](https://actix.rs/b): Web \"framework\" for Rust\n\
+            \n## tokio.rs\n\n\
+            - [Tutorial](https://Tokio.rs/c): Tutorial\n\
+            - [Tokio, the \\[async\\] runtime](https://tokio.rs/a): Tokio, the \\[async\\] runtime\n");
+
+        let csv = export(&client, filter(), ExportFormat::Csv).await.unwrap();
+        assert_eq!(csv.file_name, "meetup-urls.csv");
+        let lines = csv.content.split("\r\n").collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[1].starts_with("c,https://Tokio.rs/c,Tutorial,"));
+        assert!(lines[2].starts_with("a,https://tokio.rs/a,\"Tokio, the [async] runtime\","));
+        assert!(lines[4].contains(",\"Web \"\"framework\"\"\nfor Rust\",,actix.rs,http web,"));
+        assert!(lines[3].starts_with("d,https://actix.rs/d,Actix actors,Actix actors,\"Actors and\nHTTP\",actix.rs,"));
+
+        let html = export(&client, filter(), ExportFormat::NetscapeBookmarks).await.unwrap().content;
+        assert!(html.contains("<A HREF=\"https://actix.rs/b\" ADD_DATE=\"1724946490\" LAST_MODIFIED=\"1724946490\" TAGS=\"http,web\">Actix &lt;Web&gt;</A>\n    <DD>Web &quot;framework&quot; for Rust\n"));
+        assert!(html.contains(">Actix actors</A>\n    <DD>Actors and HTTP\n"));
+
+        // The exports read back as the same links
+        for format in [ExportFormat::Json, ExportFormat::Csv, ExportFormat::NetscapeBookmarks] {
+            let content = export(&client, filter(), format).await.unwrap().content;
+            let (_, rows) = read_rows(content.as_bytes(), "curator").unwrap();
+            let urls = rows.into_iter().map(|row| row.unwrap().row.url.to_lowercase()).collect::<Vec<String>>();
+            assert_eq!(urls, vec!["https://tokio.rs/c", "https://tokio.rs/a", "https://actix.rs/d", "https://actix.rs/b"], "{:?}", format);
+        }
+
+        let empty = MeetupUrlFilter { title: Some("^none$".to_string()), ..MeetupUrlFilter::default() };
+        assert_eq!(export(&client, empty, ExportFormat::Json).await.unwrap().content, "[]\n");
+    }
+}
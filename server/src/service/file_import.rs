@@ -1,26 +1,168 @@
-use crate::model::MeetupUrl;
-use serde_json::Error;
+use crate::graphql::ImportFormat;
+use crate::model::{MeetupUrl, NormalizedUrl};
+use crate::service::bookmark_import::BookmarkImporter;
+use crate::service::csv_import::CsvImporter;
+use crate::service::opml_import::OpmlImporter;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::Value;
 use std::fs;
-use std::io::BufReader;
+use surrealdb::sql::Datetime;
 
-pub fn import_data() -> Vec<MeetupUrl> {
-    let file = fs::File::open("../data/data.json")
-        .expect("Error opening the file");
+/// Rows read from an import file, each read on its own so a malformed one
+/// fails alone
+pub type ImportRows = Vec<Result<ImportRow, String>>;
 
-    let reader = BufReader::new(file);
+/// A row of an import file. `row` is complete to be inserted, the options
+/// tell what the file actually said: where it said nothing a stored row
+/// keeps its value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRow {
+    pub row: MeetupUrl,
+    pub title: Option<String>,
+    pub auto_descr: Option<String>,
+    pub man_descr: Option<String>,
+    /// `None` when the file tells no time, the row is then never taken as
+    /// newer than a stored one
+    pub modi_time: Option<Datetime>,
+}
+
+impl From<MeetupUrl> for ImportRow {
+    /// A row giving every column, as halreslib rows do
+    fn from(row: MeetupUrl) -> Self {
+        ImportRow {
+            title: Some(row.title.clone()),
+            auto_descr: Some(row.auto_descr.clone()),
+            man_descr: Some(row.man_descr.clone()),
+            modi_time: Some(row.modi_time.clone()),
+            row,
+        }
+    }
+}
+
+/// Reads the links of one file format
+pub trait Importer {
+    fn format(&self) -> ImportFormat;
+
+    /// Whether `content` starts like a file of this format
+    fn detect(&self, content: &str) -> bool;
+
+    /// Rows the file tells no creator of are created by `user`
+    fn read(&self, content: &str, user: &str) -> Result<ImportRows, String>;
+}
+
+/// Asked in this order, CSV last as any text with a url column passes for it
+const IMPORTERS: [&dyn Importer; 4] = [&HalreslibImporter, &BookmarkImporter, &OpmlImporter, &CsvImporter];
+
+/// The rows of `content` in the format it is detected to be in
+pub fn read_rows(content: &[u8], user: &str) -> Result<(ImportFormat, ImportRows), String> {
+    let content = std::str::from_utf8(content).map_err(|_| "not UTF-8 text".to_string())?;
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+    let importer = IMPORTERS.iter().find(|importer| importer.detect(content))
+        .ok_or("unknown format, expected halreslib JSON, CSV with a url column, Netscape bookmarks or OPML".to_string())?;
+    Ok((importer.format(), importer.read(content, user)?))
+}
+
+/// `read_rows` of `data_file`
+pub fn import_data(data_file: &str, user: &str) -> Result<(ImportFormat, ImportRows), String> {
+    let content = fs::read(data_file)
+        .map_err(|e| format!("cannot open {}: {}", data_file, e))?;
+
+    read_rows(&content, user).map_err(|e| format!("cannot read {}: {}", data_file, e))
+}
+
+/// The JSON array written by halreslib, rows in the columns of the database
+pub struct HalreslibImporter;
+
+impl Importer for HalreslibImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::Halreslib
+    }
 
-    let json: Result<Vec<MeetupUrl>, Error> = serde_json::from_reader(reader);
-    if json.is_ok() {
-        json.unwrap()
-    } else {
-        Vec::new()
+    fn detect(&self, content: &str) -> bool {
+        content.trim_start().starts_with('[')
     }
+
+    fn read(&self, content: &str, _user: &str) -> Result<ImportRows, String> {
+        let rows: Vec<Value> = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        Ok(rows.into_iter().map(|row| serde_json::from_value::<MeetupUrl>(row).map(ImportRow::from).map_err(|e| e.to_string())).collect())
+    }
+}
+
+/// A link as the formats other than halreslib describe it
+#[derive(Debug, Clone, Default)]
+pub struct Link {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// The `man_descr`
+    pub note: Option<String>,
+    pub created: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl Link {
+    /// A row created by `user`. For a new row the title defaults to the
+    /// host and the times to now, see `ImportRow` for stored ones.
+    pub fn row(self, user: &str) -> Result<ImportRow, String> {
+        let url = NormalizedUrl::parse(&self.url)?;
+        let text = |text: Option<String>| text.map(|text| text.trim().to_string()).filter(|text| !text.is_empty());
+        let (title, auto_descr, man_descr) = (text(self.title), text(self.description), text(self.note));
+        let modified = match (self.created, self.modified) {
+            (Some(created), Some(modified)) => Some(modified.max(created)),
+            (created, modified) => modified.or(created),
+        };
+        let created = self.created.or(modified).unwrap_or(Utc::now());
+
+        let row = MeetupUrl {
+            uri_uuid: String::new(),
+            title: title.clone().unwrap_or(url.host.clone()),
+            url: url.url,
+            scheme: url.scheme,
+            host: url.host,
+            port: url.port,
+            path: url.path,
+            query: url.query,
+            live_status: "OK".to_string(),
+            auto_descr: auto_descr.clone().unwrap_or_default(),
+            man_descr: man_descr.clone().unwrap_or_default(),
+            crea_user: user.to_string(),
+            crea_time: Datetime::from(created),
+            modi_user: user.to_string(),
+            modi_time: Datetime::from(modified.unwrap_or(created)),
+            url_key: None,
+            deleted_at: None,
+            deleted_by: None,
+            canonical_url: None,
+            language: None,
+            favicon: None,
+            image: None,
+            site_name: None,
+            metadata_time: None,
+        };
+        Ok(ImportRow { row, title, auto_descr, man_descr, modi_time: modified.map(Datetime::from) })
+    }
+}
+
+/// RFC 3339, RFC 2822, a date with or without time in UTC, or seconds
+/// since 1970 as bookmark files write them
+pub fn parse_time(text: &str) -> Result<DateTime<Utc>, String> {
+    let text = text.trim();
+    let time = DateTime::parse_from_rfc3339(text)
+        .or_else(|_| DateTime::parse_from_rfc2822(text))
+        .map(|time| time.to_utc())
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").ok().map(|time| time.and_utc()))
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)).map(|time| time.and_utc()))
+        .or_else(|| text.parse::<i64>().ok().and_then(|seconds| DateTime::from_timestamp(seconds, 0)));
+    time.ok_or(format!("'{}' is not a time", text))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{import_data, parse_time, read_rows};
+    use crate::graphql::ImportFormat;
     use crate::model::MeetupUrl;
-    use crate::service::import_data;
     use serde_json::Error;
 
     #[test]
@@ -44,14 +186,35 @@ mod tests {
         "#;
 
         let result: Result<MeetupUrl, Error> = serde_json::from_str(data);
-        assert_eq!(result.is_ok(), true);
+        assert!(result.is_ok());
         let url = result.unwrap();
         println!("{:?}", url);
     }
 
     #[test]
     fn test_import() {
-        let data = import_data();
-        assert_eq!(data.is_empty(), false);
+        let (format, data) = import_data("../data/data.json", "curator").unwrap();
+        assert_eq!(format, ImportFormat::Halreslib);
+        assert!(!data.is_empty());
+
+        assert!(import_data("../data/missing.json", "curator").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_detect() {
+        let format = |content: &str| read_rows(content.as_bytes(), "curator").map(|(format, _)| format);
+
+        assert_eq!(format("\u{feff}  [ ]"), Ok(ImportFormat::Halreslib));
+        assert_eq!(format("<!DOCTYPE netscape-Bookmark-file-1>\n<DL><p></DL>"), Ok(ImportFormat::NetscapeBookmarks));
+        assert_eq!(format("<?xml version=\"1.0\"?>\n<!-- feeds -->\n<opml version=\"2.0\"><body/></opml>"), Ok(ImportFormat::Opml));
+        assert_eq!(format("Name;Link\nTokio;https://tokio.rs"), Ok(ImportFormat::Csv));
+
+        assert!(format("title,description\nTokio,runtime").is_err());
+        assert!(format("<html><body>bookmarks</body></html>").is_err());
+        assert!(read_rows(&[0xff, 0xfe, b'['], "curator").is_err());
+
+        assert_eq!(parse_time("1700000000"), parse_time("2023-11-14T22:13:20Z"));
+        assert_eq!(parse_time("Tue, 14 Nov 2023 22:13:20 GMT"), parse_time("2023-11-14 22:13:20"));
+        assert!(parse_time("yesterday").is_err());
+    }
+}
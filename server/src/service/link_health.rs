@@ -0,0 +1,357 @@
+use crate::config::LinkHealthSettings;
+use crate::graphql::{LinkHealth, LinkState};
+use crate::repository::{delete_orphaned_link_health, select_link_targets, store_link_health};
+use crate::service::public_address::HostGuard;
+use chrono::Utc;
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+use reqwest::Method;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use surrealdb::engine::any::Any;
+use surrealdb::{Error, Surreal};
+use tokio::sync::Semaphore;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{interval, sleep_until, timeout, Instant};
+use tracing::log::{log, Level};
+use url::Url;
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"), " link checker");
+
+const REDIRECTS: [u16; 5] = [301, 302, 303, 307, 308];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Probe {
+    Head,
+    Get,
+}
+
+/// Answer to one request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    pub status: u16,
+    /// The `Location` header of a redirect
+    pub location: Option<String>,
+}
+
+/// Sends one request and does not follow redirects, the checker follows
+/// them itself to record where they lead. Pluggable, so the checker can be
+/// tested without the internet.
+pub trait LinkFetcher: Send + Sync + 'static {
+    fn fetch(&self, probe: Probe, url: &Url) -> impl Future<Output = Result<Fetched, String>> + Send;
+}
+
+/// Requests public hosts only, see `HostGuard`
+pub struct ReqwestFetcher {
+    client: reqwest::Client,
+    hosts: HostGuard,
+}
+
+impl ReqwestFetcher {
+    pub fn new(settings: &LinkHealthSettings) -> Result<Self, reqwest::Error> {
+        let hosts = HostGuard { allow_private: settings.allow_private_addresses };
+        let client = hosts.client()
+            .redirect(Policy::none())
+            .user_agent(USER_AGENT)
+            .build()?;
+        Ok(ReqwestFetcher { client, hosts })
+    }
+}
+
+impl LinkFetcher for ReqwestFetcher {
+    async fn fetch(&self, probe: Probe, url: &Url) -> Result<Fetched, String> {
+        let method = match probe {
+            Probe::Head => Method::HEAD,
+            Probe::Get => Method::GET,
+        };
+        self.hosts.check(url)?;
+        let response = self.client.request(method, url.clone()).send().await.map_err(|e| e.to_string())?;
+
+        Ok(Fetched {
+            status: response.status().as_u16(),
+            location: response.headers().get(LOCATION).and_then(|value| value.to_str().ok()).map(str::to_string),
+        })
+    }
+}
+
+/// Spaces the requests to the same host by `delay`
+struct HostThrottle {
+    delay: Duration,
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostThrottle {
+    async fn wait(&self, host: &str) {
+        let start = {
+            let mut next = self.next.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let slot = next.entry(host.to_string()).or_insert(now);
+            let start = (*slot).max(now);
+            *slot = start + self.delay;
+            start
+        };
+        sleep_until(start).await;
+    }
+}
+
+/// Requests so far for one url
+struct Trace {
+    status: Option<u16>,
+    final_url: String,
+    redirects: u32,
+    latency: Duration,
+}
+
+pub struct LinkChecker<F> {
+    fetcher: F,
+    throttle: HostThrottle,
+    /// Requests in flight, taken after the throttle so waiting for one host
+    /// does not hold up the others
+    permits: Semaphore,
+    timeout: Duration,
+    max_redirects: u32,
+}
+
+impl<F: LinkFetcher> LinkChecker<F> {
+    pub fn new(fetcher: F, settings: &LinkHealthSettings) -> Self {
+        LinkChecker {
+            fetcher,
+            throttle: HostThrottle { delay: Duration::from_millis(settings.per_host_delay_ms), next: Mutex::new(HashMap::new()) },
+            permits: Semaphore::new(settings.concurrency),
+            timeout: Duration::from_secs(settings.timeout_seconds),
+            max_redirects: settings.max_redirects,
+        }
+    }
+
+    pub async fn check(&self, url: &str) -> LinkHealth {
+        let mut trace = Trace { status: None, final_url: url.to_string(), redirects: 0, latency: Duration::ZERO };
+        let error = self.follow(url, &mut trace).await.err();
+
+        let state = match trace.status {
+            Some(200..=299) if error.is_none() && trace.redirects > 0 => LinkState::Redirected,
+            Some(200..=299) if error.is_none() => LinkState::Healthy,
+            _ => LinkState::Broken,
+        };
+        LinkHealth {
+            state,
+            status: trace.status.map(i32::from),
+            final_url: trace.final_url,
+            redirects: trace.redirects as i32,
+            latency_ms: i32::try_from(trace.latency.as_millis()).unwrap_or(i32::MAX),
+            error,
+            checked_at: Utc::now(),
+        }
+    }
+
+    async fn follow(&self, url: &str, trace: &mut Trace) -> Result<(), String> {
+        let mut current = Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+        loop {
+            trace.final_url = current.to_string();
+            let fetched = self.request(&current, trace).await?;
+            trace.status = Some(fetched.status);
+
+            if !REDIRECTS.contains(&fetched.status) {
+                return Ok(());
+            }
+            if trace.redirects == self.max_redirects {
+                return Err(format!("more than {} redirects", self.max_redirects));
+            }
+            let location = fetched.location.ok_or("redirect without location".to_string())?;
+            current = current.join(&location).map_err(|e| format!("invalid redirect to '{}': {}", location, e))?;
+            trace.redirects += 1;
+        }
+    }
+
+    /// HEAD, and GET when a server rejects HEAD
+    async fn request(&self, url: &Url, trace: &mut Trace) -> Result<Fetched, String> {
+        let head = self.send(Probe::Head, url, trace).await?;
+        if head.status < 400 {
+            return Ok(head);
+        }
+        self.send(Probe::Get, url, trace).await
+    }
+
+    async fn send(&self, probe: Probe, url: &Url, trace: &mut Trace) -> Result<Fetched, String> {
+        self.throttle.wait(url.host_str().unwrap_or_default()).await;
+        let _permit = self.permits.acquire().await.map_err(|e| e.to_string())?;
+
+        let started = Instant::now();
+        let fetched = timeout(self.timeout, self.fetcher.fetch(probe, url)).await;
+        trace.latency += started.elapsed();
+
+        fetched.map_err(|_| format!("no answer within {}s", self.timeout.as_secs()))?
+    }
+}
+
+/// Checks every row outside the trash and stores the results, the checker
+/// limits the requests in flight. Returns the number of checked rows.
+pub async fn check_links<F: LinkFetcher>(client: &Surreal<Any>, checker: Arc<LinkChecker<F>>) -> Result<usize, Error> {
+    delete_orphaned_link_health(client).await?;
+    let targets = select_link_targets(client).await?;
+
+    let mut checks = JoinSet::new();
+    for target in targets {
+        let checker = checker.clone();
+        checks.spawn(async move {
+            let health = checker.check(&target.url).await;
+            (target.uri_uuid, health)
+        });
+    }
+
+    let (mut checked, mut broken) = (0, 0);
+    while let Some(joined) = checks.join_next().await {
+        let Ok((uri_uuid, health)) = joined else {
+            continue;
+        };
+        if health.state == LinkState::Broken {
+            broken += 1;
+        }
+        store_link_health(client, uri_uuid, health).await?;
+        checked += 1;
+    }
+
+    log!(Level::Info, "Checked {} urls, {} broken", checked, broken);
+    Ok(checked)
+}
+
+/// Runs `check_links` on start and then every `interval_minutes`
+pub fn spawn_link_health_job(client: Surreal<Any>, settings: LinkHealthSettings) -> Result<JoinHandle<()>, reqwest::Error> {
+    let checker = Arc::new(LinkChecker::new(ReqwestFetcher::new(&settings)?, &settings));
+
+    Ok(tokio::spawn(async move {
+        let mut ticks = interval(Duration::from_secs(settings.interval_minutes * 60));
+        loop {
+            ticks.tick().await;
+            if let Err(e) = check_links(&client, checker.clone()).await {
+                log!(Level::Warn, "Checking the links failed: {}", e);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_links, Fetched, LinkChecker, LinkFetcher, Probe, ReqwestFetcher};
+    use crate::config::{connect_test_db, LinkHealthSettings};
+    use crate::graphql::testing::meetup_url;
+    use crate::graphql::{LinkState, MeetupUrlFilter};
+    use crate::repository::{delete_by_uri_uuid, insert_init_meetup_url, select_url_with_count};
+    use axum::http::header::LOCATION;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::time::timeout;
+    use url::Url;
+
+    fn settings() -> LinkHealthSettings {
+        LinkHealthSettings {
+            enabled: true,
+            interval_minutes: 60,
+            concurrency: 4,
+            per_host_delay_ms: 0,
+            timeout_seconds: 1,
+            max_redirects: 2,
+            allow_private_addresses: true,
+        }
+    }
+
+    /// Base url of a local server answering like the sites we link to
+    async fn mock_server() -> String {
+        let app = Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/old", get(|| async { (StatusCode::MOVED_PERMANENTLY, [(LOCATION, "/ok")]) }))
+            .route("/loop", get(|| async { (StatusCode::FOUND, [(LOCATION, "/loop")]) }))
+            .route("/gone", get(|| async { StatusCode::NOT_FOUND }))
+            .route("/get-only", get(|| async { "ok" }).head(|| async { StatusCode::METHOD_NOT_ALLOWED }))
+            .route("/slow", get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "late"
+            }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        base
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let base = mock_server().await;
+        let checker = LinkChecker::new(ReqwestFetcher::new(&settings()).unwrap(), &settings());
+
+        let ok = checker.check(&format!("{base}/ok")).await;
+        assert_eq!((ok.state, ok.status, ok.redirects, ok.error), (LinkState::Healthy, Some(200), 0, None));
+
+        let old = checker.check(&format!("{base}/old")).await;
+        assert_eq!((old.state, old.status, old.redirects), (LinkState::Redirected, Some(200), 1));
+        assert_eq!(old.final_url, format!("{base}/ok"));
+
+        let head_rejected = checker.check(&format!("{base}/get-only")).await;
+        assert_eq!((head_rejected.state, head_rejected.status), (LinkState::Healthy, Some(200)));
+
+        let gone = checker.check(&format!("{base}/gone")).await;
+        assert_eq!((gone.state, gone.status, gone.error), (LinkState::Broken, Some(404), None));
+
+        let endless = checker.check(&format!("{base}/loop")).await;
+        assert_eq!((endless.state, endless.status, endless.redirects), (LinkState::Broken, Some(302), 2));
+        assert_eq!(endless.error.as_deref(), Some("more than 2 redirects"));
+
+        let slow = checker.check(&format!("{base}/slow")).await;
+        assert_eq!((slow.state, slow.status, slow.error.as_deref()), (LinkState::Broken, None, Some("no answer within 1s")));
+    }
+
+    #[tokio::test]
+    async fn test_check_links() {
+        let base = mock_server().await;
+        let client = connect_test_db().await;
+        for (uri_uuid, path) in [("a", "/ok"), ("b", "/old"), ("c", "/gone"), ("d", "/get-only")] {
+            let row = crate::model::MeetupUrl { url: format!("{base}{path}"), ..meetup_url(uri_uuid, "127.0.0.1", "Mock") };
+            insert_init_meetup_url(row, &client).await.unwrap();
+        }
+        delete_by_uri_uuid(&client, "d".to_string(), "curator").await.unwrap();
+
+        let checker = Arc::new(LinkChecker::new(ReqwestFetcher::new(&settings()).unwrap(), &settings()));
+        assert_eq!(check_links(&client, checker).await.unwrap(), 3);
+
+        let state = |state| MeetupUrlFilter { link_state: Some(state), include_deleted: Some(true), ..MeetupUrlFilter::default() };
+        let (broken, _) = select_url_with_count(&client, state(LinkState::Broken)).await.unwrap();
+        assert_eq!(broken.iter().map(|u| u.uri_uuid.as_str()).collect::<Vec<&str>>(), vec!["c"]);
+        assert_eq!(broken[0].live_status, "BROKEN");
+        let (redirected, _) = select_url_with_count(&client, state(LinkState::Redirected)).await.unwrap();
+        assert_eq!(redirected[0].link_health.as_ref().unwrap().final_url, format!("{base}/ok"));
+
+        // Rows in the trash are not checked
+        let (all, _) = select_url_with_count(&client, MeetupUrlFilter { include_deleted: Some(true), ..MeetupUrlFilter::default() }).await.unwrap();
+        let unchecked = all.iter().filter(|u| u.link_health.is_none()).map(|u| u.uri_uuid.as_str()).collect::<Vec<&str>>();
+        assert_eq!(unchecked, vec!["d"]);
+    }
+
+    /// Answers every request at once
+    struct Answering;
+
+    impl LinkFetcher for Answering {
+        async fn fetch(&self, _probe: Probe, _url: &Url) -> Result<Fetched, String> {
+            Ok(Fetched { status: 200, location: None })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_throttled_host_does_not_hold_up_others() {
+        let settings = LinkHealthSettings { concurrency: 1, per_host_delay_ms: 60_000, ..settings() };
+        let checker = Arc::new(LinkChecker::new(Answering, &settings));
+        checker.check("https://a.example/1").await;
+
+        let waiting = tokio::spawn({
+            let checker = checker.clone();
+            async move { checker.check("https://a.example/2").await }
+        });
+        tokio::task::yield_now().await;
+
+        assert!(timeout(Duration::from_secs(1), checker.check("https://b.example/")).await.is_ok());
+        waiting.abort();
+    }
+}
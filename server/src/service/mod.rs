@@ -1,5 +1,19 @@
+mod bookmark_import;
+mod csv_import;
+mod data_import;
+mod duplicates;
+mod export;
 mod file_import;
-mod init_database;
+mod link_health;
+mod opml_import;
+mod page_metadata;
+mod public_address;
+mod trash_purge;
 
-use file_import::import_data;
-pub use init_database::init_database;
+pub use duplicates::duplicate_candidates;
+pub use export::{export, export_stream};
+use file_import::{import_data, read_rows};
+pub use data_import::{import_content, import_file, ImportError, ImportOptions};
+pub use link_health::spawn_link_health_job;
+pub use page_metadata::{refresh_metadata, spawn_metadata_refresh, PageFetcher};
+pub use trash_purge::spawn_purge_job;
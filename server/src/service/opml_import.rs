@@ -0,0 +1,79 @@
+use crate::graphql::ImportFormat;
+use crate::model::{decode_html, tag_attributes};
+use crate::service::file_import::{parse_time, ImportRows, Importer, Link};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// `<opml>` as the first element, after the XML declaration and comments
+static ROOT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)^\s*(?:<\?xml.*?\?>\s*)?(?:<!--.*?-->\s*)*<opml\b").unwrap());
+static OUTLINE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<outline\b([^>]*)>").unwrap());
+
+/// Outline files as written by feed readers and link collections. An
+/// outline is a link by its `url`, else its `htmlUrl` or `xmlUrl`, outlines
+/// without any only group others.
+pub struct OpmlImporter;
+
+impl Importer for OpmlImporter {
+    fn format(&self) -> ImportFormat {
+        ImportFormat::Opml
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        ROOT.is_match(content)
+    }
+
+    fn read(&self, content: &str, user: &str) -> Result<ImportRows, String> {
+        Ok(OUTLINE.captures_iter(content)
+            .filter_map(|outline| {
+                let attributes = tag_attributes(&outline[1]);
+                let get = |names: &[&str]| names.iter().find_map(|name| attributes.get(*name))
+                    .map(|value| decode_html(value).trim().to_string())
+                    .filter(|value| !value.is_empty());
+
+                let url = get(&["url", "htmlurl", "xmlurl"])?;
+                Some(get(&["created"]).map(|created| parse_time(&created)).transpose().and_then(|created| {
+                    Link {
+                        url,
+                        title: get(&["title", "text"]),
+                        description: get(&["description"]),
+                        note: None,
+                        created,
+                        modified: None,
+                    }.row(user)
+                }))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpmlImporter;
+    use crate::service::file_import::Importer;
+
+    #[test]
+    fn test_read() {
+        let opml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <opml version="2.0">
+              <head><title>Rust feeds</title></head>
+              <body>
+                <outline text="Blogs">
+                  <outline type="rss" text="Inside Rust" xmlUrl="https://blog.rust-lang.org/inside-rust/feed.xml"
+                    htmlUrl="https://blog.rust-lang.org/inside-rust/" description="Team &amp; project news"/>
+                  <outline type="link" text="Rust Berlin" url="https://berline.rs/" created="Mon, 04 Mar 2024 19:00:00 +0100"/>
+                  <outline text="Broken" url="ftp://example.org/"/>
+                </outline>
+              </body>
+            </opml>"#;
+        let rows = OpmlImporter.read(opml, "curator").unwrap();
+        assert_eq!(rows.len(), 3);
+
+        let inside = &rows[0].as_ref().unwrap().row;
+        assert_eq!((inside.url.as_str(), inside.title.as_str()), ("https://blog.rust-lang.org/inside-rust", "Inside Rust"));
+        assert_eq!(inside.auto_descr, "Team & project news");
+
+        let berlin = &rows[1].as_ref().unwrap().row;
+        assert_eq!(berlin.crea_time.to_string(), "d'2024-03-04T18:00:00Z'");
+        assert!(rows[2].is_err());
+    }
+}
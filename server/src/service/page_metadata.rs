@@ -0,0 +1,96 @@
+use crate::config::MetadataSettings;
+use crate::graphql::{ApiError, MeetupUrl, MeetupUrlChangeKind, ServerContext};
+use crate::model::HtmlMetadata;
+use crate::repository::{find_live_url, store_metadata, Patched};
+use crate::service::public_address::HostGuard;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use std::time::Duration;
+use surrealdb::engine::any::Any;
+use surrealdb::Surreal;
+use tracing::log::{log, Level};
+use url::Url;
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"), " metadata reader");
+
+/// Reads pages for their metadata, following redirects to public hosts
+#[derive(Debug, Clone)]
+pub struct PageFetcher {
+    client: reqwest::Client,
+    hosts: HostGuard,
+    max_bytes: usize,
+}
+
+impl PageFetcher {
+    pub fn new(settings: &MetadataSettings) -> Result<Self, reqwest::Error> {
+        let hosts = HostGuard { allow_private: settings.allow_private_addresses };
+        let max_redirects = settings.max_redirects;
+        let client = hosts.client()
+            .redirect(Policy::custom(move |attempt| {
+                if attempt.previous().len() >= max_redirects {
+                    return attempt.error(format!("more than {} redirects", max_redirects));
+                }
+                match hosts.check(attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(e) => attempt.error(e),
+                }
+            }))
+            .timeout(Duration::from_secs(settings.timeout_seconds))
+            .user_agent(USER_AGENT)
+            .build()?;
+        Ok(PageFetcher { client, hosts, max_bytes: settings.max_page_kb * 1024 })
+    }
+
+    /// Where the redirects led and the start of the page, HTML only
+    async fn fetch(&self, url: &str) -> Result<(Url, String), String> {
+        let url = Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+        self.hosts.check(&url)?;
+        let mut response = self.client.get(url).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("answered {}", response.status()));
+        }
+        let content_type = response.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).unwrap_or_default();
+        if !content_type.to_ascii_lowercase().contains("html") {
+            return Err(format!("not an HTML page but '{}'", content_type));
+        }
+
+        let page = response.url().clone();
+        let mut body = Vec::new();
+        while body.len() < self.max_bytes
+            && let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())?
+        {
+            body.extend_from_slice(&chunk);
+        }
+        body.truncate(self.max_bytes);
+
+        Ok((page, String::from_utf8_lossy(&body).into_owned()))
+    }
+}
+
+/// Reads the page of the row and stores its metadata, see `store_metadata`.
+/// `Conflict` when the row was changed while the page was read.
+pub async fn refresh_metadata(client: &Surreal<Any>, pages: &PageFetcher, uri_uuid: String, user: &str) -> Result<MeetupUrl, ApiError> {
+    let (url, modi_time) = find_live_url(client, uri_uuid.clone()).await?
+        .ok_or(ApiError::NotFound(uri_uuid.clone()))?;
+
+    let (page, html) = pages.fetch(&url).await.map_err(ApiError::Unreadable)?;
+    let metadata = HtmlMetadata::extract(&html, &page);
+    log!(Level::Info, "Metadata of {}: {:?}", url, metadata);
+
+    match store_metadata(client, uri_uuid.clone(), modi_time, metadata, user).await? {
+        Patched::Updated(updated) => Ok(*updated),
+        Patched::NotFound => Err(ApiError::NotFound(uri_uuid)),
+        Patched::Conflict => Err(ApiError::Conflict(uri_uuid)),
+    }
+}
+
+/// `refresh_metadata` for a row just inserted by `user`, in the
+/// background. On failure the row keeps what was entered.
+pub fn spawn_metadata_refresh(context: ServerContext, uri_uuid: String, user: String) {
+    tokio::spawn(async move {
+        match refresh_metadata(&context.db, &context.pages, uri_uuid.clone(), &user).await {
+            Ok(updated) => context.publish(MeetupUrlChangeKind::Updated, updated),
+            Err(e) => log!(Level::Info, "No metadata for {}: {:?}", uri_uuid, e),
+        }
+    });
+}
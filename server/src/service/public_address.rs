@@ -0,0 +1,136 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::ClientBuilder;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::net::lookup_host;
+use url::{Host, Url};
+
+/// Which hosts the server requests when reading stored urls. Public ones
+/// only, unless `allow_private`, so no url makes the server reach into its
+/// own network or the metadata service of its cloud.
+#[derive(Debug, Clone, Copy)]
+pub struct HostGuard {
+    pub allow_private: bool,
+}
+
+impl HostGuard {
+    /// A client builder resolving names to public addresses only. Urls
+    /// naming an address skip resolving, `check` them before each request
+    /// and redirect.
+    pub fn client(&self) -> ClientBuilder {
+        let builder = reqwest::Client::builder();
+        if self.allow_private {
+            return builder;
+        }
+        builder.dns_resolver(PublicResolver)
+    }
+
+    /// Refuses `localhost` and urls naming a non-public address
+    pub fn check(&self, url: &Url) -> Result<(), String> {
+        if self.allow_private {
+            return Ok(());
+        }
+        let public = match url.host() {
+            Some(Host::Domain(domain)) => {
+                let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+                domain != "localhost" && !domain.ends_with(".localhost")
+            }
+            Some(Host::Ipv4(ip)) => is_public(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => is_public(IpAddr::V6(ip)),
+            None => false,
+        };
+        if !public {
+            return Err(format!("{} is not a public host", url.host_str().unwrap_or_default()));
+        }
+        Ok(())
+    }
+}
+
+/// Resolves names to their public addresses, names with none fail
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = lookup_host((name.as_str(), 0)).await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Reachable from the internet: none of loopback, private, link-local,
+/// shared, documentation, multicast or reserved ranges
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(a == 0
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || (a == 100 && (64..128).contains(&b))
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        || ip.is_multicast())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_public, HostGuard};
+    use std::net::IpAddr;
+    use url::Url;
+
+    #[test]
+    fn test_is_public() {
+        for ip in ["93.184.215.14", "2606:4700::6810:84e5", "::ffff:1.1.1.1"] {
+            assert!(is_public(ip.parse::<IpAddr>().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "172.31.255.255", "192.168.1.1", "169.254.169.254",
+            "100.64.0.1", "0.0.0.0", "255.255.255.255", "224.0.0.1",
+            "::1", "::", "fd00::1", "fe80::1", "::ffff:127.0.0.1", "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public(ip.parse::<IpAddr>().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guard() {
+        let guard = HostGuard { allow_private: false };
+        let check = |url: &str| guard.check(&Url::parse(url).unwrap());
+
+        assert!(check("https://berline.rs/").is_ok());
+        for url in ["http://localhost:8080/", "http://LOCALHOST./", "http://db.localhost/", "http://127.1/", "http://[::1]/", "http://169.254.169.254/latest/meta-data/"] {
+            assert!(check(url).is_err(), "{}", url);
+        }
+        assert!(HostGuard { allow_private: true }.check(&Url::parse("http://127.0.0.1/").unwrap()).is_ok());
+
+        // Names of private addresses fail to resolve
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = guard.client().build().unwrap();
+        let error = client.get(format!("http://localhost:{}/", port)).send().await.unwrap_err();
+        assert!(error.is_connect(), "{:?}", error);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::config::TrashSettings;
+use crate::repository::purge_deleted;
+use chrono::{Duration, Utc};
+use surrealdb::engine::any::Any;
+use surrealdb::sql::Datetime;
+use surrealdb::{Error, Surreal};
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use tracing::log::{log, Level};
+
+/// Actor of the purge entries in the history
+const PURGE_ACTOR: &str = "trash-purge";
+
+/// Removes the rows deleted more than `retention_days` ago, returns their `uri_uuid`s
+pub async fn purge_trash(client: &Surreal<Any>, retention_days: u32) -> Result<Vec<String>, Error> {
+    let deleted_before = Utc::now() - Duration::days(i64::from(retention_days));
+    let purged = purge_deleted(client, Datetime::from(deleted_before), PURGE_ACTOR).await?;
+
+    if !purged.is_empty() {
+        log!(Level::Info, "Purged {} urls deleted before {}", purged.len(), deleted_before);
+    }
+    Ok(purged)
+}
+
+/// Runs `purge_trash` on start and then every `purge_interval_minutes`
+pub fn spawn_purge_job(client: Surreal<Any>, settings: TrashSettings) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = interval(std::time::Duration::from_secs(settings.purge_interval_minutes * 60));
+        loop {
+            ticks.tick().await;
+            if let Err(e) = purge_trash(&client, settings.retention_days).await {
+                log!(Level::Warn, "Purging the trash failed: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::purge_trash;
+    use crate::config::connect_test_db;
+    use crate::graphql::testing::meetup_url;
+    use crate::graphql::HistoryOperation;
+    use crate::repository::{delete_by_uri_uuid, insert_init_meetup_url, select_history};
+
+    #[tokio::test]
+    async fn test_purge_trash() {
+        let client = connect_test_db().await;
+        for (uri_uuid, host) in [("a", "actix.rs"), ("b", "leptos.dev"), ("c", "tokio.rs")] {
+            insert_init_meetup_url(meetup_url(uri_uuid, host, "Title"), &client).await.unwrap();
+        }
+        delete_by_uri_uuid(&client, "a".to_string(), "curator").await.unwrap();
+        delete_by_uri_uuid(&client, "b".to_string(), "curator").await.unwrap();
+        client.query("UPDATE url SET deleted_at = time::now() - 31d WHERE uri_uuid = 'a'")
+            .await.unwrap().check().unwrap();
+
+        assert_eq!(purge_trash(&client, 30).await.unwrap(), vec!["a"]);
+        assert!(purge_trash(&client, 30).await.unwrap().is_empty());
+
+        let left: Vec<String> = client.query("SELECT VALUE uri_uuid FROM url ORDER BY uri_uuid").await.unwrap().take(0).unwrap();
+        assert_eq!(left, vec!["b", "c"]);
+
+        let history = select_history(&client, "a".to_string()).await.unwrap();
+        let last = history.last().unwrap();
+        assert_eq!((last.operation, last.actor.as_str()), (HistoryOperation::Purge, "trash-purge"));
+    }
+}